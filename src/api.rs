@@ -0,0 +1,354 @@
+//! Additional crates.io API endpoints not covered by the `consecrates`
+//! client.
+
+use chrono::{DateTime, Utc};
+use consecrates::api::Crates;
+use serde::Deserialize;
+
+use anyhow::{Error, Result};
+
+use crate::http;
+use crate::query::{Query, MAX_PER_PAGE};
+
+/// Base url of the crates.io API.
+pub const BASE_URL: &str = "https://crates.io/api/v1/";
+
+/// Single published version of a crate.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Version {
+    pub id: u64,
+    pub num: String,
+    pub created_at: DateTime<Utc>,
+    pub downloads: u64,
+    #[serde(default)]
+    pub yanked: bool,
+    /// Minimum supported Rust version declared by the crate
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    /// User who published the version, unknown for the oldest versions
+    #[serde(default)]
+    pub published_by: Option<Publisher>,
+    /// Size of the packaged crate in bytes
+    #[serde(default)]
+    pub crate_size: Option<u64>,
+    #[serde(default)]
+    pub license: Option<String>,
+}
+
+/// User who published a version.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Publisher {
+    pub login: String,
+}
+
+#[derive(Deserialize)]
+struct VersionResponse {
+    version: Version,
+}
+
+/// Gets a single published version of the given crate.
+pub(crate) fn get_crate_version(crate_id: &str, num: &str) -> Result<Version> {
+    let response: VersionResponse =
+        http::get_json(&format!("{}crates/{}/{}", BASE_URL, crate_id, num), &[])?;
+    Ok(response.version)
+}
+
+/// Dependency declared by a published version of a crate.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Dependency {
+    pub crate_id: String,
+    pub req: String,
+    /// Either `normal`, `dev` or `build`
+    pub kind: String,
+    pub optional: bool,
+    pub default_features: bool,
+    #[serde(default)]
+    pub features: Vec<String>,
+    pub target: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DependenciesResponse {
+    dependencies: Vec<Dependency>,
+}
+
+/// Gets the dependencies of the given crate version.
+pub(crate) fn get_dependencies(crate_id: &str, num: &str) -> Result<Vec<Dependency>> {
+    let response: DependenciesResponse = http::get_json(
+        &format!("{}crates/{}/{}/dependencies", BASE_URL, crate_id, num),
+        &[],
+    )?;
+    Ok(response.dependencies)
+}
+
+#[derive(Deserialize)]
+struct TotalMeta {
+    total: u64,
+}
+
+#[derive(Deserialize)]
+struct ReverseDependenciesCount {
+    meta: TotalMeta,
+}
+
+/// Gets the number of crates depending on the given crate.
+pub(crate) fn get_reverse_dependency_count(crate_id: &str) -> Result<u64> {
+    let response: ReverseDependenciesCount = http::get_json(
+        &format!(
+            "{}crates/{}/reverse_dependencies?per_page=1",
+            BASE_URL, crate_id
+        ),
+        &[],
+    )?;
+    Ok(response.meta.total)
+}
+
+/// Crate depending on another crate.
+#[derive(Debug, Clone)]
+pub(crate) struct ReverseDependency {
+    /// Name of the dependent crate
+    pub name: String,
+    /// Latest version of the dependent crate
+    pub version: String,
+    /// Requirement the dependent puts on the crate
+    pub req: String,
+    /// Either `normal`, `dev` or `build`
+    pub kind: String,
+    pub optional: bool,
+    /// All-time downloads of the dependent crate
+    pub downloads: u64,
+}
+
+#[derive(Deserialize)]
+struct ReverseDependencyEntry {
+    version_id: u64,
+    req: String,
+    kind: String,
+    optional: bool,
+    downloads: u64,
+}
+
+#[derive(Deserialize)]
+struct ReverseDependencyVersion {
+    id: u64,
+    #[serde(rename = "crate")]
+    crate_name: String,
+    num: String,
+}
+
+#[derive(Deserialize)]
+struct ReverseDependenciesResponse {
+    dependencies: Vec<ReverseDependencyEntry>,
+    versions: Vec<ReverseDependencyVersion>,
+    meta: TotalMeta,
+}
+
+/// Gets a page of the crates depending on the given crate, most downloaded
+/// first, along with the total number of them.
+pub(crate) fn get_reverse_dependencies(
+    crate_id: &str,
+    page: usize,
+) -> Result<(Vec<ReverseDependency>, u64)> {
+    let response: ReverseDependenciesResponse = http::get_json(
+        &format!(
+            "{}crates/{}/reverse_dependencies?page={}&per_page={}",
+            BASE_URL, crate_id, page, MAX_PER_PAGE
+        ),
+        &[],
+    )?;
+    Ok(join_reverse_dependencies(response))
+}
+
+/// Names the dependencies of the response after the versions they belong to.
+fn join_reverse_dependencies(
+    response: ReverseDependenciesResponse,
+) -> (Vec<ReverseDependency>, u64) {
+    // dependents are only named in the versions depending on the crate
+    let versions = response.versions;
+    let dependents = response
+        .dependencies
+        .into_iter()
+        .filter_map(|dep| {
+            let version = versions.iter().find(|v| v.id == dep.version_id)?;
+            Some(ReverseDependency {
+                name: version.crate_name.clone(),
+                version: version.num.clone(),
+                req: dep.req,
+                kind: dep.kind,
+                optional: dep.optional,
+                downloads: dep.downloads,
+            })
+        })
+        .collect();
+    (dependents, response.meta.total)
+}
+
+#[derive(Deserialize)]
+struct VersionsResponse {
+    versions: Vec<Version>,
+    meta: Option<VersionsMeta>,
+}
+
+#[derive(Deserialize)]
+struct VersionsMeta {
+    next_page: Option<String>,
+}
+
+/// Gets all the published versions of the given crate, newest first.
+pub(crate) fn get_crate_versions(crate_id: &str) -> Result<Vec<Version>> {
+    let mut versions = Vec::new();
+    let mut url = format!("{}crates/{}/versions", BASE_URL, crate_id);
+    loop {
+        let response: VersionsResponse = http::get_json(&url, &[])?;
+        versions.extend(response.versions);
+        match response.meta.and_then(|m| m.next_page) {
+            Some(next_page) => {
+                url = format!("{}crates/{}/versions{}", BASE_URL, crate_id, next_page)
+            }
+            None => break,
+        }
+    }
+    Ok(versions)
+}
+
+#[derive(Deserialize)]
+struct Owner {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct UserResponse {
+    user: Owner,
+}
+
+#[derive(Deserialize)]
+struct TeamResponse {
+    team: Owner,
+}
+
+/// Searches crates using the parsed query. Only the valid terms of the
+/// query are used.
+pub(crate) fn search_crates(query: &Query, page: usize) -> Result<Crates> {
+    let mut params = vec![("page", page.to_string())];
+    if let Some(per_page) = query.per_page() {
+        params.push(("per_page", per_page.to_string()));
+    }
+    let text = query.text();
+    if !text.is_empty() {
+        params.push(("q", text));
+    }
+    if let Some(category) = query.category() {
+        params.push(("category", category));
+    }
+    if let Some(keyword) = query.keyword() {
+        params.push(("keyword", keyword));
+    }
+    if let Some(sort) = query.sort() {
+        params.push(("sort", sort.to_str().to_string()));
+    }
+    // owners are filtered by their ids
+    if let Some(user) = query.user() {
+        let response: UserResponse = http::get_json(&format!("{}users/{}", BASE_URL, user), &[])
+            .map_err(|_| Error::msg(format!("user not found: {}", user)))?;
+        params.push(("user_id", response.user.id.to_string()));
+    }
+    if let Some(team) = query.team() {
+        let response: TeamResponse = http::get_json(&format!("{}teams/{}", BASE_URL, team), &[])
+            .map_err(|_| Error::msg(format!("team not found: {}", team)))?;
+        params.push(("team_id", response.team.id.to_string()));
+    }
+
+    let params: Vec<String> = params
+        .iter()
+        .map(|(key, val)| format!("{}={}", key, http::encode(val)))
+        .collect();
+    http::get_json(&format!("{}crates?{}", BASE_URL, params.join("&")), &[])
+}
+
+#[derive(Deserialize)]
+struct CategorySlug {
+    slug: String,
+}
+
+#[derive(Deserialize)]
+struct CategorySlugsResponse {
+    category_slugs: Vec<CategorySlug>,
+}
+
+/// Gets the slugs of all the categories, including subcategories.
+pub(crate) fn get_category_slugs() -> Result<Vec<String>> {
+    let response: CategorySlugsResponse =
+        http::get_json(&format!("{}category_slugs", BASE_URL), &[])?;
+    Ok(response
+        .category_slugs
+        .into_iter()
+        .map(|c| c.slug)
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct Keyword {
+    keyword: String,
+}
+
+#[derive(Deserialize)]
+struct KeywordsResponse {
+    keywords: Vec<Keyword>,
+}
+
+/// Gets the given number of pages of keywords, most popular first.
+pub(crate) fn get_popular_keywords(pages: usize) -> Result<Vec<String>> {
+    let mut keywords = Vec::new();
+    for page in 1..=pages {
+        let response: KeywordsResponse = http::get_json(
+            &format!(
+                "{}keywords?sort=crates&per_page={}&page={}",
+                BASE_URL, MAX_PER_PAGE, page
+            ),
+            &[],
+        )?;
+        keywords.extend(response.keywords.into_iter().map(|k| k.keyword));
+    }
+    Ok(keywords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_dependencies_named_by_version() {
+        let response: ReverseDependenciesResponse = serde_json::from_str(
+            r#"{
+                "dependencies": [
+                    {"version_id": 2, "req": "^1.0", "kind": "normal",
+                     "optional": false, "downloads": 500},
+                    {"version_id": 1, "req": "^0.9", "kind": "dev",
+                     "optional": true, "downloads": 20},
+                    {"version_id": 3, "req": "*", "kind": "build",
+                     "optional": false, "downloads": 10}
+                ],
+                "versions": [
+                    {"id": 1, "crate": "small", "num": "0.1.0"},
+                    {"id": 2, "crate": "big", "num": "2.3.4"}
+                ],
+                "meta": {"total": 42}
+            }"#,
+        )
+        .unwrap();
+        let (dependents, total) = join_reverse_dependencies(response);
+        assert_eq!(total, 42);
+        // the dependency without a version can't be named
+        assert_eq!(dependents.len(), 2);
+        let big = &dependents[0];
+        assert_eq!((big.name.as_str(), big.version.as_str()), ("big", "2.3.4"));
+        assert_eq!((big.req.as_str(), big.kind.as_str()), ("^1.0", "normal"));
+        assert_eq!((big.optional, big.downloads), (false, 500));
+        let small = &dependents[1];
+        assert_eq!(
+            (small.name.as_str(), small.version.as_str()),
+            ("small", "0.1.0")
+        );
+        assert_eq!((small.kind.as_str(), small.optional), ("dev", true));
+    }
+}
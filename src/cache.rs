@@ -0,0 +1,239 @@
+//! Persistent on-disk cache.
+//!
+//! Search results, crate data, readmes and index files are stored as files
+//! under the XDG cache directory, e.g. `~/.cache/cns`. Search results only
+//! store the ids of the crates found, the crates themselves are stored
+//! separately by id. An entry is considered fresh for the configured
+//! time-to-live counted from the moment it was written. Cache failures are
+//! never fatal, worst case the data is fetched from the network again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::dirs;
+use crate::items::Crate;
+use crate::search::Page;
+
+/// Default time-to-live of cache entries.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+const SEARCH_DIR: &str = "search";
+const CRATE_DIR: &str = "crates";
+const README_DIR: &str = "readmes";
+const INDEX_DIR: &str = "index";
+
+/// Search results entry, the crates are stored separately.
+#[derive(Serialize, Deserialize)]
+struct SearchEntry {
+    ids: Vec<String>,
+    total: u64,
+}
+
+/// Cache of data fetched from the network.
+#[derive(Debug, Clone)]
+pub(crate) struct Cache {
+    /// Cache root directory, `None` if the cache is disabled
+    dir: Option<PathBuf>,
+    ttl: Duration,
+}
+
+impl Cache {
+    /// Creates a new cache in the default location with the given
+    /// time-to-live. Zero time-to-live disables the cache.
+    pub fn new(ttl: Duration) -> Self {
+        let dir = dirs::cache_dir().filter(|_| !ttl.is_zero());
+        Cache { dir, ttl }
+    }
+
+    /// Gets the page of results found with the given query.
+    pub fn search(&self, query: &str, number: usize) -> Option<Page> {
+        let entry: SearchEntry = self.get(SEARCH_DIR, &search_key(query, number))?;
+        Some(Page {
+            crates: entry
+                .ids
+                .iter()
+                .map(|id| self.get(CRATE_DIR, id))
+                .collect::<Option<_>>()?,
+            number,
+            total: entry.total,
+            filtered: 0,
+        })
+    }
+
+    /// Stores the page of results found with the given query.
+    pub fn store_search(&self, query: &str, page: &Page) {
+        for item in &page.crates {
+            self.put(CRATE_DIR, &item.id, item);
+        }
+        let entry = SearchEntry {
+            ids: page.crates.iter().map(|c| c.id.clone()).collect(),
+            total: page.total,
+        };
+        self.put(SEARCH_DIR, &search_key(query, page.number), &entry);
+    }
+
+    /// Gets the readme of the given crate version, fetching and storing it
+    /// if it's not cached.
+    pub fn readme(
+        &self,
+        item: &Crate,
+        fetch: impl FnOnce(&Crate) -> Result<String>,
+    ) -> Result<String> {
+        let key = format!("{}-{}", item.id, item.max_version);
+        if let Some(markdown) = self.get(README_DIR, &key) {
+            return Ok(markdown);
+        }
+        let markdown = fetch(item)?;
+        self.put(README_DIR, &key, &markdown);
+        Ok(markdown)
+    }
+
+    /// Gets the sparse index file of the given crate, fetching and storing
+    /// it if it's not cached.
+    pub fn index_file(
+        &self,
+        name: &str,
+        fetch: impl FnOnce(&str) -> Result<String>,
+    ) -> Result<String> {
+        if let Some(file) = self.get(INDEX_DIR, name) {
+            return Ok(file);
+        }
+        let file = fetch(name)?;
+        self.put(INDEX_DIR, name, &file);
+        Ok(file)
+    }
+
+    /// Reads a fresh entry of the given kind.
+    fn get<T: DeserializeOwned>(&self, kind: &str, key: &str) -> Option<T> {
+        let path = self.path(kind, key)?;
+        let age = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+        if age > self.ttl {
+            return None;
+        }
+        serde_json::from_slice(&fs::read(&path).ok()?).ok()
+    }
+
+    /// Writes an entry of the given kind, ignoring any errors.
+    fn put<T: Serialize + ?Sized>(&self, kind: &str, key: &str, value: &T) {
+        if let Some(path) = self.path(kind, key) {
+            let _ = write(&path, &serde_json::to_vec(value).unwrap_or_default());
+        }
+    }
+
+    fn path(&self, kind: &str, key: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(kind).join(format!("{}.json", file_name(key))))
+    }
+}
+
+fn search_key(query: &str, page: usize) -> String {
+    format!("{}\n{}", query.trim(), page)
+}
+
+/// Writes the file through a temporary one, so that readers never see
+/// partially written entries.
+pub(crate) fn write(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Escapes the key so that it can be safely used as a file name.
+fn file_name(key: &str) -> String {
+    let mut name = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => name.push(byte as char),
+            _ => name.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::fs::File;
+
+    fn cache(name: &str) -> (Cache, PathBuf) {
+        let dir = dirs::test_dir(name);
+        let cache = Cache {
+            dir: Some(dir.clone()),
+            ttl: DEFAULT_TTL,
+        };
+        (cache, dir)
+    }
+
+    #[test]
+    fn key_layout() {
+        let (cache, dir) = cache("cache-keys");
+        assert_eq!(file_name("serde_json-1.0"), "serde_json-1%2E0");
+        assert_eq!(
+            cache.path(SEARCH_DIR, &search_key(" serde ", 2)),
+            Some(dir.join("search").join("serde%0A2.json"))
+        );
+        assert_eq!(
+            cache.path(INDEX_DIR, "../etc"),
+            Some(dir.join("index").join("%2E%2E%2Fetc.json"))
+        );
+        assert_eq!(Cache::new(Duration::ZERO).path(CRATE_DIR, "serde"), None);
+    }
+
+    #[test]
+    fn expiry() {
+        let (cache, dir) = cache("cache-expiry");
+        let fetches = Cell::new(0);
+        let fetch = |name: &str| {
+            fetches.set(fetches.get() + 1);
+            Ok(format!("{} file", name))
+        };
+        assert_eq!(cache.index_file("serde", fetch).unwrap(), "serde file");
+        assert_eq!(cache.index_file("serde", fetch).unwrap(), "serde file");
+        assert_eq!(fetches.get(), 1);
+
+        let path = dir.join("index").join("serde.json");
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - DEFAULT_TTL * 2)
+            .unwrap();
+        assert_eq!(cache.get::<String>(INDEX_DIR, "serde"), None);
+        cache.index_file("serde", fetch).unwrap();
+        assert_eq!(fetches.get(), 2);
+    }
+
+    #[test]
+    fn failed_fetches_are_not_stored() {
+        let (cache, _) = cache("cache-errors");
+        assert!(cache
+            .index_file("serde", |_| Err(anyhow::Error::msg("offline")))
+            .is_err());
+        assert_eq!(cache.get::<String>(INDEX_DIR, "serde"), None);
+    }
+
+    #[test]
+    fn atomic_write() {
+        let dir = dirs::test_dir("cache-write");
+        let path = dir.join("nested").join("entry.json");
+        write(&path, b"first").unwrap();
+        write(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        let files: Vec<_> = fs::read_dir(dir.join("nested")).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+}
@@ -0,0 +1,376 @@
+//! Compare tab showing the results side by side in a table.
+//!
+//! The columns shown can be picked from a popup, and the results can be
+//! sorted by any of the columns. Column widths follow the content, so that
+//! short values don't waste space and long ones aren't cut off needlessly.
+
+use std::ops::Sub;
+
+use chrono::{DateTime, Utc};
+use tui::layout::Constraint;
+use tui::style::{Modifier, Style};
+use tui::widgets::{Cell, Row, TableState};
+
+use crate::items::{Crate, Remote, SortKey};
+use crate::workspace::Workspace;
+
+/// Maximum width of a column, longer values are cut off.
+const MAX_COLUMN_WIDTH: usize = 32;
+
+/// Column of the compare table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Column {
+    Name,
+    Created,
+    Updated,
+    Downloads,
+    RecentDownloads,
+    MaxVersion,
+    RepoHost,
+    License,
+    Categories,
+    ReverseDependencies,
+    Dependencies,
+    Msrv,
+}
+
+impl Column {
+    /// All the columns, in the order they're listed in the picker.
+    pub const ALL: [Column; 12] = [
+        Column::Name,
+        Column::Created,
+        Column::Updated,
+        Column::Downloads,
+        Column::RecentDownloads,
+        Column::MaxVersion,
+        Column::RepoHost,
+        Column::License,
+        Column::Categories,
+        Column::ReverseDependencies,
+        Column::Dependencies,
+        Column::Msrv,
+    ];
+
+    /// Columns shown by default.
+    pub const DEFAULT: [Column; 7] = [
+        Column::Name,
+        Column::Created,
+        Column::Updated,
+        Column::Downloads,
+        Column::RecentDownloads,
+        Column::MaxVersion,
+        Column::RepoHost,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Created => "Since creation",
+            Column::Updated => "Since update",
+            Column::Downloads => "All-time dl",
+            Column::RecentDownloads => "Recent dl",
+            Column::MaxVersion => "Max version",
+            Column::RepoHost => "Repo host",
+            Column::License => "License",
+            Column::Categories => "Categories",
+            Column::ReverseDependencies => "Reverse deps",
+            Column::Dependencies => "Deps",
+            Column::Msrv => "MSRV",
+        }
+    }
+
+    /// Returns the key the results are sorted by when sorting by the
+    /// column.
+    pub fn sort_key(self) -> SortKey {
+        match self {
+            Column::Name => SortKey::Name,
+            Column::Created => SortKey::Created,
+            Column::Updated => SortKey::Updated,
+            Column::Downloads => SortKey::Downloads,
+            Column::RecentDownloads => SortKey::RecentDownloads,
+            Column::MaxVersion => SortKey::Version,
+            Column::RepoHost => SortKey::RepoHost,
+            Column::License => SortKey::License,
+            Column::Categories => SortKey::Categories,
+            Column::ReverseDependencies => SortKey::ReverseDependencies,
+            Column::Dependencies => SortKey::Dependencies,
+            Column::Msrv => SortKey::Msrv,
+        }
+    }
+
+    /// Formats the value of the column for the given crate. The max version
+    /// of a crate the project depends on is followed by the locked one.
+    pub fn cell(self, item: &Crate, now: DateTime<Utc>, workspace: Option<&Workspace>) -> String {
+        let na = || "n/a".to_string();
+        match self {
+            Column::Name => item.name.clone(),
+            Column::Created => now.sub(item.created_at).num_days().to_string(),
+            Column::Updated => now.sub(item.updated_at).num_days().to_string(),
+            Column::Downloads => item.downloads.to_string(),
+            Column::RecentDownloads => item
+                .recent_downloads
+                .map(|d| d.to_string())
+                .unwrap_or_else(na),
+            Column::MaxVersion => match workspace.and_then(|w| w.locked_version(&item.id)) {
+                Some(locked) if workspace.is_some_and(|w| w.depends_on(&item.id)) => {
+                    format!("{} ({})", item.max_version, locked)
+                }
+                _ => item.max_version.clone(),
+            },
+            Column::RepoHost => item.repo_host().unwrap_or_else(na),
+            Column::License => item.license.clone().unwrap_or_else(na),
+            Column::Categories => match &item.categories {
+                Some(categories) if !categories.is_empty() => categories.join(", "),
+                _ => na(),
+            },
+            Column::ReverseDependencies => {
+                remote_cell(&item.reverse_dependencies, |n| n.to_string())
+            }
+            Column::Dependencies => remote_cell(&item.dependencies, |_| {
+                item.dependency_count().unwrap_or_default().to_string()
+            }),
+            Column::Msrv => remote_cell(&item.msrv, |msrv| msrv.clone().unwrap_or_else(na)),
+        }
+    }
+}
+
+/// Formats lazily fetched data, showing its state until it's ready.
+fn remote_cell<T>(remote: &Remote<T>, format: impl FnOnce(&T) -> String) -> String {
+    match remote {
+        Remote::Missing => String::new(),
+        Remote::Loading => "...".to_string(),
+        Remote::Ready(data) => format(data),
+        Remote::Failed(_) => "n/a".to_string(),
+    }
+}
+
+/// State of the compare tab.
+pub(crate) struct CompareView {
+    /// Visible columns, in display order
+    pub columns: Vec<Column>,
+    /// Index of the visible column selected for sorting
+    pub selected: usize,
+    /// Cursor of the column picker popup, the popup is shown if set
+    pub picker: Option<usize>,
+    /// State of the table, keeps the scroll offset between frames
+    pub table_state: TableState,
+}
+
+impl Default for CompareView {
+    fn default() -> Self {
+        CompareView {
+            columns: Column::DEFAULT.to_vec(),
+            selected: 0,
+            picker: None,
+            table_state: TableState::default(),
+        }
+    }
+}
+
+impl CompareView {
+    /// Returns the visible column selected for sorting.
+    pub fn selected_column(&self) -> Column {
+        self.columns[self.selected.min(self.columns.len() - 1)]
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.columns.len() - 1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Shows or hides the column under the picker cursor. The name column
+    /// is always shown.
+    pub fn toggle_picked(&mut self) {
+        let column = match self.picker {
+            Some(n) => Column::ALL[n],
+            None => return,
+        };
+        if column == Column::Name {
+            return;
+        }
+        match self.columns.iter().position(|c| *c == column) {
+            Some(n) => {
+                self.columns.remove(n);
+            }
+            None => {
+                // keep the columns in the order they're listed in
+                let position = |c: &Column| Column::ALL.iter().position(|a| a == c);
+                let n = self
+                    .columns
+                    .iter()
+                    .position(|c| position(c) > position(&column))
+                    .unwrap_or(self.columns.len());
+                self.columns.insert(n, column);
+            }
+        }
+        self.selected = self.selected.min(self.columns.len() - 1);
+    }
+
+    /// Builds the header, the rows and the column widths of the table.
+    ///
+    /// The column the results are sorted by is marked in the header, and
+    /// the one selected for sorting is underlined.
+    pub fn table(
+        &self,
+        items: &[Crate],
+        sorting: Option<(SortKey, bool)>,
+        now: DateTime<Utc>,
+        workspace: Option<&Workspace>,
+    ) -> (Row<'static>, Vec<Row<'static>>, Vec<Constraint>) {
+        let titles = self.titles(sorting);
+        let cells: Vec<Vec<String>> = items
+            .iter()
+            .map(|item| {
+                self.columns
+                    .iter()
+                    .map(|c| c.cell(item, now, workspace))
+                    .collect()
+            })
+            .collect();
+
+        let widths = titles
+            .iter()
+            .enumerate()
+            .map(|(n, title)| {
+                let content = cells.iter().map(|row| row[n].chars().count());
+                let width = content.chain([title.chars().count()]).max().unwrap_or(0);
+                Constraint::Length(width.min(MAX_COLUMN_WIDTH) as u16)
+            })
+            .collect();
+
+        let header = Row::new(titles.into_iter().enumerate().map(|(n, title)| {
+            let style = Style::default().add_modifier(Modifier::BOLD);
+            match n == self.selected {
+                true => Cell::from(title).style(style.add_modifier(Modifier::UNDERLINED)),
+                false => Cell::from(title).style(style),
+            }
+        }));
+        let rows = cells.into_iter().map(Row::new).collect();
+
+        (header, rows, widths)
+    }
+
+    /// Returns the titles of the visible columns, with an arrow showing the
+    /// direction of the column the results are sorted by.
+    fn titles(&self, sorting: Option<(SortKey, bool)>) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|column| match sorting {
+                Some((key, reversed)) if key == column.sort_key() => {
+                    // text and days since a date increase in the natural
+                    // order, other numbers decrease
+                    let ascending = matches!(
+                        key,
+                        SortKey::Name
+                            | SortKey::License
+                            | SortKey::Categories
+                            | SortKey::RepoHost
+                            | SortKey::Created
+                            | SortKey::Updated
+                    );
+                    let arrow = if ascending != reversed { "^" } else { "v" };
+                    format!("{} {}", column.title(), arrow)
+                }
+                _ => column.title().to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Duration;
+
+    use crate::items::test_crate;
+
+    #[test]
+    fn picker() {
+        let mut view = CompareView::default();
+        let picked = |view: &mut CompareView, column: Column| {
+            view.picker = Column::ALL.iter().position(|c| *c == column);
+            view.toggle_picked();
+        };
+
+        // picked columns keep the order they're listed in
+        picked(&mut view, Column::Msrv);
+        picked(&mut view, Column::License);
+        assert_eq!(
+            &view.columns[6..],
+            &[Column::RepoHost, Column::License, Column::Msrv]
+        );
+
+        // the name column can't be hidden
+        picked(&mut view, Column::Name);
+        assert_eq!(view.columns[0], Column::Name);
+
+        // the selection moves along when the selected column is hidden
+        view.selected = 8;
+        picked(&mut view, Column::Msrv);
+        assert_eq!(view.columns.len(), 8);
+        assert_eq!(view.selected_column(), Column::License);
+        view.select_next();
+        assert_eq!(view.selected, 7);
+        view.selected = 0;
+        view.select_previous();
+        assert_eq!(view.selected_column(), Column::Name);
+    }
+
+    #[test]
+    fn sorted_column_titles() {
+        let view = CompareView::default();
+        assert_eq!(view.titles(None)[3], "All-time dl");
+        // numbers decrease in the natural order, text and ages increase
+        assert_eq!(
+            view.titles(Some((SortKey::Downloads, false)))[3],
+            "All-time dl v"
+        );
+        assert_eq!(
+            view.titles(Some((SortKey::Downloads, true)))[3],
+            "All-time dl ^"
+        );
+        assert_eq!(view.titles(Some((SortKey::Name, false)))[0], "Name ^");
+        assert_eq!(
+            view.titles(Some((SortKey::Created, true)))[1],
+            "Since creation v"
+        );
+        // keys without a visible column don't mark any title
+        assert_eq!(view.titles(Some((SortKey::Msrv, false))), view.titles(None));
+    }
+
+    #[test]
+    fn cells_and_widths() {
+        let now = Utc::now();
+        let mut item = test_crate("a-crate-with-a-name-longer-than-a-column", "1.0.0");
+        item.created_at = now - Duration::days(400);
+        item.downloads = 12345;
+        item.reverse_dependencies = Remote::Loading;
+        item.msrv = Remote::Ready(None);
+
+        assert_eq!(Column::Created.cell(&item, now, None), "400");
+        assert_eq!(Column::RecentDownloads.cell(&item, now, None), "n/a");
+        assert_eq!(Column::Dependencies.cell(&item, now, None), "");
+        assert_eq!(Column::ReverseDependencies.cell(&item, now, None), "...");
+        assert_eq!(Column::Msrv.cell(&item, now, None), "n/a");
+
+        let view = CompareView {
+            columns: vec![Column::Name, Column::Created, Column::Downloads],
+            ..CompareView::default()
+        };
+        let (_, rows, widths) = view.table(&[item], None, now, None);
+        assert_eq!(rows.len(), 1);
+        // long values are cut off, short ones take the width of the title
+        assert_eq!(
+            widths,
+            vec![
+                Constraint::Length(MAX_COLUMN_WIDTH as u16),
+                Constraint::Length("Since creation".len() as u16),
+                Constraint::Length("All-time dl".len() as u16),
+            ]
+        );
+    }
+}
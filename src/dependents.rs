@@ -0,0 +1,203 @@
+//! Popup listing the crates depending on the selected crate.
+//!
+//! Download counts say little about whether serious projects rely on a
+//! crate, while the most downloaded dependents usually do. Dependents are
+//! fetched a page at a time as the list is scrolled through.
+
+use std::sync::{Arc, Mutex};
+
+use tui::backend::Backend;
+use tui::layout::{Constraint, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, Cell, Clear, Row, Table, TableState};
+use tui::Frame;
+
+use crate::api::{self, ReverseDependency};
+use crate::pool::{catch_panic, FetchPool};
+
+/// Dependents fetched so far.
+#[derive(Debug, Default)]
+struct Loaded {
+    items: Vec<ReverseDependency>,
+    /// Number of all the dependents, known after the first page
+    total: Option<u64>,
+    /// Number of the last fetched page
+    page: usize,
+    loading: bool,
+    error: Option<String>,
+}
+
+/// State of the dependents popup.
+pub(crate) struct Dependents {
+    /// Crate the dependents of which are listed
+    id: String,
+    pub name: String,
+    loaded: Arc<Mutex<Loaded>>,
+    /// Whether the least downloaded dependents are listed first. Only the
+    /// loaded pages are reversed, the rest are fetched most downloaded first
+    pub ascending: bool,
+    table_state: TableState,
+    /// Worker fetching the pages, pending fetches are dropped along with it
+    pool: FetchPool,
+}
+
+impl Dependents {
+    /// Starts fetching the first page of dependents of the given crate.
+    pub fn start(id: &str, name: &str) -> Self {
+        let dependents = Dependents {
+            id: id.to_string(),
+            name: name.to_string(),
+            loaded: Default::default(),
+            ascending: false,
+            table_state: TableState::default(),
+            pool: FetchPool::new(1),
+        };
+        dependents.load_more();
+        dependents
+    }
+
+    /// Fetches the next page of dependents, unless there's a request in
+    /// progress or there's nothing more to fetch.
+    pub fn load_more(&self) {
+        let mut loaded = self.loaded.lock().unwrap();
+        let exhausted = loaded
+            .total
+            .is_some_and(|total| loaded.items.len() as u64 >= total);
+        if loaded.loading || exhausted {
+            return;
+        }
+        loaded.loading = true;
+        loaded.error = None;
+        let page = loaded.page + 1;
+        let id = self.id.clone();
+        let shared = self.loaded.clone();
+        self.pool.push(&self.id, move || {
+            let result = catch_panic(|| api::get_reverse_dependencies(&id, page));
+            let mut loaded = shared.lock().unwrap();
+            loaded.loading = false;
+            match result {
+                Ok((items, total)) => {
+                    loaded.items.extend(items);
+                    loaded.total = Some(total);
+                    loaded.page = page;
+                }
+                Err(e) => loaded.error = Some(e.to_string()),
+            }
+        });
+    }
+
+    /// Returns the dependents in the order they're listed.
+    fn sorted(&self) -> Vec<ReverseDependency> {
+        let mut items = self.loaded.lock().unwrap().items.clone();
+        items.sort_by(|a, b| match self.ascending {
+            true => a.downloads.cmp(&b.downloads),
+            false => b.downloads.cmp(&a.downloads),
+        });
+        items
+    }
+
+    /// Returns the dependent under the cursor.
+    pub fn selected(&self) -> Option<ReverseDependency> {
+        self.sorted().get(self.table_state.selected()?).cloned()
+    }
+
+    /// Moves the cursor down, loading more dependents when it gets to the
+    /// last of the fetched ones.
+    pub fn select_next(&mut self) {
+        let len = self.loaded.lock().unwrap().items.len();
+        let n = match self.table_state.selected() {
+            Some(n) => (n + 1).min(len.saturating_sub(1)),
+            None => 0,
+        };
+        self.table_state.select(Some(n));
+        if n + 1 >= len {
+            self.load_more();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let n = self.table_state.selected().unwrap_or(0);
+        self.table_state.select(Some(n.saturating_sub(1)));
+    }
+
+    /// Reverses the order, keeping the cursor on the same dependent.
+    pub fn reverse(&mut self) {
+        let len = self.loaded.lock().unwrap().items.len();
+        self.ascending = !self.ascending;
+        if let Some(n) = self.table_state.selected() {
+            self.table_state
+                .select(Some(len.saturating_sub(1).saturating_sub(n)));
+        }
+    }
+
+    /// Renders the popup into the given area.
+    pub fn render<B: Backend>(&mut self, f: &mut Frame<B>, rect: Rect) {
+        let items = self.sorted();
+        let (total, loading, error) = {
+            let loaded = self.loaded.lock().unwrap();
+            (loaded.total, loaded.loading, loaded.error.clone())
+        };
+        if self.table_state.selected().is_none() && !items.is_empty() {
+            self.table_state.select(Some(0));
+        }
+
+        let mut title = format!("Crates depending on {}", self.name);
+        if let Some(total) = total {
+            title.push_str(&format!(" ({} of {})", items.len(), total));
+        }
+        let exhausted = total.is_some_and(|total| items.len() as u64 >= total);
+        title.push_str(match (self.ascending, exhausted) {
+            (true, true) => " [by downloads, ascending]",
+            (true, false) => " [loaded pages by downloads, ascending]",
+            (false, _) => " [by downloads]",
+        });
+        if loading {
+            title.push_str(" loading...");
+        }
+        if let Some(error) = error {
+            title.push_str(&format!(" - failed: {}", error));
+        }
+
+        let header = Row::new(
+            ["Name", "Version", "Requires", "Kind", "Downloads"]
+                .iter()
+                .map(|t| Cell::from(*t).style(Style::default().add_modifier(Modifier::BOLD))),
+        );
+        let rows: Vec<Row> = items
+            .into_iter()
+            .map(|dep| {
+                let kind = match dep.optional {
+                    true => format!("{}, optional", dep.kind),
+                    false => dep.kind,
+                };
+                Row::new(vec![
+                    dep.name,
+                    dep.version,
+                    dep.req,
+                    kind,
+                    dep.downloads.to_string(),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+        ];
+        let table = Table::new(rows)
+            .header(header)
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::White)),
+            )
+            .widths(&widths)
+            .column_spacing(2)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        f.render_widget(Clear, rect);
+        f.render_stateful_widget(table, rect, &mut self.table_state);
+    }
+}
@@ -0,0 +1,283 @@
+//! Side-by-side dependency comparison of two crates.
+//!
+//! Direct dependencies come from the crates.io API, while the features and
+//! the total number of crates a build may need are worked out from the sparse
+//! index. Dependencies shared by both crates are highlighted, as they
+//! don't add to the weight of picking one crate over the other.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+
+use crate::api::{self, Dependency};
+use crate::cache::Cache;
+use crate::index::Index;
+use crate::items::{Crate, Remote};
+use crate::pool::{catch_panic, FetchPool};
+
+/// Kinds of dependencies, in the order they're listed.
+const KINDS: [&str; 3] = ["normal", "build", "dev"];
+
+/// Dependency data of one of the compared crates.
+#[derive(Debug, Clone)]
+pub(crate) struct Side {
+    pub name: String,
+    pub version: String,
+    pub dependencies: Vec<Dependency>,
+    /// Features of the crate, along with whether they're enabled by default
+    pub features: Vec<(String, bool)>,
+    /// Number of crates needed to build the crate with default features,
+    /// counting build dependencies and the dependencies of every target.
+    /// Resolved after the rest of the data as it takes a while
+    pub transitive: Remote<usize>,
+}
+
+impl Side {
+    fn load(item: &Crate, index: &mut Index) -> Result<Side> {
+        let dependencies = api::get_dependencies(&item.id, &item.max_version)?;
+        let features = match index.version(&item.id, &item.max_version) {
+            Ok(entry) => {
+                let all = entry.all_features();
+                let default: HashSet<&String> = all.get("default").into_iter().flatten().collect();
+                all.keys()
+                    .filter(|f| *f != "default")
+                    .map(|f| (f.clone(), default.contains(f)))
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        Ok(Side {
+            name: item.name.clone(),
+            version: item.max_version.clone(),
+            dependencies,
+            features,
+            transitive: Remote::Loading,
+        })
+    }
+
+    /// Creates the lines listing the dependencies and features,
+    /// highlighting the dependencies also found on the other side.
+    fn lines(&self, other: Option<&Side>) -> Vec<Spans<'static>> {
+        let shared: HashSet<&str> = other
+            .map(|o| o.dependencies.iter().map(|d| d.crate_id.as_str()).collect())
+            .unwrap_or_default();
+        let heading = Style::default().add_modifier(Modifier::BOLD);
+        let dimmed = Style::default().fg(Color::DarkGray);
+
+        let mut lines = vec![
+            Spans::from(Span::styled(
+                format!("{} {}", self.name, self.version),
+                heading,
+            )),
+            Spans::from(format!("{} direct dependencies", self.dependencies.len())),
+            Spans::from(match &self.transitive {
+                Remote::Ready(n) => format!("{} crates in total for all targets", n),
+                Remote::Failed(e) => format!("total unknown: {}", e),
+                _ => "resolving the total...".to_string(),
+            }),
+        ];
+
+        for kind in KINDS {
+            let deps: Vec<&Dependency> = self
+                .dependencies
+                .iter()
+                .filter(|d| d.kind == kind)
+                .collect();
+            if deps.is_empty() {
+                continue;
+            }
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled(
+                format!("{} ({})", kind, deps.len()),
+                heading,
+            )));
+            for dep in deps {
+                let style = match shared.contains(dep.crate_id.as_str()) {
+                    true => Style::default().fg(Color::Cyan),
+                    false => Style::default(),
+                };
+                let mut spans = vec![Span::styled(
+                    format!("  {} {}", dep.crate_id, dep.req),
+                    style,
+                )];
+                if dep.optional {
+                    spans.push(Span::styled(" optional", dimmed));
+                }
+                if let Some(target) = &dep.target {
+                    spans.push(Span::styled(format!(" {}", target), dimmed));
+                }
+                lines.push(Spans::from(spans));
+            }
+        }
+
+        if !self.features.is_empty() {
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled(
+                format!("features ({})", self.features.len()),
+                heading,
+            )));
+            for (feature, default) in &self.features {
+                let mut spans = vec![Span::raw(format!("  {}", feature))];
+                if *default {
+                    spans.push(Span::styled(" default", dimmed));
+                }
+                lines.push(Spans::from(spans));
+            }
+        }
+        lines
+    }
+}
+
+/// Dependency comparison of two crates, loaded in the background.
+pub(crate) struct DepDiff {
+    pub sides: [Arc<Mutex<Remote<Side>>>; 2],
+    /// Vertical offset of both of the lists
+    pub scroll: u16,
+    /// Workers loading the sides, pending loads are dropped along with it
+    pool: FetchPool,
+}
+
+impl DepDiff {
+    /// Starts loading the dependency data of both crates.
+    pub fn start(items: [Crate; 2], cache: &Cache) -> Self {
+        let pool = FetchPool::new(items.len());
+        let sides = items.map(|item| {
+            let side = Arc::new(Mutex::new(Remote::Loading));
+            let result = side.clone();
+            let cache = cache.clone();
+            let key = item.id.clone();
+            pool.push(&key, move || {
+                let mut index = Index::new(cache);
+                match catch_panic(|| Side::load(&item, &mut index)) {
+                    Ok(side) => *result.lock().unwrap() = Remote::Ready(side),
+                    Err(e) => {
+                        *result.lock().unwrap() = Remote::Failed(e.to_string());
+                        return;
+                    }
+                }
+                let transitive =
+                    match catch_panic(|| index.resolve(&item.id, &item.max_version, &[], true)) {
+                        Ok(resolve) => Remote::Ready(resolve.packages.len() - 1),
+                        Err(e) => Remote::Failed(e.to_string()),
+                    };
+                if let Remote::Ready(side) = &mut *result.lock().unwrap() {
+                    side.transitive = transitive;
+                }
+            });
+            side
+        });
+        DepDiff {
+            sides,
+            scroll: 0,
+            pool,
+        }
+    }
+
+    /// Creates the lines of both sides of the comparison.
+    pub fn lines(&self) -> [Vec<Spans<'static>>; 2] {
+        let left = self.sides[0].lock().unwrap().clone();
+        let right = self.sides[1].lock().unwrap().clone();
+        [(&left, &right), (&right, &left)].map(|(side, other)| match side {
+            Remote::Ready(side) => side.lines(other.ready()),
+            Remote::Failed(e) => vec![Spans::from(format!("failed: {}", e))],
+            _ => vec![Spans::from("loading...")],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency(crate_id: &str, kind: &str) -> Dependency {
+        Dependency {
+            crate_id: crate_id.to_string(),
+            req: "^1".to_string(),
+            kind: kind.to_string(),
+            optional: false,
+            default_features: true,
+            features: Vec::new(),
+            target: None,
+        }
+    }
+
+    fn side(name: &str, dependencies: Vec<Dependency>) -> Side {
+        Side {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            dependencies,
+            features: Vec::new(),
+            transitive: Remote::Loading,
+        }
+    }
+
+    fn text(lines: &[Spans]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|spans| spans.0.iter().map(|s| s.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn kinds_and_features() {
+        let mut optional = dependency("serde", "normal");
+        optional.optional = true;
+        let mut windows = dependency("winapi", "normal");
+        windows.target = Some("cfg(windows)".to_string());
+        let mut left = side(
+            "left",
+            vec![
+                dependency("rand", "dev"),
+                optional,
+                dependency("cc", "build"),
+                windows,
+            ],
+        );
+        left.features = vec![("std".to_string(), true), ("alloc".to_string(), false)];
+        left.transitive = Remote::Ready(12);
+
+        assert_eq!(
+            text(&left.lines(None)),
+            [
+                "left 1.0.0",
+                "4 direct dependencies",
+                "12 crates in total for all targets",
+                "",
+                "normal (2)",
+                "  serde ^1 optional",
+                "  winapi ^1 cfg(windows)",
+                "",
+                "build (1)",
+                "  cc ^1",
+                "",
+                "dev (1)",
+                "  rand ^1",
+                "",
+                "features (2)",
+                "  std default",
+                "  alloc",
+            ]
+        );
+    }
+
+    #[test]
+    fn shared_dependencies() {
+        let left = side(
+            "left",
+            vec![dependency("libc", "normal"), dependency("log", "normal")],
+        );
+        let right = side("right", vec![dependency("libc", "build")]);
+        let lines = left.lines(Some(&right));
+        let styles: Vec<Option<Color>> = lines[5..].iter().map(|l| l.0[0].style.fg).collect();
+        assert_eq!(text(&lines[5..]), ["  libc ^1", "  log ^1"]);
+        assert_eq!(styles, [Some(Color::Cyan), None]);
+        assert_eq!(text(&lines[2..3]), ["resolving the total..."]);
+
+        let mut failed = right;
+        failed.transitive = Remote::Failed("no index".to_string());
+        assert_eq!(text(&failed.lines(None)[2..3]), ["total unknown: no index"]);
+    }
+}
@@ -0,0 +1,33 @@
+//! Locations of the files kept between sessions, following the XDG base
+//! directory specification.
+
+use std::path::{Path, PathBuf};
+
+/// Returns the directory for cached data, i.e. `$XDG_CACHE_HOME/cns`.
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    base_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// Returns the directory for persistent data, i.e. `$XDG_DATA_HOME/cns`.
+pub(crate) fn data_dir() -> Option<PathBuf> {
+    base_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Uses the directory given by the environment variable, falling back to
+/// the default location under the home directory.
+fn base_dir(var: &str, default: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(default)))
+        .map(|d| d.join("cns"))
+}
+
+/// Creates an empty temporary directory for a test.
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cns-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,237 @@
+//! Popup for picking the features of a dependency before it's copied to the
+//! clipboard or added to the manifest.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use tui::style::{Color, Style};
+use tui::text::{Span, Spans};
+
+use crate::items::{Crate, Remote};
+use crate::manifest::DependencySpec;
+
+/// Features declared by a crate version, along with what they enable.
+pub(crate) type Features = BTreeMap<String, Vec<String>>;
+
+/// Where the picked dependency ends up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Target {
+    Clipboard,
+    Manifest,
+}
+
+/// State of the feature picker.
+#[derive(Debug, Clone)]
+pub(crate) struct FeaturePicker {
+    pub target: Target,
+    /// Crate the dependency is on
+    pub id: String,
+    pub version: String,
+    /// Line under the cursor, the first one is `default-features`
+    cursor: usize,
+    enabled: BTreeSet<String>,
+    default_features: bool,
+    /// Features and `default-features` the picker started with, a picker
+    /// left that way keeps the features of an existing dependency as they are
+    initial: (BTreeSet<String>, bool),
+}
+
+impl FeaturePicker {
+    /// Creates the picker, starting with the features of the dependency
+    /// if it's already in the manifest.
+    pub fn new(item: &Crate, target: Target, existing: Option<&DependencySpec>) -> Self {
+        let enabled: BTreeSet<String> = existing
+            .and_then(|spec| spec.features.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let default_features = existing
+            .and_then(|spec| spec.default_features)
+            .unwrap_or(true);
+        FeaturePicker {
+            target,
+            id: item.id.clone(),
+            version: item.max_version.clone(),
+            cursor: 0,
+            initial: (enabled.clone(), default_features),
+            enabled,
+            default_features,
+        }
+    }
+
+    pub fn select_next(&mut self, features: Option<&Features>) {
+        let len = features.map(|f| names(f).len()).unwrap_or(0);
+        self.cursor = (self.cursor + 1).min(len);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Toggles `default-features` or the feature under the cursor.
+    pub fn toggle(&mut self, features: Option<&Features>) {
+        match self.cursor {
+            0 => self.default_features = !self.default_features,
+            n => {
+                let name = match features.and_then(|f| names(f).get(n - 1).cloned()) {
+                    Some(name) => name,
+                    None => return,
+                };
+                if !self.enabled.remove(&name) {
+                    self.enabled.insert(name);
+                }
+            }
+        }
+    }
+
+    /// Checks whether the picked features differ from the initial ones.
+    fn changed(&self) -> bool {
+        (&self.enabled, self.default_features) != (&self.initial.0, self.initial.1)
+    }
+
+    /// Returns the dependency with the picked features.
+    pub fn spec(&self) -> DependencySpec {
+        let mut spec = DependencySpec::new(&self.version);
+        if self.changed() {
+            spec.features = Some(self.enabled.iter().cloned().collect());
+            spec.default_features = Some(self.default_features);
+        }
+        spec
+    }
+
+    /// Returns the manifest line of the dependency.
+    pub fn line(&self) -> String {
+        format!("{} = {}", self.id, self.spec())
+    }
+
+    /// Returns the line the cursor is on.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Creates the lines of the picker, listing the features along with
+    /// the resulting dependency.
+    pub fn lines(&self, features: &Remote<Features>) -> Vec<Spans<'static>> {
+        let dimmed = Style::default().fg(Color::DarkGray);
+        let highlight = Style::default().bg(Color::DarkGray);
+        let mark = |on: bool| if on { "[x]" } else { "[ ]" };
+
+        let mut lines = vec![];
+        let default_line = format!("{} default-features", mark(self.default_features));
+        lines.push(match self.cursor {
+            0 => Spans::from(Span::styled(default_line, highlight)),
+            _ => Spans::from(default_line),
+        });
+        match features {
+            Remote::Ready(features) => {
+                let defaults: BTreeSet<&String> =
+                    features.get("default").into_iter().flatten().collect();
+                for (n, name) in names(features).iter().enumerate() {
+                    let line = format!("{} {}", mark(self.enabled.contains(name)), name);
+                    let mut spans = vec![match self.cursor == n + 1 {
+                        true => Span::styled(line, highlight),
+                        false => Span::raw(line),
+                    }];
+                    if defaults.contains(name) {
+                        spans.push(Span::styled(" default", dimmed));
+                    }
+                    lines.push(Spans::from(spans));
+                }
+            }
+            Remote::Failed(e) => lines.push(Spans::from(format!("features unknown: {}", e))),
+            _ => lines.push(Spans::from("loading features...")),
+        }
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            self.line(),
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.push(Spans::from(Span::styled(
+            "<Space> toggle | <Enter> confirm | <Esc> cancel",
+            dimmed,
+        )));
+        lines
+    }
+}
+
+/// Returns the names of the features that can be picked.
+fn names(features: &Features) -> Vec<String> {
+    features
+        .keys()
+        .filter(|name| *name != "default")
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::test_crate;
+
+    fn features() -> Features {
+        Features::from([
+            ("default".to_string(), vec!["std".to_string()]),
+            ("derive".to_string(), vec![]),
+            ("std".to_string(), vec![]),
+        ])
+    }
+
+    #[test]
+    fn picking() {
+        let features = features();
+        let mut picker = FeaturePicker::new(&test_crate("serde", "1.0.0"), Target::Clipboard, None);
+        assert_eq!(picker.line(), "serde = \"1.0.0\"");
+
+        for _ in 0..5 {
+            picker.select_next(Some(&features));
+        }
+        assert_eq!(picker.cursor(), 2);
+        picker.toggle(Some(&features));
+        picker.select_previous();
+        picker.select_previous();
+        picker.toggle(Some(&features));
+        assert_eq!(
+            picker.line(),
+            "serde = { version = \"1.0.0\", features = [\"std\"], default-features = false }"
+        );
+    }
+
+    #[test]
+    fn toggling_back_is_no_change() {
+        let features = features();
+        let mut picker = FeaturePicker::new(&test_crate("serde", "1.0.0"), Target::Manifest, None);
+        picker.toggle(Some(&features));
+        picker.toggle(Some(&features));
+        picker.select_next(Some(&features));
+        picker.toggle(Some(&features));
+        picker.toggle(Some(&features));
+        assert_eq!(picker.spec(), DependencySpec::new("1.0.0"));
+    }
+
+    #[test]
+    fn seeded_from_existing() {
+        let features = features();
+        let existing = DependencySpec {
+            version: "0.9".to_string(),
+            features: Some(vec!["derive".to_string()]),
+            default_features: Some(false),
+        };
+        let mut picker = FeaturePicker::new(
+            &test_crate("serde", "1.0.0"),
+            Target::Manifest,
+            Some(&existing),
+        );
+        // untouched, the existing features are left as they are
+        assert_eq!(picker.spec(), DependencySpec::new("1.0.0"));
+
+        // toggling one keeps the others
+        picker.select_next(Some(&features));
+        picker.select_next(Some(&features));
+        picker.toggle(Some(&features));
+        let spec = picker.spec();
+        assert_eq!(
+            spec.features,
+            Some(vec!["derive".to_string(), "std".to_string()])
+        );
+        assert_eq!(spec.default_features, Some(false));
+    }
+}
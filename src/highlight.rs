@@ -0,0 +1,408 @@
+//! Simple syntax highlighting of Rust code.
+//!
+//! Code is tokenized line by line with a small state machine, which is enough
+//! to color keywords, strings, comments, macros, lifetimes, numbers and type
+//! names. State is carried between lines to support block comments and
+//! strings spanning multiple lines.
+
+use tui::style::{Color, Modifier, Style};
+use tui::text::Span;
+
+const KEYWORDS: [&str; 39] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "yield",
+];
+
+/// Code block language tags that are highlighted as Rust. Untagged code
+/// blocks and rustdoc attributes are assumed to be Rust.
+const RUST_TAGS: [&str; 8] = [
+    "",
+    "rust",
+    "rs",
+    "ignore",
+    "no_run",
+    "should_panic",
+    "compile_fail",
+    "edition2021",
+];
+
+/// Checks whether a code block with the given language tag should be
+/// highlighted as Rust.
+pub(crate) fn is_rust(lang: &str) -> bool {
+    RUST_TAGS.contains(&lang) || lang.starts_with("edition")
+}
+
+/// Tokenizer state carried over between lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum State {
+    #[default]
+    Normal,
+    /// Inside a block comment, with the nesting depth
+    BlockComment(usize),
+    /// Inside a string literal
+    Str,
+    /// Inside a raw string literal, with the number of hashes
+    RawStr(usize),
+}
+
+fn keyword_style(base: Style) -> Style {
+    base.fg(Color::Magenta)
+}
+
+fn string_style(base: Style) -> Style {
+    base.fg(Color::Green)
+}
+
+fn comment_style(base: Style) -> Style {
+    base.fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+}
+
+/// Highlights a single line of Rust code.
+pub(crate) fn highlight_line(line: &str, state: &mut State, base: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+
+    // pushes a token, flushing any plain text collected before it
+    let push = |spans: &mut Vec<Span<'static>>, plain: &mut String, text: &[char], style: Style| {
+        if !plain.is_empty() {
+            spans.push(Span::styled(std::mem::take(plain), base));
+        }
+        spans.push(Span::styled(text.iter().collect::<String>(), style));
+    };
+
+    while i < chars.len() {
+        match *state {
+            State::BlockComment(depth) => {
+                let start = i;
+                let mut depth = depth;
+                while i < chars.len() && depth > 0 {
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        depth += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                        depth -= 1;
+                        i += 2;
+                    } else {
+                        i += 1;
+                    }
+                }
+                *state = match depth {
+                    0 => State::Normal,
+                    d => State::BlockComment(d),
+                };
+                push(
+                    &mut spans,
+                    &mut plain,
+                    &chars[start..i],
+                    comment_style(base),
+                );
+            }
+            State::Str => {
+                let start = i;
+                while i < chars.len() {
+                    match chars[i] {
+                        '\\' => i += 2,
+                        '"' => {
+                            i += 1;
+                            *state = State::Normal;
+                            break;
+                        }
+                        _ => i += 1,
+                    }
+                }
+                let end = i.min(chars.len());
+                push(
+                    &mut spans,
+                    &mut plain,
+                    &chars[start..end],
+                    string_style(base),
+                );
+            }
+            State::RawStr(hashes) => {
+                let start = i;
+                while i < chars.len() {
+                    if chars[i] == '"'
+                        && chars[i + 1..].iter().take_while(|c| **c == '#').count() >= hashes
+                    {
+                        i += 1 + hashes;
+                        *state = State::Normal;
+                        break;
+                    }
+                    i += 1;
+                }
+                push(&mut spans, &mut plain, &chars[start..i], string_style(base));
+            }
+            State::Normal => {
+                let c = chars[i];
+                let next = chars.get(i + 1).cloned();
+                if c == '/' && next == Some('/') {
+                    push(&mut spans, &mut plain, &chars[i..], comment_style(base));
+                    i = chars.len();
+                } else if c == '/' && next == Some('*') {
+                    push(
+                        &mut spans,
+                        &mut plain,
+                        &chars[i..i + 2],
+                        comment_style(base),
+                    );
+                    *state = State::BlockComment(1);
+                    i += 2;
+                } else if c == '"' {
+                    push(&mut spans, &mut plain, &chars[i..i + 1], string_style(base));
+                    *state = State::Str;
+                    i += 1;
+                } else if let Some(len) = raw_string_start(&chars[i..]) {
+                    let hashes = chars[i..i + len].iter().filter(|c| **c == '#').count();
+                    push(
+                        &mut spans,
+                        &mut plain,
+                        &chars[i..i + len],
+                        string_style(base),
+                    );
+                    *state = State::RawStr(hashes);
+                    i += len;
+                } else if c == 'b' && next == Some('"') {
+                    push(&mut spans, &mut plain, &chars[i..i + 2], string_style(base));
+                    *state = State::Str;
+                    i += 2;
+                } else if c == '\'' {
+                    // tell character literals apart from lifetimes
+                    let len = if next == Some('\\') {
+                        // skip the escaped character, which can be a quote
+                        chars
+                            .get(i + 3..)
+                            .and_then(|rest| rest.iter().position(|c| *c == '\''))
+                            .map(|p| p + 4)
+                    } else if chars.get(i + 2) == Some(&'\'') {
+                        Some(3)
+                    } else {
+                        None
+                    };
+                    match len {
+                        Some(len) => {
+                            let end = (i + len).min(chars.len());
+                            push(&mut spans, &mut plain, &chars[i..end], string_style(base));
+                            i = end;
+                        }
+                        None if next.is_some_and(is_ident_start) => {
+                            let len = 1 + ident_len(&chars[i + 1..]);
+                            push(
+                                &mut spans,
+                                &mut plain,
+                                &chars[i..i + len],
+                                base.fg(Color::LightRed),
+                            );
+                            i += len;
+                        }
+                        None => {
+                            plain.push(c);
+                            i += 1;
+                        }
+                    }
+                } else if c.is_ascii_digit() && !chars[..i].last().is_some_and(|p| is_ident(*p)) {
+                    let mut len = 1;
+                    while let Some(n) = chars.get(i + len) {
+                        let continues_float =
+                            *n == '.' && chars.get(i + len + 1).is_some_and(|d| d.is_ascii_digit());
+                        if is_ident(*n) || continues_float {
+                            len += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    push(
+                        &mut spans,
+                        &mut plain,
+                        &chars[i..i + len],
+                        base.fg(Color::LightYellow),
+                    );
+                    i += len;
+                } else if is_ident_start(c) {
+                    let len = ident_len(&chars[i..]);
+                    let word: String = chars[i..i + len].iter().collect();
+                    if chars.get(i + len) == Some(&'!') && chars.get(i + len + 1) != Some(&'=') {
+                        push(
+                            &mut spans,
+                            &mut plain,
+                            &chars[i..i + len + 1],
+                            base.fg(Color::Cyan),
+                        );
+                        i += len + 1;
+                        continue;
+                    }
+                    let style = if KEYWORDS.contains(&word.as_str()) {
+                        Some(keyword_style(base))
+                    } else if word.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        Some(base.fg(Color::LightBlue))
+                    } else {
+                        None
+                    };
+                    match style {
+                        Some(style) => push(&mut spans, &mut plain, &chars[i..i + len], style),
+                        None => plain.push_str(&word),
+                    }
+                    i += len;
+                } else {
+                    plain.push(c);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    if !plain.is_empty() {
+        spans.push(Span::styled(plain, base));
+    }
+    spans
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn ident_len(chars: &[char]) -> usize {
+    chars.iter().take_while(|c| is_ident(**c)).count()
+}
+
+/// Returns the length of the raw string opening, e.g. `r#"` or `br"`.
+fn raw_string_start(chars: &[char]) -> Option<usize> {
+    let prefix = match chars {
+        ['r', ..] => 1,
+        ['b', 'r', ..] => 2,
+        _ => return None,
+    };
+    let hashes = chars[prefix..].iter().take_while(|c| **c == '#').count();
+    match chars.get(prefix + hashes) {
+        Some('"') => Some(prefix + hashes + 1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(line: &str) -> Vec<(String, Style)> {
+        highlight_line(line, &mut State::Normal, Style::default())
+            .into_iter()
+            .map(|span| (span.content.to_string(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn keywords_types_and_macros() {
+        let base = Style::default();
+        assert_eq!(
+            spans("pub fn new() -> Self { println!(x) }"),
+            vec![
+                ("pub".to_string(), keyword_style(base)),
+                (" ".to_string(), base),
+                ("fn".to_string(), keyword_style(base)),
+                (" new() -> ".to_string(), base),
+                ("Self".to_string(), keyword_style(base)),
+                (" { ".to_string(), base),
+                ("println!".to_string(), base.fg(Color::Cyan)),
+                ("(x) }".to_string(), base),
+            ]
+        );
+        let typed = spans("let v: Vec<u8> = 0x1f;");
+        assert!(typed.contains(&("Vec".to_string(), base.fg(Color::LightBlue))));
+        assert!(typed.contains(&("0x1f".to_string(), base.fg(Color::LightYellow))));
+        // identifiers containing keywords aren't highlighted
+        assert_eq!(spans("inner_fn"), vec![("inner_fn".to_string(), base)]);
+    }
+
+    #[test]
+    fn strings() {
+        let base = Style::default();
+        assert_eq!(
+            spans(r##"f("a \" b", r#"raw"#)"##),
+            vec![
+                ("f(".to_string(), base),
+                ("\"".to_string(), string_style(base)),
+                (r#"a \" b""#.to_string(), string_style(base)),
+                (", ".to_string(), base),
+                ("r#\"".to_string(), string_style(base)),
+                ("raw\"#".to_string(), string_style(base)),
+                (")".to_string(), base),
+            ]
+        );
+
+        // strings spanning multiple lines
+        let mut state = State::Normal;
+        highlight_line("let s = \"first", &mut state, base);
+        assert_eq!(state, State::Str);
+        let spans = highlight_line("second\"; x", &mut state, base);
+        assert_eq!(state, State::Normal);
+        assert_eq!(spans[0].content, "second\"");
+        assert_eq!(spans[0].style, string_style(base));
+    }
+
+    #[test]
+    fn comments() {
+        let base = Style::default();
+        assert_eq!(
+            spans("x // fn \"not code\""),
+            vec![
+                ("x ".to_string(), base),
+                ("// fn \"not code\"".to_string(), comment_style(base)),
+            ]
+        );
+
+        // nested block comments spanning multiple lines
+        let mut state = State::Normal;
+        highlight_line("a /* outer /* inner */", &mut state, base);
+        assert_eq!(state, State::BlockComment(1));
+        let spans = highlight_line("still */ fn", &mut state, base);
+        assert_eq!(state, State::Normal);
+        assert_eq!(spans[0].content, "still */");
+        assert_eq!(spans[0].style, comment_style(base));
+        assert_eq!(spans[2].style, keyword_style(base));
+    }
+
+    #[test]
+    fn lifetimes() {
+        let base = Style::default();
+        assert_eq!(
+            spans("&'a str, 'b', 'static"),
+            vec![
+                ("&".to_string(), base),
+                ("'a".to_string(), base.fg(Color::LightRed)),
+                (" str, ".to_string(), base),
+                ("'b'".to_string(), string_style(base)),
+                (", ".to_string(), base),
+                ("'static".to_string(), base.fg(Color::LightRed)),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_quote_char_literal() {
+        let spans = spans("let q = '\\''; x");
+        assert!(spans.contains(&("'\\''".to_string(), string_style(Style::default()))));
+        assert_eq!(spans.last().unwrap().0, "; x");
+    }
+
+    #[test]
+    fn escaped_char_literal() {
+        let spans = spans("'\\n'");
+        assert_eq!(
+            spans,
+            vec![("'\\n'".to_string(), string_style(Style::default()))]
+        );
+    }
+
+    #[test]
+    fn unterminated_escape_at_line_end() {
+        let text: String = spans("'\\").into_iter().map(|(s, _)| s).collect();
+        assert_eq!(text, "'\\");
+    }
+}
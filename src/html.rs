@@ -0,0 +1,466 @@
+//! Conversion of rendered readme html into markdown.
+//!
+//! crates.io serves readmes already rendered into html. Converting them back
+//! into markdown lets us handle them the same way as the raw readme files
+//! fetched from repositories. Only the subset of html produced by crates.io
+//! markdown rendering is supported, any other tags are dropped while keeping
+//! their text content.
+
+/// Single html tag.
+struct Tag {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn parse(raw: &str) -> Tag {
+        let raw = raw.trim().trim_end_matches('/');
+        let (closing, raw) = match raw.strip_prefix('/') {
+            Some(r) => (true, r),
+            None => (false, raw),
+        };
+        let name_end = raw.find(|c: char| c.is_whitespace()).unwrap_or(raw.len());
+        let name = raw[..name_end].to_lowercase();
+
+        // parse `key="value"` pairs, also accepting single quotes and
+        // unquoted values
+        let mut attrs = Vec::new();
+        let mut rest = raw[name_end..].trim_start();
+        while !rest.is_empty() {
+            let key_end = rest
+                .find(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let key = rest[..key_end].to_lowercase();
+            rest = rest[key_end..].trim_start();
+            let mut value = String::new();
+            if let Some(r) = rest.strip_prefix('=') {
+                let r = r.trim_start();
+                let (val, r) = match r.chars().next() {
+                    Some(q) if q == '"' || q == '\'' => {
+                        let end = r[1..].find(q).map(|e| e + 1).unwrap_or(r.len());
+                        (&r[1..end], r.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = r.find(char::is_whitespace).unwrap_or(r.len());
+                        (&r[..end], &r[end..])
+                    }
+                };
+                value = decode_entities(val);
+                rest = r.trim_start();
+            }
+            if !key.is_empty() {
+                attrs.push((key, value));
+            }
+        }
+
+        Tag {
+            name,
+            closing,
+            attrs,
+        }
+    }
+
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// List currently being converted.
+enum List {
+    Unordered,
+    Ordered(usize),
+}
+
+/// Markdown output, keeps track of block quote prefixes.
+#[derive(Default)]
+struct Output {
+    text: String,
+    quote_depth: usize,
+}
+
+impl Output {
+    fn push(&mut self, s: &str) {
+        for (n, line) in s.split('\n').enumerate() {
+            if n > 0 {
+                self.text.push('\n');
+            }
+            if !line.is_empty() {
+                if self.at_line_start() {
+                    self.text.push_str(&"> ".repeat(self.quote_depth));
+                }
+                self.text.push_str(line);
+            }
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.text.is_empty() || self.text.ends_with('\n')
+    }
+
+    fn newline(&mut self) {
+        if !self.at_line_start() {
+            self.text.push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.text.is_empty() && !self.text.ends_with("\n\n") {
+            self.text.push('\n');
+        }
+    }
+}
+
+/// Converts html into markdown.
+pub(crate) fn to_markdown(html: &str) -> String {
+    let mut out = Output::default();
+    let mut lists: Vec<List> = Vec::new();
+    let mut links: Vec<Option<String>> = Vec::new();
+    let mut in_pre = false;
+    let mut in_code = false;
+    // code block fence waiting for the language from the inner code tag
+    let mut fence_pending = false;
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        // comments
+        if let Some(r) = rest.strip_prefix("<!--") {
+            rest = r.find("-->").map(|e| &r[e + 3..]).unwrap_or("");
+            continue;
+        }
+
+        // tags
+        if rest.starts_with('<') {
+            if let Some(end) = rest.find('>') {
+                let tag = Tag::parse(&rest[1..end]);
+                rest = &rest[end + 1..];
+
+                // skip the content, which isn't html and can contain `<`
+                if (tag.name == "script" || tag.name == "style") && !tag.closing {
+                    let close = format!("</{}", tag.name);
+                    rest = match rest.to_ascii_lowercase().find(&close) {
+                        Some(start) => rest[start..]
+                            .find('>')
+                            .map(|e| &rest[start + e + 1..])
+                            .unwrap_or(""),
+                        None => "",
+                    };
+                    continue;
+                }
+
+                match (tag.name.as_str(), tag.closing) {
+                    ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                        let level = tag.name[1..].parse().unwrap_or(1);
+                        out.blank_line();
+                        out.push(&format!("{} ", "#".repeat(level)));
+                    }
+                    ("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "table", true) => {
+                        out.blank_line()
+                    }
+                    ("p" | "table", false) => out.blank_line(),
+                    ("div" | "details" | "summary" | "tr", _) => out.newline(),
+                    ("br", _) => out.push("\n"),
+                    ("hr", _) => {
+                        out.blank_line();
+                        out.push("---");
+                        out.blank_line();
+                    }
+                    ("th" | "td", false) => out.push("| "),
+                    ("th" | "td", true) => out.push(" "),
+                    ("strong" | "b", _) => out.push("**"),
+                    ("em" | "i", _) => out.push("*"),
+                    ("del" | "s", _) => out.push("~~"),
+                    ("pre", false) => {
+                        out.blank_line();
+                        in_pre = true;
+                        fence_pending = true;
+                    }
+                    ("pre", true) => {
+                        if fence_pending {
+                            out.push("```");
+                            fence_pending = false;
+                        }
+                        out.newline();
+                        out.push("```");
+                        out.blank_line();
+                        in_pre = false;
+                    }
+                    ("code", false) if in_pre && fence_pending => {
+                        let lang = tag
+                            .attr("class")
+                            .and_then(|c| {
+                                c.split_whitespace()
+                                    .find_map(|c| c.strip_prefix("language-"))
+                            })
+                            .unwrap_or("");
+                        out.push(&format!("```{}\n", lang));
+                        fence_pending = false;
+                    }
+                    ("code", closing) if !in_pre => {
+                        in_code = !closing;
+                        out.push("`");
+                    }
+                    ("blockquote", false) => {
+                        out.blank_line();
+                        out.quote_depth += 1;
+                    }
+                    ("blockquote", true) => {
+                        out.newline();
+                        out.quote_depth = out.quote_depth.saturating_sub(1);
+                        out.blank_line();
+                    }
+                    ("ul", false) => {
+                        out.newline();
+                        lists.push(List::Unordered);
+                    }
+                    ("ol", false) => {
+                        out.newline();
+                        let start = tag.attr("start").and_then(|s| s.parse().ok());
+                        lists.push(List::Ordered(start.unwrap_or(1)));
+                    }
+                    ("ul" | "ol", true) => {
+                        lists.pop();
+                        if lists.is_empty() {
+                            out.blank_line();
+                        }
+                    }
+                    ("li", false) => {
+                        out.newline();
+                        let indent = "  ".repeat(lists.len().saturating_sub(1));
+                        let marker = match lists.last_mut() {
+                            Some(List::Ordered(n)) => {
+                                *n += 1;
+                                format!("{}. ", *n - 1)
+                            }
+                            _ => "- ".to_string(),
+                        };
+                        out.push(&format!("{}{}", indent, marker));
+                    }
+                    ("a", false) => {
+                        // skip heading anchors and other in-page links
+                        let href = tag
+                            .attr("href")
+                            .filter(|h| !h.starts_with('#'))
+                            .map(|h| h.to_string());
+                        if href.is_some() {
+                            out.push("[");
+                        }
+                        links.push(href);
+                    }
+                    ("a", true) => {
+                        if let Some(Some(href)) = links.pop() {
+                            out.push(&format!("]({})", href));
+                        }
+                    }
+                    ("img", false) => {
+                        if let Some(src) = tag.attr("src") {
+                            out.push(&format!("![{}]({})", tag.attr("alt").unwrap_or(""), src));
+                        }
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+        }
+
+        // text content up until the next tag
+        let first_len = rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        let end = rest[first_len..]
+            .find('<')
+            .map(|e| e + first_len)
+            .unwrap_or(rest.len());
+        let text = decode_entities(&rest[..end]);
+        rest = &rest[end..];
+
+        if in_pre {
+            if fence_pending {
+                out.push("```\n");
+                fence_pending = false;
+            }
+            out.push(&text);
+        } else {
+            let mut words = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+            if !in_code {
+                words = escape(&words, out.at_line_start());
+            }
+            let mut collapsed = String::new();
+            if text.starts_with(char::is_whitespace)
+                && !out.at_line_start()
+                && !out.text.ends_with(' ')
+            {
+                collapsed.push(' ');
+            }
+            collapsed.push_str(&words);
+            if text.ends_with(char::is_whitespace) && !words.is_empty() {
+                collapsed.push(' ');
+            }
+            out.push(&collapsed);
+        }
+    }
+
+    out.text.trim().to_string()
+}
+
+/// Escapes text so that it isn't parsed as markdown syntax. Block markers,
+/// e.g. `#` or `1.`, only need escaping at the start of a line.
+fn escape(text: &str, line_start: bool) -> String {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let mut escaped = String::with_capacity(text.len());
+    for (n, c) in text.chars().enumerate() {
+        let block_marker = line_start
+            && match c {
+                '#' | '-' | '+' | '=' | '>' | '|' => n == 0,
+                '.' | ')' => digits > 0 && n == digits,
+                _ => false,
+            };
+        if block_marker || matches!(c, '\\' | '`' | '*' | '_' | '~' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Decodes the most common html character references.
+fn decode_entities(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(e) if e <= 10 => e,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let ch = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                if let Some(hex) = entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok().and_then(char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+        match ch {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &#169; &#x2764; &nbsp;"),
+            "a & b © ❤  "
+        );
+        assert_eq!(
+            decode_entities("AT&T &unknown; & so on"),
+            "AT&T &unknown; & so on"
+        );
+        assert_eq!(to_markdown("<p>Tom &amp; Jerry</p>"), "Tom & Jerry");
+    }
+
+    #[test]
+    fn escaped_text() {
+        assert_eq!(to_markdown("<p>Vec&lt;T&gt;</p>"), "Vec\\<T>");
+        assert_eq!(
+            to_markdown("<p>a * b, snake_case, [x] and `y`</p>"),
+            "a \\* b, snake\\_case, \\[x\\] and \\`y\\`"
+        );
+        assert_eq!(
+            to_markdown("<p># 1. not a heading</p>"),
+            "\\# 1. not a heading"
+        );
+        assert_eq!(to_markdown("<p>1. not a list</p>"), "1\\. not a list");
+        // inline code is rendered verbatim
+        assert_eq!(to_markdown("<p><code>Vec&lt;T&gt;</code></p>"), "`Vec<T>`");
+    }
+
+    #[test]
+    fn escaped_text_renders_literally() {
+        let rendered = crate::markdown::render(&to_markdown("<p>Vec&lt;T&gt; and *x*</p>"));
+        let text: String = rendered.lines[0]
+            .0
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(text, "Vec<T> and *x*");
+    }
+
+    #[test]
+    fn nested_lists() {
+        let html = "<ul><li>one<ul><li>nested</li></ul></li><li>two</li></ul>\
+            <ol start=\"3\"><li>three</li><li>four</li></ol>";
+        assert_eq!(
+            to_markdown(html),
+            "- one\n  - nested\n- two\n\n3. three\n4. four"
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        let html = "<pre><code class=\"language-rust\">let v: Vec&lt;u8&gt; = *x;\n\
+            fn main() {}\n</code></pre><pre>plain</pre>";
+        assert_eq!(
+            to_markdown(html),
+            "```rust\nlet v: Vec<u8> = *x;\nfn main() {}\n```\n\n```\nplain\n```"
+        );
+    }
+
+    #[test]
+    fn links_and_images() {
+        let html = "<h2><a href=\"#usage\"></a>Usage</h2>\
+            <p>See <a href=\"https://docs.rs\">the <em>docs</em></a>.\
+            <img src=\"logo.png\" alt=\"Logo\"></p>";
+        assert_eq!(
+            to_markdown(html),
+            "## Usage\n\nSee [the *docs*](https://docs.rs).![Logo](logo.png)"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let html = "<table><thead><tr><th>Name</th><th>Value</th></tr></thead>\
+            <tbody><tr><td>a</td><td>1</td></tr></tbody></table>";
+        assert_eq!(to_markdown(html), "| Name | Value \n| a | 1");
+    }
+
+    #[test]
+    fn skipped_content() {
+        let html = "<p>a<!-- comment --> b</p><script>let x = 1 < 2;</script><p>c</p>";
+        assert_eq!(to_markdown(html), "a b\n\nc");
+    }
+}
@@ -0,0 +1,64 @@
+//! Simple http helpers used for requests that don't go through the crates.io
+//! client.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use anyhow::{Error, Result};
+use http_req::request::Request;
+use http_req::uri::Uri;
+use serde::de::DeserializeOwned;
+
+/// User-agent string used for all outgoing requests.
+pub const USER_AGENT: &str = "crate_name_search (github.com/adamsky/cns)";
+
+/// Default timeout for a single request.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Performs a GET request and returns the response body.
+///
+/// Returns an error if the response status is not a success.
+pub(crate) fn get(url: &str, headers: &[(&str, &str)]) -> Result<Vec<u8>> {
+    let uri = Uri::try_from(url)?;
+    let mut request = Request::new(&uri);
+    request
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/json")
+        .timeout(TIMEOUT);
+    for (key, val) in headers {
+        request.header(key, val);
+    }
+
+    let mut buffer = Vec::new();
+    let response = request.send(&mut buffer)?;
+    if !response.status_code().is_success() {
+        return Err(Error::msg(format!(
+            "request to {} failed: {} {}",
+            url,
+            response.status_code(),
+            response.reason()
+        )));
+    }
+
+    Ok(buffer)
+}
+
+/// Performs a GET request and deserializes the json response body.
+pub(crate) fn get_json<T: DeserializeOwned>(url: &str, headers: &[(&str, &str)]) -> Result<T> {
+    let body = get(url, headers)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Percent-encodes a string for use as a url query parameter value.
+pub(crate) fn encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,516 @@
+//! Client of the crates.io sparse index.
+//!
+//! The index lists every published version of a crate along with its
+//! dependencies and features, which is enough to resolve the full dependency
+//! graph of a crate the way cargo would, without downloading any sources.
+//! Index files are cached on disk like the rest of the fetched data.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use anyhow::{Error, Result};
+use serde::Deserialize;
+
+use crate::cache::Cache;
+use crate::http;
+use crate::version::{SemVer, VersionReq};
+
+/// Base url of the sparse index.
+pub const INDEX_URL: &str = "https://index.crates.io/";
+
+/// Dependency of a version as listed in the index.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct IndexDependency {
+    /// Name the dependency is used under, see `package` for renames
+    pub name: String,
+    pub req: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default = "default_true")]
+    pub default_features: bool,
+    pub target: Option<String>,
+    /// Either `normal`, `dev` or `build`, missing means `normal`
+    pub kind: Option<String>,
+    /// Actual name of a renamed dependency
+    pub package: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl IndexDependency {
+    /// Returns the name of the crate depended on.
+    pub fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn is_dev(&self) -> bool {
+        self.kind.as_deref() == Some("dev")
+    }
+}
+
+/// Published version of a crate as listed in the index.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct IndexVersion {
+    pub name: String,
+    pub vers: String,
+    pub deps: Vec<IndexDependency>,
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Features using the newer syntax are listed separately
+    #[serde(default)]
+    pub features2: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub yanked: bool,
+    pub rust_version: Option<String>,
+}
+
+impl IndexVersion {
+    /// Returns all the features of the version.
+    pub fn all_features(&self) -> BTreeMap<String, Vec<String>> {
+        let mut features = self.features.clone();
+        features.extend(self.features2.clone());
+        features
+    }
+}
+
+/// Crate version in a resolved dependency graph.
+#[derive(Debug, Clone)]
+pub(crate) struct Package {
+    pub name: String,
+    pub version: String,
+    /// Indices of the dependencies of the package in the graph
+    pub dependencies: Vec<usize>,
+    /// Features enabled on the package
+    pub features: BTreeSet<String>,
+}
+
+/// Dependency graph of a crate, the crate itself is the first package.
+#[derive(Debug, Clone)]
+pub(crate) struct Resolve {
+    pub packages: Vec<Package>,
+}
+
+/// Index client, keeping the files it fetched in memory.
+pub(crate) struct Index {
+    cache: Cache,
+    crates: HashMap<String, Vec<IndexVersion>>,
+}
+
+impl Index {
+    pub fn new(cache: Cache) -> Self {
+        Index {
+            cache,
+            crates: HashMap::new(),
+        }
+    }
+
+    /// Gets all the published versions of the given crate, oldest first.
+    pub fn versions(&mut self, name: &str) -> Result<&[IndexVersion]> {
+        let name = name.to_lowercase();
+        if !self.crates.contains_key(&name) {
+            let file = self.cache.index_file(&name, |name| {
+                let body = http::get(&format!("{}{}", INDEX_URL, index_path(name)), &[])?;
+                Ok(String::from_utf8(body)?)
+            })?;
+            let versions = file
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<serde_json::Result<_>>()?;
+            self.crates.insert(name.clone(), versions);
+        }
+        Ok(&self.crates[&name])
+    }
+
+    /// Gets the given version of the crate.
+    pub fn version(&mut self, name: &str, version: &str) -> Result<IndexVersion> {
+        self.versions(name)?
+            .iter()
+            .find(|v| v.vers == version)
+            .cloned()
+            .ok_or_else(|| Error::msg(format!("{} {} not found in the index", name, version)))
+    }
+
+    /// Finds the highest version of the crate that's not yanked and
+    /// satisfies the requirement.
+    pub fn best_match(&mut self, name: &str, req: &VersionReq) -> Result<Option<IndexVersion>> {
+        Ok(self
+            .versions(name)?
+            .iter()
+            .filter(|v| !v.yanked)
+            .map(|v| (SemVer::parse(&v.vers), v))
+            .filter(|(semver, _)| req.matches(semver))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, v)| v.clone()))
+    }
+
+    /// Resolves the dependency graph of the given crate version, built with
+    /// the given features.
+    ///
+    /// Like cargo, features are unified and each dependency is resolved to
+    /// the highest matching version. Dev-dependencies aren't followed as
+    /// they're not needed for building the crate, while dependencies of all
+    /// targets are.
+    pub fn resolve(
+        &mut self,
+        name: &str,
+        version: &str,
+        features: &[String],
+        default_features: bool,
+    ) -> Result<Resolve> {
+        let mut packages = vec![Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: Vec::new(),
+            features: BTreeSet::new(),
+        }];
+        // features requested by the dependents of each package
+        let mut requested = vec![(
+            features.iter().cloned().collect::<BTreeSet<_>>(),
+            default_features,
+        )];
+        let mut by_version = HashMap::from([((name.to_string(), version.to_string()), 0)]);
+        let mut queue = VecDeque::from([0]);
+
+        while let Some(n) = queue.pop_front() {
+            let entry = self.version(&packages[n].name, &packages[n].version)?;
+            let (enabled, activated, forwarded) =
+                activate_features(&entry, &requested[n].0, requested[n].1);
+            packages[n].features = enabled;
+
+            let mut dependencies = Vec::new();
+            for dep in &entry.deps {
+                if dep.is_dev() || (dep.optional && !activated.contains(&dep.name)) {
+                    continue;
+                }
+                let found = match VersionReq::parse(&dep.req) {
+                    Some(req) => self.best_match(dep.crate_name(), &req)?,
+                    None => None,
+                };
+                // requirements nothing satisfies can't be followed
+                let found = match found {
+                    Some(found) => found,
+                    None => continue,
+                };
+                let mut features: BTreeSet<String> = dep.features.iter().cloned().collect();
+                features.extend(forwarded.get(&dep.name).cloned().unwrap_or_default());
+
+                let key = (found.name.clone(), found.vers.clone());
+                let m = match by_version.get(&key) {
+                    Some(&m) => {
+                        let (known, default) = &mut requested[m];
+                        if !features.is_subset(known) || (dep.default_features && !*default) {
+                            known.extend(features);
+                            *default |= dep.default_features;
+                            queue.push_back(m);
+                        }
+                        m
+                    }
+                    None => {
+                        packages.push(Package {
+                            name: found.name,
+                            version: found.vers,
+                            dependencies: Vec::new(),
+                            features: BTreeSet::new(),
+                        });
+                        requested.push((features, dep.default_features));
+                        by_version.insert(key, packages.len() - 1);
+                        queue.push_back(packages.len() - 1);
+                        packages.len() - 1
+                    }
+                };
+                if !dependencies.contains(&m) {
+                    dependencies.push(m);
+                }
+            }
+            packages[n].dependencies = dependencies;
+        }
+
+        Ok(Resolve { packages })
+    }
+}
+
+/// Works out the features enabled on the version, the optional
+/// dependencies they activate, and the features they enable on the
+/// dependencies.
+fn activate_features(
+    entry: &IndexVersion,
+    requested: &BTreeSet<String>,
+    default_features: bool,
+) -> (
+    BTreeSet<String>,
+    HashSet<String>,
+    HashMap<String, BTreeSet<String>>,
+) {
+    let all = entry.all_features();
+    let optional: HashSet<&str> = entry
+        .deps
+        .iter()
+        .filter(|d| d.optional)
+        .map(|d| d.name.as_str())
+        .collect();
+
+    let mut enabled = BTreeSet::new();
+    let mut activated = HashSet::new();
+    let mut forwarded: HashMap<String, BTreeSet<String>> = HashMap::new();
+    let mut stack: Vec<String> = requested.iter().cloned().collect();
+    if default_features && all.contains_key("default") {
+        stack.push("default".to_string());
+    }
+    while let Some(feature) = stack.pop() {
+        if let Some(dep) = feature.strip_prefix("dep:") {
+            activated.insert(dep.to_string());
+        } else if let Some((dep, dep_feature)) = feature.split_once('/') {
+            // weak `dep?/feature` doesn't activate the dependency itself
+            let dep = match dep.strip_suffix('?') {
+                Some(dep) => dep,
+                None => {
+                    activated.insert(dep.to_string());
+                    dep
+                }
+            };
+            forwarded
+                .entry(dep.to_string())
+                .or_default()
+                .insert(dep_feature.to_string());
+        } else if enabled.insert(feature.clone()) {
+            match all.get(&feature) {
+                Some(values) => stack.extend(values.iter().cloned()),
+                // optional dependencies are implicitly features too
+                None if optional.contains(feature.as_str()) => {
+                    activated.insert(feature);
+                }
+                None => (),
+            }
+        }
+    }
+    (enabled, activated, forwarded)
+}
+
+/// Returns the path of the crate file in the index.
+fn index_path(name: &str) -> String {
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    /// Creates an index with the given crate versions, without a cache so
+    /// that nothing is read from the disk. All the crates resolved need to
+    /// be included, any other ones would be fetched.
+    fn index(versions: &[serde_json::Value]) -> Index {
+        let mut index = Index::new(Cache::new(Duration::ZERO));
+        for version in versions {
+            let version: IndexVersion = serde_json::from_value(version.clone()).unwrap();
+            index
+                .crates
+                .entry(version.name.clone())
+                .or_default()
+                .push(version);
+        }
+        index
+    }
+
+    fn version(name: &str, vers: &str, deps: serde_json::Value) -> serde_json::Value {
+        json!({ "name": name, "vers": vers, "deps": deps, "features": {} })
+    }
+
+    fn dep(name: &str, req: &str) -> serde_json::Value {
+        json!({ "name": name, "req": req })
+    }
+
+    /// Returns the resolved packages as `name version [features]`, sorted.
+    fn resolved(index: &mut Index, features: &[&str], default_features: bool) -> Vec<String> {
+        let features: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+        let resolve = index
+            .resolve("app", "1.0.0", &features, default_features)
+            .unwrap();
+        let mut packages: Vec<String> = resolve
+            .packages
+            .iter()
+            .map(|p| {
+                let features: Vec<&str> = p.features.iter().map(|f| f.as_str()).collect();
+                format!("{} {} [{}]", p.name, p.version, features.join(","))
+            })
+            .collect();
+        packages.sort();
+        packages
+    }
+
+    #[test]
+    fn highest_matching_version() {
+        let mut index = index(&[
+            version("app", "1.0.0", json!([dep("a", "^1.2"), dep("b", "0.2")])),
+            version("a", "1.1.0", json!([])),
+            version("a", "1.3.0", json!([])),
+            json!({ "name": "a", "vers": "1.4.0", "deps": [], "features": {}, "yanked": true }),
+            version("a", "1.5.0-beta.1", json!([])),
+            version("a", "2.0.0", json!([])),
+            version("b", "0.2.1", json!([])),
+            version("b", "0.2.5", json!([])),
+            version("b", "0.3.0", json!([])),
+        ]);
+        assert_eq!(
+            resolved(&mut index, &[], true),
+            ["a 1.3.0 []", "app 1.0.0 []", "b 0.2.5 []"]
+        );
+    }
+
+    #[test]
+    fn shared_dependencies() {
+        let mut index = index(&[
+            version("app", "1.0.0", json!([dep("a", "1"), dep("b", "1")])),
+            version("a", "1.0.0", json!([dep("c", "1.0")])),
+            version("b", "1.0.0", json!([dep("c", "^1.1")])),
+            version("c", "1.1.0", json!([])),
+        ]);
+        let resolve = index.resolve("app", "1.0.0", &[], true).unwrap();
+        assert_eq!(resolve.packages.len(), 4);
+        let c = resolve.packages.iter().position(|p| p.name == "c").unwrap();
+        for package in &resolve.packages[1..] {
+            if package.name != "c" {
+                assert_eq!(package.dependencies, vec![c]);
+            }
+        }
+    }
+
+    #[test]
+    fn skip_optional_and_dev_dependencies() {
+        let mut index = index(&[
+            version(
+                "app",
+                "1.0.0",
+                json!([
+                    dep("a", "1"),
+                    { "name": "b", "req": "1", "optional": true },
+                    { "name": "c", "req": "1", "kind": "dev" },
+                    { "name": "d", "req": "1", "kind": "build" },
+                    { "name": "e", "req": "1", "target": "cfg(windows)" },
+                ]),
+            ),
+            version("a", "1.0.0", json!([])),
+            version("d", "1.0.0", json!([])),
+            version("e", "1.0.0", json!([])),
+        ]);
+        assert_eq!(
+            resolved(&mut index, &[], true),
+            ["a 1.0.0 []", "app 1.0.0 []", "d 1.0.0 []", "e 1.0.0 []"]
+        );
+    }
+
+    #[test]
+    fn default_features() {
+        let lib = |default_features: bool| {
+            json!({
+                "name": "app",
+                "vers": "1.0.0",
+                "deps": [{ "name": "lib", "req": "1", "default_features": default_features }],
+                "features": {},
+            })
+        };
+        let versions = [
+            json!({
+                "name": "lib",
+                "vers": "1.0.0",
+                "deps": [{ "name": "alloc", "req": "1", "optional": true }],
+                "features": { "default": ["std"], "std": ["dep:alloc"] },
+            }),
+            version("alloc", "1.0.0", json!([])),
+        ];
+
+        let mut with_default = index(&[&[lib(true)], &versions[..]].concat());
+        assert_eq!(
+            resolved(&mut with_default, &[], true),
+            ["alloc 1.0.0 []", "app 1.0.0 []", "lib 1.0.0 [default,std]"]
+        );
+        let mut without_default = index(&[&[lib(false)], &versions[..]].concat());
+        assert_eq!(
+            resolved(&mut without_default, &[], true),
+            ["app 1.0.0 []", "lib 1.0.0 []"]
+        );
+    }
+
+    #[test]
+    fn explicit_features() {
+        let mut index = index(&[
+            json!({
+                "name": "app",
+                "vers": "1.0.0",
+                "deps": [
+                    { "name": "a", "req": "1", "optional": true },
+                    { "name": "b", "req": "1", "optional": true },
+                    { "name": "c", "req": "1", "optional": true },
+                    { "name": "renamed", "req": "1", "optional": true, "package": "d" },
+                ],
+                "features": { "default": ["c"], "full": ["a/extra", "b?/x"] },
+                "features2": { "d": ["dep:renamed"] },
+            }),
+            json!({ "name": "a", "vers": "1.0.0", "deps": [],
+                "features": { "extra": [] } }),
+            json!({ "name": "b", "vers": "1.0.0", "deps": [],
+                "features": { "x": [] } }),
+            version("c", "1.0.0", json!([])),
+            version("d", "1.0.0", json!([])),
+        ]);
+        // `b?/x` doesn't activate `b`, and the defaults are left out
+        assert_eq!(
+            resolved(&mut index, &["full"], false),
+            ["a 1.0.0 [extra]", "app 1.0.0 [full]"]
+        );
+        // optional dependencies are features too, `b?/x` applies once `b`
+        // is activated
+        assert_eq!(
+            resolved(&mut index, &["full", "b", "d"], true),
+            [
+                "a 1.0.0 [extra]",
+                "app 1.0.0 [b,c,d,default,full]",
+                "b 1.0.0 [x]",
+                "c 1.0.0 []",
+                "d 1.0.0 []",
+            ]
+        );
+    }
+
+    #[test]
+    fn unified_features() {
+        let mut index = index(&[
+            json!({
+                "name": "app",
+                "vers": "1.0.0",
+                "deps": [
+                    { "name": "a", "req": "1" },
+                    { "name": "lib", "req": "1", "features": ["one"], "default_features": false },
+                ],
+                "features": {},
+            }),
+            json!({
+                "name": "a",
+                "vers": "1.0.0",
+                "deps": [{ "name": "lib", "req": "1", "features": ["two"] }],
+                "features": {},
+            }),
+            json!({
+                "name": "lib",
+                "vers": "1.0.0",
+                "deps": [],
+                "features": { "default": [], "one": [], "two": [] },
+            }),
+        ]);
+        assert_eq!(
+            resolved(&mut index, &[], true),
+            ["a 1.0.0 []", "app 1.0.0 []", "lib 1.0.0 [default,one,two]"]
+        );
+    }
+}
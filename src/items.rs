@@ -1,9 +1,21 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 
 use chrono::{DateTime, Utc};
 use consecrates::api::{CrateLinks, User, VersionLinks};
+use http_req::uri::Uri;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::api::{Dependency, Version};
+use crate::features::Features;
+use crate::index::Resolve;
+use crate::readme::Readme;
+use crate::repo::RepoInfo;
+use crate::stats::Stats;
+use crate::version::SemVer;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Crate {
     pub id: String,
     pub name: String,
@@ -22,5 +34,257 @@ pub(crate) struct Crate {
     pub updated_at: DateTime<Utc>,
     pub exact_match: Option<bool>,
 
-    pub readme: Option<String>,
+    #[serde(skip)]
+    pub readme: Remote<Readme>,
+    #[serde(skip)]
+    pub repo_info: Remote<RepoInfo>,
+    #[serde(skip)]
+    pub stats: Remote<Stats>,
+    #[serde(skip)]
+    pub dependencies: Remote<Vec<Dependency>>,
+    #[serde(skip)]
+    pub reverse_dependencies: Remote<u64>,
+    /// Minimum supported Rust version of the max version, if declared
+    #[serde(skip)]
+    pub msrv: Remote<Option<String>>,
+    /// Dependency graph of the max version built with default features
+    #[serde(skip)]
+    pub tree: Remote<Resolve>,
+    /// All the published versions, newest first
+    #[serde(skip)]
+    pub versions: Remote<Vec<Version>>,
+    /// Features declared by the max version
+    #[serde(skip)]
+    pub features: Remote<Features>,
+}
+
+impl Crate {
+    /// Returns the host of the crate repository, e.g. `github.com`.
+    pub fn repo_host(&self) -> Option<String> {
+        let uri = Uri::try_from(self.repository.as_deref()?).ok()?;
+        uri.host().map(|host| host.to_string())
+    }
+
+    /// Returns the number of regular dependencies, if they're fetched.
+    pub fn dependency_count(&self) -> Option<usize> {
+        self.dependencies
+            .ready()
+            .map(|deps| deps.iter().filter(|d| d.kind == "normal").count())
+    }
+
+    /// Takes over the data already fetched for the same crate, e.g. when
+    /// it shows up again in updated search results. Pending fetches are
+    /// not taken over.
+    pub fn keep_fetched(&mut self, old: &mut Crate) {
+        self.readme.keep_from(&mut old.readme);
+        self.repo_info.keep_from(&mut old.repo_info);
+        self.stats.keep_from(&mut old.stats);
+        self.dependencies.keep_from(&mut old.dependencies);
+        self.reverse_dependencies
+            .keep_from(&mut old.reverse_dependencies);
+        self.msrv.keep_from(&mut old.msrv);
+        self.tree.keep_from(&mut old.tree);
+        self.versions.keep_from(&mut old.versions);
+        self.features.keep_from(&mut old.features);
+    }
+}
+
+/// Crate data that's fetched lazily in the background.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Remote<T> {
+    /// Data was not requested yet
+    #[default]
+    Missing,
+    /// Request is in progress
+    Loading,
+    Ready(T),
+    Failed(String),
+}
+
+impl<T> Remote<T> {
+    /// Returns the data if it's ready.
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            Remote::Ready(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Takes the result of a finished fetch from the other one.
+    fn keep_from(&mut self, other: &mut Remote<T>) {
+        if matches!(other, Remote::Ready(_) | Remote::Failed(_)) {
+            *self = std::mem::take(other);
+        }
+    }
+}
+
+/// Field the results can be sorted by locally.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SortKey {
+    Name,
+    Downloads,
+    RecentDownloads,
+    Created,
+    Updated,
+    Version,
+    License,
+    Categories,
+    RepoHost,
+    ReverseDependencies,
+    Dependencies,
+    Msrv,
+}
+
+impl SortKey {
+    /// Label shown in the results title.
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Downloads => "downloads",
+            SortKey::RecentDownloads => "recent downloads",
+            SortKey::Created => "created",
+            SortKey::Updated => "updated",
+            SortKey::Version => "version",
+            SortKey::License => "license",
+            SortKey::Categories => "categories",
+            SortKey::RepoHost => "repo host",
+            SortKey::ReverseDependencies => "reverse deps",
+            SortKey::Dependencies => "deps",
+            SortKey::Msrv => "msrv",
+        }
+    }
+
+    /// Checks whether the key uses data fetched lazily, in which case the
+    /// sorting needs to be reapplied as the data comes in.
+    pub fn is_lazy(self) -> bool {
+        matches!(
+            self,
+            SortKey::ReverseDependencies | SortKey::Dependencies | SortKey::Msrv
+        )
+    }
+
+    /// Compares the crates in the natural order of the key, i.e.
+    /// alphabetically for text and from the largest value otherwise.
+    pub fn compare(self, a: &Crate, b: &Crate) -> Ordering {
+        match self {
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Downloads => b.downloads.cmp(&a.downloads),
+            SortKey::RecentDownloads => b.recent_downloads.cmp(&a.recent_downloads),
+            SortKey::Created => b.created_at.cmp(&a.created_at),
+            SortKey::Updated => b.updated_at.cmp(&a.updated_at),
+            SortKey::Version => SemVer::parse(&b.max_version).cmp(&SemVer::parse(&a.max_version)),
+            SortKey::License => a.license.cmp(&b.license),
+            SortKey::Categories => a.categories.cmp(&b.categories),
+            SortKey::RepoHost => a.repo_host().cmp(&b.repo_host()),
+            // crates with the data missing go last
+            SortKey::ReverseDependencies => b
+                .reverse_dependencies
+                .ready()
+                .cmp(&a.reverse_dependencies.ready()),
+            SortKey::Dependencies => b.dependency_count().cmp(&a.dependency_count()),
+            SortKey::Msrv => {
+                let msrv = |c: &Crate| c.msrv.ready().cloned().flatten().map(|v| SemVer::parse(&v));
+                msrv(b).cmp(&msrv(a))
+            }
+        }
+    }
+}
+
+/// Sorts the crates by the given key, in reverse if the flag is set.
+///
+/// Sorting is stable, crates that compare equal stay in the order they
+/// were found in, also when reversed.
+pub(crate) fn sort(items: &mut [Crate], key: SortKey, reversed: bool) {
+    items.sort_by(|a, b| match reversed {
+        true => key.compare(b, a),
+        false => key.compare(a, b),
+    });
+}
+
+/// Creates a crate with only the name and the version set.
+#[cfg(test)]
+pub(crate) fn test_crate(name: &str, version: &str) -> Crate {
+    serde_json::from_value(serde_json::json!({
+        "id": name,
+        "name": name,
+        "downloads": 0,
+        "max_version": version,
+        "links": {
+            "owner_team": "",
+            "owner_user": "",
+            "owners": "",
+            "reverse_dependencies": "",
+            "version_downloads": "",
+        },
+        "created_at": "2020-01-01T00:00:00Z",
+        "updated_at": "2020-01-01T00:00:00Z",
+    }))
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(items: &[Crate]) -> Vec<&str> {
+        items.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn sorted(items: &[Crate], key: SortKey, reversed: bool) -> Vec<String> {
+        let mut items = items.to_vec();
+        sort(&mut items, key, reversed);
+        names(&items).into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn sort_keys() {
+        let mut items = vec![
+            test_crate("b", "0.10.0"),
+            test_crate("A", "1.0.0-rc.1"),
+            test_crate("c", "0.9.3"),
+            test_crate("d", "1.0.0"),
+        ];
+        for (n, downloads) in [20, 10, 30, 20].iter().enumerate() {
+            items[n].downloads = *downloads;
+            items[n].updated_at += chrono::Duration::days(n as i64);
+        }
+
+        // text alphabetically ignoring case, everything else from the
+        // largest value
+        assert_eq!(sorted(&items, SortKey::Name, false), ["A", "b", "c", "d"]);
+        assert_eq!(
+            sorted(&items, SortKey::Updated, false),
+            ["d", "c", "A", "b"]
+        );
+        // versions by semver precedence, not as text
+        assert_eq!(
+            sorted(&items, SortKey::Version, false),
+            ["d", "A", "b", "c"]
+        );
+        // crates with equal values stay in the order they were found in
+        assert_eq!(
+            sorted(&items, SortKey::Downloads, false),
+            ["c", "b", "d", "A"]
+        );
+        assert_eq!(
+            sorted(&items, SortKey::Downloads, true),
+            ["A", "b", "d", "c"]
+        );
+    }
+
+    #[test]
+    fn sort_lazy_keys() {
+        let mut items = vec![
+            test_crate("a", "1.0.0"),
+            test_crate("b", "1.0.0"),
+            test_crate("c", "1.0.0"),
+        ];
+        items[0].msrv = Remote::Ready(Some("1.56".to_string()));
+        items[1].msrv = Remote::Loading;
+        items[2].msrv = Remote::Ready(Some("1.70".to_string()));
+        assert!(SortKey::Msrv.is_lazy());
+        // crates with the data missing go last
+        assert_eq!(sorted(&items, SortKey::Msrv, false), ["c", "a", "b"]);
+        assert_eq!(sorted(&items, SortKey::Msrv, true), ["b", "a", "c"]);
+    }
 }
@@ -1,33 +1,75 @@
 #![allow(unused)]
 
-use std::convert::TryFrom;
 use std::io::{self, Write};
 use std::ops::Sub;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use consecrates::api::{CrateResponse, Crates};
+use consecrates::api::CrateResponse;
 use consecrates::Client;
 
-use anyhow::Result;
+use anyhow::{Error, Result};
 use chrono::{DateTime, Utc};
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
-use http_req::uri::Uri;
-use tui::backend::CrosstermBackend;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
-use tui::text::{Span, Spans};
+use tui::text::{Span, Spans, Text};
 use tui::widgets::{
-    self, Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Tabs, Widget, Wrap,
+    self, Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Table, Tabs, Widget,
+    Wrap,
 };
 use tui::Terminal;
 
-use items::Crate;
+use cache::Cache;
+use compare::{Column, CompareView};
+use dependents::Dependents;
+use deps::DepDiff;
+use features::{FeaturePicker, Target};
+use index::Index;
+use items::{Crate, Remote, SortKey};
+use manifest::{DependencySpec, DiffLine, ManifestEdit, Section};
+use offline::Dump;
+use pinned::Pinned;
+use pool::{catch_panic, FetchPool};
+use query::{Completion, Query, Vocabulary};
+use repo::RepoHosts;
+use search::{Page, Searcher};
+use terminal::TerminalGuard;
+use tree::TreeView;
+use workspace::Workspace;
 
 #[cfg(feature = "clipboard")]
 use clipboard::ClipboardProvider;
 
+mod api;
+mod cache;
+mod compare;
+mod dependents;
+mod deps;
+mod dirs;
+mod features;
+mod highlight;
+mod html;
+mod http;
+mod index;
 mod items;
+mod manifest;
+mod markdown;
+mod offline;
+mod pinned;
+mod pool;
+mod query;
+mod readme;
+mod repo;
+mod search;
+mod stats;
+mod terminal;
+mod tree;
+mod version;
+mod versions;
+mod workspace;
 
 pub const INTRO: &str = r#"
                   __
@@ -67,19 +109,38 @@ pub const HELP: &str = r#"
 
 # search mode
 <C-s> clear input
+<C-l> toggle live search, updating the results as you type
 <Enter> perform the search and focus the results block
-<Escape> | <C-r> focus the results block
+<Tab> complete filter names and values, repeat to cycle
+<Escape> cancel the search in progress, or focus the results block
+<C-r> focus the results block
 <C-q> | <C-c> quit
 
 # results mode
-<Escape> | <C-s> focus the search bar
+<Escape> cancel the search in progress, or focus the search bar
+<C-s> focus the search bar
 <k>, <j>, <up>, <down> move up and down the results
+<m> load more results, also done when moving past the last result
+<s><n|d|r|N|u|v> sort results by name, downloads, recent downloads, newly
+    added, updated or version, repeat to reverse
 <h>, <l>, <left>, <right> move left and right between result tabs
-<C-u>, <C-d> scroll up and down the readme view
+<J>, <K> move up and down the dependency tree
+<Space> expand or collapse the dependency tree node
+<C-u>, <C-d> scroll up and down the readme and versions views
+<n><o> open readme link [n] (browser, or search if it's a crate)
 <C-g> go to documentation (browser)
 <C-r> go to repository (browser)
 <Enter> go to crate (browser)
-<c> copy Cargo.toml dependency line to clipboard
+<[>, <]> select the previous or next compare tab column
+<S> sort by the selected compare tab column, repeat to reverse
+<C> pick the compare tab columns
+<p> pin or unpin the crate for comparison across searches
+<P> switch between the results and the pinned crates
+<D> mark the crate for a dependency diff, again on another crate to show it
+<R> list the crates depending on the crate, <Enter> goes to the one selected
+<a> pick features and add the crate to the nearest Cargo.toml, showing the
+    change first
+<c> pick features and copy the Cargo.toml dependency line to clipboard
 <x> copy clone+compile+run one-liner to clipboard
 <C-q> | <C-c> | <q> quit
 
@@ -87,6 +148,9 @@ pub const HELP: &str = r#"
 
 pub const README_SCROLL_AMOUNT: u16 = 8;
 
+/// Time since the last change to the input after which live search starts.
+pub const LIVE_SEARCH_DELAY: Duration = Duration::from_millis(300);
+
 /// Specifies current cursor location.
 enum Mode {
     Search,
@@ -98,7 +162,29 @@ enum InfoScreen {
     Help,
 }
 
-const TAB_TITLES: [&str; 5] = ["Summary", "Compare", "Readme", "Repository", "Stats"];
+/// Error shown in a popup on top of the interface.
+struct ErrorPopup {
+    message: String,
+    /// Failed action, if it can be retried
+    retry: Option<Retry>,
+}
+
+/// Actions that can be retried after failing.
+enum Retry {
+    Search(String),
+    MoreResults,
+    Summary,
+}
+
+const TAB_TITLES: [&str; 7] = [
+    "Summary",
+    "Compare",
+    "Readme",
+    "Repository",
+    "Stats",
+    "Tree",
+    "Versions",
+];
 
 /// List of crate items.
 ///
@@ -112,58 +198,156 @@ struct CratesList {
     items: Arc<Mutex<Vec<Crate>>>,
     /// Current state of the user-facing list interface
     list_state: ListState,
-    /// Current vertical offset of the readme viewport
-    readme_scroll: u16,
+    /// Current vertical offset of the readme and versions viewports
+    scroll: u16,
+    /// Workers fetching additional crate data, dropping the list cancels
+    /// any pending fetches
+    pool: FetchPool,
+    /// Query the crates were found with
+    query: String,
+    /// Number of the last page of results loaded
+    page: usize,
+    /// Total number of results available
+    total: u64,
+    /// Number of results left out by local filters so far
+    filtered: u64,
+    /// Number of results skipped on later pages as they were already listed
+    skipped: u64,
+    /// Local sorting of the results, reversed if the flag is set
+    sorting: Option<(SortKey, bool)>,
+    /// Database dump the readmes are read from in offline mode
+    dump: Option<Arc<Dump>>,
+    /// Compare tab columns the data was queued for, reset when the items
+    /// change
+    fetched_columns: Option<Vec<Column>>,
 }
 
 impl CratesList {
-    /// Creates a new `Crates` object using a list of `Crate` items.
-    fn new(items: Vec<Crate>) -> Self {
-        let items_arc = Arc::new(Mutex::new(items));
-        let items_arc_clone = items_arc.clone();
-
-        // spawn a new thread that will query crates' readmes
-        //TODO this is a quick and dirty approach with sequential queries
-        // using a global lock on the crate list
-        //TODO find a better way to get crate readmes
-        std::thread::spawn(move || 'outer: loop {
-            std::thread::sleep(Duration::from_millis(100));
-            let mut items = items_arc_clone.lock().unwrap().clone();
-            for (n, item) in &mut items.iter().enumerate() {
-                if item.readme.is_none() && item.repository.is_some() {
-                    let repo_url = item.repository.clone().unwrap();
-                    let repo_short = format!(
-                        "{}/{}",
-                        repo_url.rsplitn(3, '/').collect::<Vec<&str>>()[1],
-                        repo_url.rsplitn(3, '/').collect::<Vec<&str>>()[0]
-                    );
-
-                    // this only works for github/gitlab repos with master branch
-                    let url = if repo_url.contains("github") {
-                        format!(
-                            "https://raw.githubusercontent.com/{}/master/README.md",
-                            repo_short
-                        )
-                    } else if repo_url.contains("gitlab") {
-                        format!("{}/raw/master/README.md", repo_url)
-                    } else {
-                        continue;
-                    };
+    /// Creates a new `Crates` object using a page of search results found
+    /// with the given query.
+    ///
+    /// Readmes of all the crates are queued for fetching in the background.
+    fn new(query: String, page: Page, cache: &Arc<Cache>, dump: &Option<Arc<Dump>>) -> Self {
+        let len = page.crates.len();
+        let list = CratesList {
+            items: Arc::new(Mutex::new(page.crates)),
+            list_state: ListState::default(),
+            scroll: 0,
+            pool: FetchPool::default(),
+            query,
+            page: page.number,
+            total: page.total,
+            filtered: page.filtered,
+            skipped: 0,
+            sorting: None,
+            dump: dump.clone(),
+            fetched_columns: None,
+        };
+        list.fetch_readmes(len, cache);
+        list
+    }
 
-                    let mut buffer = vec![];
-                    if let Ok(resp) = http_req::request::get(url, &mut buffer) {
-                        if let Ok(s) = String::from_utf8(buffer) {
-                            items_arc_clone.lock().unwrap().get_mut(n).unwrap().readme = Some(s);
-                        }
-                    }
+    /// Replaces the crate items in place, keeping the selection on the same
+    /// crate if it's still present.
+    ///
+    /// Data already fetched for crates present in both lists is kept.
+    fn replace(&mut self, query: String, page: Page, cache: &Arc<Cache>) {
+        let selected_id = self.selected().map(|c| c.id);
+        let mut items = page.crates;
+        self.query = query;
+        self.page = page.number;
+        self.total = page.total;
+        self.filtered = page.filtered;
+        self.skipped = 0;
+        {
+            let mut old_items = self.items.lock().unwrap();
+            for item in &mut items {
+                if let Some(old) = old_items.iter_mut().find(|o| o.id == item.id) {
+                    // pending fetches are cancelled along with the old pool
+                    item.keep_fetched(old);
                 }
             }
-        });
+            *old_items = items;
+        }
+        self.pool = FetchPool::default();
+        self.fetched_columns = None;
+        let len = self.items.lock().unwrap().len();
+        self.fetch_readmes(len, cache);
+
+        let position =
+            selected_id.and_then(|id| self.items.lock().unwrap().iter().position(|c| c.id == id));
+        match position {
+            // keep the scroll as it's still the same crate
+            Some(n) => {
+                let scroll = self.scroll;
+                self.select(Some(n));
+                self.scroll = scroll;
+            }
+            None => self.select(Some(0)),
+        }
+        self.sort();
+    }
 
-        CratesList {
-            items: items_arc,
-            list_state: ListState::default(),
-            readme_scroll: 0,
+    /// Sorts the results locally by the given key, reversing the order if
+    /// they're already sorted by it.
+    fn sort_by(&mut self, key: SortKey) {
+        self.sorting = match self.sorting {
+            Some((current, reversed)) if current == key => Some((key, !reversed)),
+            _ => Some((key, false)),
+        };
+        self.sort();
+    }
+
+    /// Applies the local sorting, keeping the selection on the same crate.
+    fn sort(&mut self) {
+        let (key, reversed) = match self.sorting {
+            Some(sorting) => sorting,
+            None => return,
+        };
+        let selected_id = self.selected().map(|c| c.id);
+        let position = {
+            let mut items = self.items.lock().unwrap();
+            items::sort(&mut items, key, reversed);
+            selected_id.and_then(|id| items.iter().position(|c| c.id == id))
+        };
+        if position.is_some() {
+            let scroll = self.scroll;
+            self.select(position);
+            self.scroll = scroll;
+        }
+    }
+
+    /// Returns a copy of the currently selected crate.
+    fn selected(&self) -> Option<Crate> {
+        let n = self.list_state.selected()?;
+        self.items.lock().unwrap().get(n).cloned()
+    }
+
+    /// Removes the crate with the given id, moving the selection to the
+    /// following crate.
+    fn remove(&mut self, id: &str) {
+        self.items.lock().unwrap().retain(|c| c.id != id);
+        self.select(self.list_state.selected());
+    }
+
+    /// Queues readme fetches for the first `len` crates.
+    fn fetch_readmes(&self, len: usize, cache: &Arc<Cache>) {
+        for n in 0..len {
+            match &self.dump {
+                Some(dump) => {
+                    let dump = dump.clone();
+                    self.fetch(n, |c| &mut c.readme, move |c| dump.readme(&c.id), false);
+                }
+                None => {
+                    let cache = cache.clone();
+                    self.fetch(
+                        n,
+                        |c| &mut c.readme,
+                        move |c| readme::fetch_readme(c, &cache),
+                        false,
+                    );
+                }
+            }
         }
     }
 
@@ -172,6 +356,132 @@ impl CratesList {
         self.items.lock().unwrap().push(item);
     }
 
+    /// Appends the next page of search results, skipping crates that are
+    /// already listed as results can shift between pages.
+    fn add_page(&mut self, page: Page, cache: &Arc<Cache>) {
+        let exhausted = page.crates.is_empty() && page.filtered == 0;
+        for item in page.crates {
+            if self.items.lock().unwrap().iter().any(|c| c.id == item.id) {
+                self.skipped += 1;
+            } else {
+                self.add(item);
+            }
+        }
+        self.page = page.number;
+        self.total = page.total;
+        self.filtered += page.filtered;
+        if exhausted {
+            // the total can be off when results change while paging
+            self.total = self.items.lock().unwrap().len() as u64 + self.filtered + self.skipped;
+        }
+        self.fetched_columns = None;
+        let len = self.items.lock().unwrap().len();
+        self.fetch_readmes(len, cache);
+        self.sort();
+    }
+
+    /// Checks whether there are more results to be loaded.
+    fn has_more(&self) -> bool {
+        (self.items.lock().unwrap().len() as u64) + self.filtered + self.skipped < self.total
+    }
+
+    /// Queues a background fetch of a piece of data for the crate at the
+    /// given index, unless it was already requested.
+    ///
+    /// `field` points at the crate field where the result will be stored.
+    /// Prioritized fetches are put at the front of the queue.
+    fn fetch<T, F, G>(&self, n: usize, field: F, fetch: G, priority: bool)
+    where
+        T: Send + 'static,
+        F: Fn(&mut Crate) -> &mut Remote<T> + Send + 'static,
+        G: FnOnce(&Crate) -> Result<T> + Send + 'static,
+    {
+        let item = {
+            let mut items = self.items.lock().unwrap();
+            let item = match items.get_mut(n) {
+                Some(i) => i,
+                None => return,
+            };
+            let remote = field(item);
+            if !matches!(remote, Remote::Missing) {
+                return;
+            }
+            *remote = Remote::Loading;
+            item.clone()
+        };
+
+        let items_arc = self.items.clone();
+        let id = item.id.clone();
+        let job = move || {
+            let result = catch_panic(|| fetch(&item));
+            // the list might have changed in the meantime, find by id
+            if let Some(i) = items_arc
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find(|i| i.id == item.id)
+            {
+                *field(i) = match result {
+                    Ok(data) => Remote::Ready(data),
+                    Err(e) => Remote::Failed(e.to_string()),
+                };
+            }
+        };
+        if priority {
+            self.pool.push_front(&id, job);
+        } else {
+            self.pool.push(&id, job);
+        }
+    }
+
+    /// Queues fetches of the lazily loaded data shown in the given compare
+    /// tab columns, unless they're already queued for the current items.
+    fn fetch_columns(&mut self, columns: &[Column]) {
+        if self.fetched_columns.as_deref() == Some(columns) {
+            return;
+        }
+        self.fetched_columns = Some(columns.to_vec());
+        let len = self.items.lock().unwrap().len();
+        for column in columns {
+            for n in 0..len {
+                match column {
+                    Column::ReverseDependencies => self.fetch(
+                        n,
+                        |c| &mut c.reverse_dependencies,
+                        |c| api::get_reverse_dependency_count(&c.id),
+                        false,
+                    ),
+                    Column::Dependencies => self.fetch(
+                        n,
+                        |c| &mut c.dependencies,
+                        |c| api::get_dependencies(&c.id, &c.max_version),
+                        false,
+                    ),
+                    Column::Msrv => self.fetch(
+                        n,
+                        |c| &mut c.msrv,
+                        |c| Ok(api::get_crate_version(&c.id, &c.max_version)?.rust_version),
+                        false,
+                    ),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Fetches a piece of data for the currently selected crate in the
+    /// background, ahead of any other queued fetches.
+    fn fetch_selected<T, F, G>(&self, field: F, fetch: G)
+    where
+        T: Send + 'static,
+        F: Fn(&mut Crate) -> &mut Remote<T> + Send + 'static,
+        G: FnOnce(&Crate) -> Result<T> + Send + 'static,
+    {
+        if let Some(n) = self.list_state.selected() {
+            self.fetch(n, field, fetch, true);
+        }
+    }
+
     /// Selects crate in the collection based on the given index.
     /// If index is `None` deselects the current selection.
     fn select(&mut self, idx: Option<usize>) {
@@ -186,8 +496,15 @@ impl CratesList {
             self.list_state.select(idx);
         }
 
-        // reset the readme scroll on change to current selection
-        self.readme_scroll = 0;
+        // reset the scroll on change to current selection
+        self.scroll = 0;
+
+        // make sure pending fetches for the selected crate are done first
+        if let Some(n) = self.list_state.selected() {
+            if let Some(item) = self.items.lock().unwrap().get(n) {
+                self.pool.prioritize(&item.id);
+            }
+        }
     }
 
     /// Selects next crate in the collection.
@@ -199,7 +516,7 @@ impl CratesList {
     fn select_previous(&mut self, n: Option<usize>) {
         self.select(self.list_state.selected().map(|i| match i {
             0 => 0,
-            _ => i.checked_sub(n.unwrap_or(1)).unwrap_or(0),
+            _ => i.saturating_sub(n.unwrap_or(1)),
         }))
     }
 }
@@ -211,11 +528,30 @@ fn main() -> Result<()> {
     let mut get_summary = false;
     let mut search = String::new();
     let mut blazing_fast = false;
+    let mut cache_ttl = cache::DEFAULT_TTL;
+    let mut live_search = false;
+    let mut offline = None;
 
     for arg in args {
         // showing registry summary is optional
         if arg == "--summary" {
             get_summary = true;
+        } else if arg == "--offline" {
+            offline = Some(
+                offline::default_dir()
+                    .ok_or_else(|| Error::msg("can't determine the dump location"))?,
+            );
+        } else if let Some(dir) = arg.strip_prefix("--offline=") {
+            offline = Some(PathBuf::from(dir));
+        } else if arg == "--live" {
+            live_search = true;
+        } else if arg == "--no-cache" {
+            cache_ttl = Duration::ZERO;
+        } else if let Some(minutes) = arg.strip_prefix("--cache-ttl=") {
+            let minutes: u64 = minutes
+                .parse()
+                .map_err(|_| Error::msg(format!("invalid cache ttl: {}", minutes)))?;
+            cache_ttl = Duration::from_secs(minutes * 60);
         } else {
             // put any other input directly into the search window
             search.push_str(&format!("{arg} "));
@@ -225,30 +561,101 @@ fn main() -> Result<()> {
         blazing_fast = true;
     }
 
+    // load the database dump before taking over the terminal, as it takes
+    // a while
+    let dump = match offline {
+        Some(dir) => {
+            println!("loading crates.io database dump from {}...", dir.display());
+            Some(Arc::new(Dump::open(&dir)?))
+        }
+        None => None,
+    };
+
     #[cfg(feature = "clipboard")]
     let mut clipboard = clipboard::ClipboardContext::new().unwrap();
 
-    // set up tui using crossterm backend
+    // set up tui using crossterm backend, the guard restores the terminal
+    // on exit, including early returns on errors and panics
+    let _guard = TerminalGuard::new()?;
     let stdout = io::stdout();
-    crossterm::terminal::enable_raw_mode()?;
-    crossterm::execute!(io::stdout(), crossterm::terminal::EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     let mut stdout = io::stdout();
     terminal.clear()?;
 
     // create new crates.io client
-    let client = Client::new("crate_name_search (github.com/adamsky/cns)");
+    let client = Arc::new(Client::new(http::USER_AGENT));
+    // on-disk cache of search results and readmes
+    let cache = Arc::new(Cache::new(cache_ttl));
+    // repository hosting services used by the repository tab
+    let repo_hosts = Arc::new(RepoHosts::default());
+    // categories and keywords used for query validation and completion,
+    // loaded in the background as the query works fine without them
+    let vocabulary = Arc::new(Mutex::new(Vocabulary::default()));
+    {
+        let vocabulary = vocabulary.clone();
+        let dump = dump.clone();
+        std::thread::spawn(move || {
+            let loaded = match dump {
+                Some(dump) => dump.vocabulary(),
+                None => Vocabulary {
+                    categories: api::get_category_slugs().unwrap_or_default(),
+                    keywords: api::get_popular_keywords(3).unwrap_or_default(),
+                },
+            };
+            *vocabulary.lock().unwrap() = loaded;
+        });
+    }
+    // tab completion of the search input being cycled through
+    let mut completion: Option<Completion> = None;
 
     let mut intro_string = HELP.to_string();
-    // load up the registry summary data
-    if get_summary {
-        let summary = client.get_registry_summary()?;
-        intro_string = create_intro_string(summary)?;
-    }
+    // registry summary data is loaded on the first loop iteration
+    let mut load_summary = get_summary && dump.is_none();
+    // search to be started on the next loop iteration
+    let mut pending_search = None;
+    // searches running in the background
+    let mut searcher = Searcher::default();
+    // loading of further pages of results, requested by scrolling past the
+    // last result
+    let mut more_searcher = Searcher::default();
+    let mut pending_more = false;
+    // whether the results of the running search should take the focus,
+    // live searches update the results without interrupting typing
+    let mut focus_results = true;
+    // search input as of the last loop iteration, along with the time of
+    // its last change, used for live search
+    let mut live_input = String::new();
+    let mut live_input_changed: Option<Instant> = None;
+    let mut live_search_error = None;
+    // error to be shown over the interface
+    let mut error_popup: Option<ErrorPopup> = None;
 
     // initialize crate items list
     let mut crates = CratesList::default();
+    // columns and column sorting of the compare tab
+    let mut compare = CompareView::default();
+    // expanded nodes of the tree tab
+    let mut tree_view = TreeView::default();
+    // crates pinned for comparison, along with the search results hidden
+    // while only the pinned crates are shown
+    let mut pinned = Pinned::load();
+    let mut search_results: Option<CratesList> = None;
+    // crate marked for a dependency diff, and the diff shown over the
+    // results
+    let mut diff_base: Option<Crate> = None;
+    let mut dep_diff: Option<DepDiff> = None;
+    // crates depending on the selected crate, shown over the results
+    let mut dependents: Option<Dependents> = None;
+    // features picked for a dependency, and the dependency about to be
+    // added to the project manifest
+    let mut feature_picker: Option<FeaturePicker> = None;
+    let mut manifest_edit: Option<ManifestEdit> = None;
+    // project the application is run in, if any, used to mark the crates
+    // it already depends on
+    let mut workspace = std::env::current_dir()
+        .ok()
+        .and_then(|dir| Workspace::load(&dir));
 
     // start the application with the cursor on the search bar
     let mut current_mode = Mode::Search;
@@ -258,7 +665,6 @@ fn main() -> Result<()> {
     // set up application interface blocks
     let mut search_block_title = "Search".to_string();
     let mut search_block_text = search.trim().to_string();
-    let mut results_block_label = "Results".to_string();
     let mut results_current_tab = 0;
     let mut search_block_border_style = Style::default().fg(tui::style::Color::DarkGray);
     let mut results_block_border_style = Style::default().fg(tui::style::Color::DarkGray);
@@ -266,20 +672,138 @@ fn main() -> Result<()> {
 
     // go directly to results using the input provided on program startup
     if blazing_fast {
-        crates = CratesList::new(crate_query(&search_block_text, &client).unwrap());
-        crates.select(Some(0));
-        show_info = None;
-        current_mode = Mode::Results;
+        pending_search = Some(search_block_text.clone());
     }
 
     // store some information on previously pressed keys to support basic
     // vim-like shortcuts like `gg`, along with num prefixed ones like `5j`
-    let mut num_input = None;
+    let mut num_input: Option<u32> = None;
     let mut last_key = KeyEvent::new(KeyCode::Null, KeyModifiers::NONE);
     let mut previous_key = KeyEvent::new(KeyCode::Null, KeyModifiers::NONE);
 
     // start main application loop
     loop {
+        // load up the registry summary data
+        if load_summary {
+            load_summary = false;
+            match client.get_registry_summary().and_then(create_intro_string) {
+                Ok(intro) => intro_string = intro,
+                Err(e) => {
+                    error_popup = Some(ErrorPopup {
+                        message: format!("failed getting registry summary: {}", e),
+                        retry: Some(Retry::Summary),
+                    })
+                }
+            }
+        }
+
+        // search as you type once the input settles down
+        let mut live_pending = None;
+        if live_search {
+            if search_block_text != live_input {
+                live_input = search_block_text.clone();
+                live_input_changed = Some(Instant::now());
+            } else if live_input_changed.is_some_and(|t| t.elapsed() >= LIVE_SEARCH_DELAY) {
+                live_input_changed = None;
+                if !live_input.trim().is_empty() && query_error(&live_input, &vocabulary).is_none()
+                {
+                    live_pending = Some(live_input.clone());
+                }
+            }
+        }
+
+        // start the requested search in the background
+        let search = match pending_search.take() {
+            Some(input) => Some((input, true)),
+            None => live_pending.map(|input| (input, false)),
+        };
+        if let Some((input, focus)) = search {
+            focus_results = focus;
+            if focus {
+                live_input_changed = None;
+            }
+            live_search_error = None;
+            let cache = cache.clone();
+            let dump = dump.clone();
+            searcher.start(input, move |input| {
+                crate_query(input, None, &cache, dump.as_deref())
+            });
+            more_searcher.cancel();
+        }
+
+        // load the next page of results in the background
+        if pending_more
+            && crates.has_more()
+            && !searcher.is_searching()
+            && !more_searcher.is_searching()
+        {
+            let cache = cache.clone();
+            let dump = dump.clone();
+            let page = crates.page + 1;
+            more_searcher.start(crates.query.clone(), move |input| {
+                crate_query(input, Some(page), &cache, dump.as_deref())
+            });
+        }
+        pending_more = false;
+
+        // show the results once the search is done
+        if let Some((input, result)) = searcher.poll() {
+            match result {
+                Ok(page) if focus_results => {
+                    pinned.refresh(&page.crates);
+                    // leave the pinned view to show the new results
+                    search_results = None;
+                    crates = CratesList::new(input, page, &cache, &dump);
+                    crates.select(Some(0));
+                    show_info = None;
+                    current_mode = Mode::Results;
+                }
+                Ok(page) => {
+                    pinned.refresh(&page.crates);
+                    search_results
+                        .as_mut()
+                        .unwrap_or(&mut crates)
+                        .replace(input, page, &cache);
+                    show_info = None;
+                }
+                Err(e) if focus_results => {
+                    error_popup = Some(ErrorPopup {
+                        message: format!("search failed: {}", e),
+                        retry: Some(Retry::Search(input)),
+                    })
+                }
+                // don't interrupt typing with a popup
+                Err(e) => live_search_error = Some(e.to_string()),
+            }
+        }
+
+        if let Some((input, result)) = more_searcher.poll() {
+            let results = search_results.as_mut().unwrap_or(&mut crates);
+            match result {
+                // make sure the page follows the ones already loaded
+                Ok(page) if input == results.query && page.number == results.page + 1 => {
+                    pinned.refresh(&page.crates);
+                    results.add_page(page, &cache)
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    error_popup = Some(ErrorPopup {
+                        message: format!("failed loading more results: {}", e),
+                        retry: Some(Retry::MoreResults),
+                    })
+                }
+            }
+        }
+
+        search_block_title = match (live_search, &live_search_error) {
+            (true, Some(e)) => format!("Search (live, failed: {})", e),
+            (true, None) => "Search (live)".to_string(),
+            (false, _) => "Search".to_string(),
+        };
+        if let Some(e) = query_error(&search_block_text, &vocabulary) {
+            search_block_title = format!("{} - {}", search_block_title, e);
+        }
+
         // handle mode-specific changes
         match current_mode {
             Mode::Search => {
@@ -292,6 +816,75 @@ fn main() -> Result<()> {
             }
         }
 
+        // lazily fetch data needed by the currently visible tab
+        if show_info.is_none() && results_current_tab == 1 {
+            crates.fetch_columns(&compare.columns);
+            // keep the order up to date as the data comes in
+            if crates.sorting.is_some_and(|(key, _)| key.is_lazy()) {
+                crates.sort();
+            }
+        } else if show_info.is_none() && results_current_tab == 3 {
+            let repo_hosts = repo_hosts.clone();
+            crates.fetch_selected(
+                |c| &mut c.repo_info,
+                move |c| match &c.repository {
+                    Some(url) => repo_hosts.fetch(url),
+                    None => Err(Error::msg("crate doesn't specify a repository")),
+                },
+            );
+        } else if show_info.is_none() && results_current_tab == 4 {
+            match &dump {
+                Some(dump) => {
+                    let dump = dump.clone();
+                    crates.fetch_selected(|c| &mut c.stats, move |c| dump.stats(&c.id));
+                }
+                None => {
+                    let client = client.clone();
+                    crates.fetch_selected(
+                        |c| &mut c.stats,
+                        move |c| stats::fetch_stats(&client, &c.id),
+                    );
+                }
+            }
+        } else if show_info.is_none() && results_current_tab == 5 {
+            let cache = (*cache).clone();
+            crates.fetch_selected(
+                |c| &mut c.tree,
+                move |c| Index::new(cache).resolve(&c.id, &c.max_version, &[], true),
+            );
+        } else if show_info.is_none() && results_current_tab == 6 {
+            match &dump {
+                Some(dump) => {
+                    let dump = dump.clone();
+                    crates.fetch_selected(|c| &mut c.versions, move |c| dump.versions(&c.id));
+                }
+                None => {
+                    crates.fetch_selected(|c| &mut c.versions, |c| api::get_crate_versions(&c.id))
+                }
+            }
+        }
+
+        // the feature picker lists the features of the selected crate
+        if feature_picker.is_some() {
+            match &dump {
+                Some(dump) => {
+                    let dump = dump.clone();
+                    crates.fetch_selected(|c| &mut c.features, move |c| dump.features(&c.id));
+                }
+                None => {
+                    let cache = (*cache).clone();
+                    crates.fetch_selected(
+                        |c| &mut c.features,
+                        move |c| {
+                            Ok(Index::new(cache)
+                                .version(&c.id, &c.max_version)?
+                                .all_features())
+                        },
+                    );
+                }
+            }
+        }
+
         // draw the interface
         terminal
             .draw(|f| {
@@ -329,101 +922,103 @@ fn main() -> Result<()> {
                     .title(search_block_title.as_str())
                     .borders(Borders::ALL)
                     .border_style(search_block_border_style);
-                let search_block_text_final = match current_mode {
-                    Mode::Search => format!("{}|", search_block_text),
-                    _ => search_block_text.clone(),
-                };
-                let paragraph =
-                    Paragraph::new(search_block_text_final.as_str()).block(search_block);
+                let search_block_line = query::highlighted_input(
+                    &search_block_text,
+                    matches!(current_mode, Mode::Search),
+                    &vocabulary.lock().unwrap(),
+                );
+                let paragraph = Paragraph::new(search_block_line).block(search_block);
                 f.render_widget(paragraph, chunks_left[0]);
                 let items = crates.items.lock().unwrap();
                 let mut list_items: Vec<ListItem> = items
                     .iter()
-                    .map(|i| ListItem::new(i.name.as_str()))
+                    .map(|i| {
+                        let mut spans = vec![match pinned.contains(&i.id) {
+                            true => {
+                                Span::styled(i.name.as_str(), Style::default().fg(Color::Yellow))
+                            }
+                            false => Span::raw(i.name.as_str()),
+                        }];
+                        // crates the project depends on are marked, in red if
+                        // a newer version is out
+                        if let Some(ws) = &workspace {
+                            if let Some(status) = ws.status(i) {
+                                let color = if ws.is_behind(i) {
+                                    Color::Red
+                                } else {
+                                    Color::Green
+                                };
+                                spans.push(Span::styled(
+                                    format!(" [{}]", status),
+                                    Style::default().fg(color),
+                                ));
+                            }
+                        }
+                        ListItem::new(Spans::from(spans))
+                    })
                     .collect::<Vec<ListItem>>()
                     .clone();
 
                 let mut rect = chunks_left[1];
 
-                // some changes to results block are needed for the compare tab
+                let mut results_title = match searcher.spinner() {
+                    Some(frame) => format!("Results {} searching...", frame),
+                    None if search_results.is_some() => format!("Pinned ({})", items.len()),
+                    None if crates.filtered > 0 => format!(
+                        "Results ({} of {}, {} filtered out)",
+                        items.len(),
+                        crates.total,
+                        crates.filtered
+                    ),
+                    None if crates.total > 0 => {
+                        format!("Results ({} of {})", items.len(), crates.total)
+                    }
+                    None => "Results".to_string(),
+                };
+                if let Some((key, reversed)) = crates.sorting {
+                    results_title.push_str(&format!(
+                        " [by {}{}]",
+                        key.label(),
+                        if reversed { ", reversed" } else { "" }
+                    ));
+                }
+                if let Some(base) = &diff_base {
+                    results_title.push_str(&format!(" [diff: {} vs ?]", base.name));
+                }
+                if let Some(frame) = more_searcher.spinner() {
+                    results_title.push_str(&format!(" {} loading more...", frame));
+                }
+
+                // the compare tab shows the results as a table spanning the
+                // whole width
                 if results_current_tab == 1 && show_info.is_none() {
                     rect = chunks_vert[1];
-                    let comp_strings_titles = vec![
-                        "Since creation ".to_string(),
-                        "Since update ".to_string(),
-                        "All-time dl ".to_string(),
-                        "Recent dl ".to_string(),
-                        "Max version ".to_string(),
-                        "Repo host ".to_string(),
-                    ];
-                    let comp_strings_len: Vec<usize> =
-                        comp_strings_titles.iter().map(|cs| cs.len()).collect();
-
-                    let mut new_list_items = Vec::new();
-                    for item in items.iter() {
-                        let recent_downloads_string = match item.recent_downloads {
-                            Some(s) => s.to_string(),
-                            None => "n/a".to_string(),
-                        };
-                        let days_since_creation =
-                            Utc::now().sub(item.created_at).num_days().to_string();
-                        let days_since_update =
-                            Utc::now().sub(item.updated_at).num_days().to_string();
-                        let max_version = item.max_version.clone();
-                        let mut repo_host = "n/a".to_string();
-
-                        if let Some(repo_url) = &item.repository {
-                            if let Ok(uri) = Uri::try_from(repo_url.as_str()) {
-                                if let Some(host) = uri.host() {
-                                    repo_host = host.to_string();
-                                }
-                            }
-                        }
-
-                        let comp_strings = vec![
-                            days_since_creation,
-                            days_since_update,
-                            item.downloads.to_string(),
-                            recent_downloads_string,
-                            max_version,
-                            repo_host,
-                        ];
-                        let item_string = create_list_item_string(
-                            item.name.to_string(),
-                            comp_strings,
-                            comp_strings_len.clone(),
-                            ' ',
-                            '|',
-                            rect.width as usize,
-                        );
-                        let list_item = ListItem::new(Span::raw(item_string));
-                        new_list_items.push(list_item);
-                    }
-                    list_items = new_list_items;
-
-                    results_block_label.clear();
-                    results_block_label = create_list_item_string(
-                        "Results".to_string(),
-                        comp_strings_titles,
-                        comp_strings_len.clone(),
-                        '─',
-                        '─',
-                        rect.width as usize,
-                    );
+                    let (header, rows, widths) =
+                        compare.table(&items, crates.sorting, Utc::now(), workspace.as_ref());
+                    compare.table_state.select(crates.list_state.selected());
+                    let table = Table::new(rows)
+                        .header(header)
+                        .block(
+                            Block::default()
+                                .title(results_title)
+                                .borders(Borders::ALL)
+                                .border_style(results_block_border_style),
+                        )
+                        .widths(&widths)
+                        .column_spacing(2)
+                        .highlight_style(results_block_highlight_style);
+                    f.render_stateful_widget(table, rect, &mut compare.table_state);
                 } else {
-                    results_block_label = "Results".to_string();
+                    let results_block = Block::default()
+                        .title(results_title)
+                        .borders(Borders::ALL)
+                        .border_style(results_block_border_style);
+                    let results = List::new(list_items)
+                        .block(results_block)
+                        .highlight_style(results_block_highlight_style);
+                    f.render_stateful_widget(results, rect, &mut crates.list_state);
                 }
 
-                let mut results_block = Block::default()
-                    .title(results_block_label.as_str())
-                    .borders(Borders::ALL)
-                    .border_style(results_block_border_style);
-                let results = List::new(list_items)
-                    .block(results_block)
-                    .highlight_style(results_block_highlight_style);
-
-                f.render_stateful_widget(results, rect, &mut crates.list_state);
-
                 if let Some(info) = &show_info {
                     match info {
                         InfoScreen::Help => {
@@ -474,7 +1069,11 @@ fn main() -> Result<()> {
                                             Repository: {}\n",
                                             item.name,
                                             item.description.as_ref().unwrap_or(&"".to_string()),
-                                            item.max_version,
+                                            match workspace.as_ref().and_then(|w| w.status(item)) {
+                                                Some(status) =>
+                                                    format!("{} ({})", item.max_version, status),
+                                                None => item.max_version.clone(),
+                                            },
                                             item.homepage.clone().unwrap_or("n/a".to_string()),
                                             item.downloads,
                                             item.recent_downloads.unwrap_or(0),
@@ -509,46 +1108,342 @@ fn main() -> Result<()> {
                             let readme = match crates.list_state.selected() {
                                 Some(n) => {
                                     if let Some(item) = items.get(n) {
-                                        item.readme
-                                            .clone()
-                                            .unwrap_or("(downloading...)".to_string())
+                                        match &item.readme {
+                                            Remote::Ready(readme) => {
+                                                Text::from(readme.rendered.lines.clone())
+                                            }
+                                            Remote::Failed(e) => {
+                                                Text::raw(format!("failed getting readme: {}", e))
+                                            }
+                                            _ => Text::raw("(downloading...)"),
+                                        }
                                     } else {
-                                        "failed getting crate".to_string()
+                                        Text::raw("failed getting crate")
                                     }
                                 }
-                                None => "select a crate".to_string(),
+                                None => Text::raw("select a crate"),
                             };
                             f.render_widget(
-                                widgets::Paragraph::new(readme.as_str())
-                                    .scroll((crates.readme_scroll, 0))
+                                widgets::Paragraph::new(readme)
+                                    .scroll((crates.scroll, 0))
                                     .wrap(Wrap { trim: false })
                                     .block(Block::default().borders(Borders::NONE)),
                                 chunks_right[1],
                             );
                         }
                         3 => {
+                            let repo = match crates.list_state.selected() {
+                                Some(n) => {
+                                    if let Some(item) = items.get(n) {
+                                        match &item.repo_info {
+                                            Remote::Ready(info) => repo::create_repo_string(info),
+                                            Remote::Failed(e) => {
+                                                format!("failed getting repository: {}", e)
+                                            }
+                                            _ => "(downloading...)".to_string(),
+                                        }
+                                    } else {
+                                        "failed getting crate".to_string()
+                                    }
+                                }
+                                None => "select a crate".to_string(),
+                            };
                             f.render_widget(
-                                widgets::Paragraph::new("WIP")
+                                widgets::Paragraph::new(repo.as_str())
+                                    .wrap(Wrap { trim: false })
                                     .block(Block::default().borders(Borders::NONE)),
                                 chunks_right[1],
                             );
                         }
                         4 => {
-                            f.render_widget(
-                                widgets::Paragraph::new("WIP")
-                                    .block(Block::default().borders(Borders::NONE)),
-                                chunks_right[1],
-                            );
+                            let message = match crates.list_state.selected() {
+                                Some(n) => {
+                                    if let Some(item) = items.get(n) {
+                                        match &item.stats {
+                                            Remote::Ready(stats) => {
+                                                stats::render(f, chunks_right[1], stats);
+                                                None
+                                            }
+                                            Remote::Failed(e) => {
+                                                Some(format!("failed getting stats: {}", e))
+                                            }
+                                            _ => Some("(downloading...)".to_string()),
+                                        }
+                                    } else {
+                                        Some("failed getting crate".to_string())
+                                    }
+                                }
+                                None => Some("select a crate".to_string()),
+                            };
+                            if let Some(message) = message {
+                                f.render_widget(
+                                    widgets::Paragraph::new(message.as_str())
+                                        .wrap(Wrap { trim: false })
+                                        .block(Block::default().borders(Borders::NONE)),
+                                    chunks_right[1],
+                                );
+                            }
+                        }
+                        5 => {
+                            let message = match crates.list_state.selected() {
+                                Some(n) => {
+                                    if let Some(item) = items.get(n) {
+                                        match &item.tree {
+                                            Remote::Ready(resolve) => {
+                                                tree_view.show(&item.id);
+                                                tree::render(
+                                                    f,
+                                                    chunks_right[1],
+                                                    resolve,
+                                                    &mut tree_view,
+                                                );
+                                                None
+                                            }
+                                            Remote::Failed(e) => Some(format!(
+                                                "failed resolving dependencies: {}",
+                                                e
+                                            )),
+                                            _ => Some("(resolving...)".to_string()),
+                                        }
+                                    } else {
+                                        Some("failed getting crate".to_string())
+                                    }
+                                }
+                                None => Some("select a crate".to_string()),
+                            };
+                            if let Some(message) = message {
+                                f.render_widget(
+                                    widgets::Paragraph::new(message.as_str())
+                                        .wrap(Wrap { trim: false })
+                                        .block(Block::default().borders(Borders::NONE)),
+                                    chunks_right[1],
+                                );
+                            }
+                        }
+                        6 => {
+                            let message = match crates.list_state.selected() {
+                                Some(n) => {
+                                    if let Some(item) = items.get(n) {
+                                        match &item.versions {
+                                            Remote::Ready(list) => {
+                                                versions::render(
+                                                    f,
+                                                    chunks_right[1],
+                                                    list,
+                                                    crates.scroll as usize,
+                                                    Utc::now(),
+                                                );
+                                                None
+                                            }
+                                            Remote::Failed(e) => {
+                                                Some(format!("failed getting versions: {}", e))
+                                            }
+                                            _ => Some("(downloading...)".to_string()),
+                                        }
+                                    } else {
+                                        Some("failed getting crate".to_string())
+                                    }
+                                }
+                                None => Some("select a crate".to_string()),
+                            };
+                            if let Some(message) = message {
+                                f.render_widget(
+                                    widgets::Paragraph::new(message.as_str())
+                                        .wrap(Wrap { trim: false })
+                                        .block(Block::default().borders(Borders::NONE)),
+                                    chunks_right[1],
+                                );
+                            }
                         }
                         _ => (),
                     }
                 }
+
+                if let Some(diff) = &dep_diff {
+                    render_dep_diff(f, diff, chunks_vert[1]);
+                }
+                if let Some(dependents) = &mut dependents {
+                    dependents.render(f, chunks_vert[1]);
+                }
+                if let Some(picker) = &feature_picker {
+                    let features = crates
+                        .list_state
+                        .selected()
+                        .and_then(|n| items.get(n))
+                        .map(|item| item.features.clone())
+                        .unwrap_or_default();
+                    render_feature_picker(f, picker, &features);
+                }
+                if let Some(edit) = &manifest_edit {
+                    render_manifest_edit(f, edit);
+                }
+                if let Some(cursor) = compare.picker {
+                    render_column_picker(f, &compare, cursor);
+                }
+                if let Some(popup) = &error_popup {
+                    render_error_popup(f, popup);
+                }
             })
             .unwrap();
 
-        if let Event::Key(key_event) = read().unwrap() {
+        // redraw periodically so that data fetched in the background shows up
+        if !crossterm::event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+
+        if let Event::Key(key_event) = read()? {
             previous_key = last_key;
-            last_key = key_event.clone();
+            last_key = key_event;
+
+            // error popup takes over the input until dismissed
+            if let Some(popup) = error_popup.take() {
+                match key_event.code {
+                    KeyCode::Enter | KeyCode::Char('r') => match popup.retry {
+                        Some(Retry::Search(input)) => pending_search = Some(input),
+                        Some(Retry::MoreResults) => pending_more = true,
+                        Some(Retry::Summary) => load_summary = true,
+                        None => (),
+                    },
+                    KeyCode::Esc => (),
+                    _ => error_popup = Some(popup),
+                }
+                num_input = None;
+                continue;
+            }
+
+            // so does the compare tab column picker
+            if let Some(cursor) = compare.picker {
+                match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        compare.picker = Some(cursor.saturating_sub(1))
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        compare.picker = Some((cursor + 1).min(Column::ALL.len() - 1))
+                    }
+                    KeyCode::Enter | KeyCode::Char(' ') => compare.toggle_picked(),
+                    KeyCode::Esc | KeyCode::Char('C') | KeyCode::Char('q') => compare.picker = None,
+                    _ => (),
+                }
+                num_input = None;
+                continue;
+            }
+
+            // and the dependency diff
+            if let Some(diff) = &mut dep_diff {
+                match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => diff.scroll = diff.scroll.saturating_sub(1),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        diff.scroll = diff.scroll.saturating_add(1)
+                    }
+                    KeyCode::PageUp => {
+                        diff.scroll = diff.scroll.saturating_sub(README_SCROLL_AMOUNT)
+                    }
+                    KeyCode::PageDown => {
+                        diff.scroll = diff.scroll.saturating_add(README_SCROLL_AMOUNT)
+                    }
+                    KeyCode::Esc | KeyCode::Char('D') | KeyCode::Char('q') => dep_diff = None,
+                    _ => (),
+                }
+                num_input = None;
+                continue;
+            }
+
+            // and the list of dependents
+            if let Some(list) = &mut dependents {
+                match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => list.select_previous(),
+                    KeyCode::Down | KeyCode::Char('j') => list.select_next(),
+                    KeyCode::Char('m') => list.load_more(),
+                    KeyCode::Char('s') => list.reverse(),
+                    KeyCode::Enter => {
+                        if let Some(dependent) = list.selected() {
+                            search_block_text = dependent.name;
+                            pending_search = Some(search_block_text.clone());
+                            dependents = None;
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('R') | KeyCode::Char('q') => dependents = None,
+                    _ => (),
+                }
+                num_input = None;
+                continue;
+            }
+
+            // and the feature picker
+            if let Some(picker) = &mut feature_picker {
+                let features = crates.selected().and_then(|c| c.features.ready().cloned());
+                match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => picker.select_previous(),
+                    KeyCode::Down | KeyCode::Char('j') => picker.select_next(features.as_ref()),
+                    KeyCode::Char(' ') => picker.toggle(features.as_ref()),
+                    KeyCode::Enter => {
+                        match picker.target {
+                            #[cfg(feature = "clipboard")]
+                            Target::Clipboard => {
+                                clipboard.set_contents(picker.line());
+                            }
+                            #[cfg(not(feature = "clipboard"))]
+                            Target::Clipboard => (),
+                            Target::Manifest => {
+                                let edit = find_manifest().and_then(|path| {
+                                    ManifestEdit::new(
+                                        &path,
+                                        &picker.id,
+                                        picker.spec(),
+                                        Section::Normal,
+                                    )
+                                });
+                                match edit {
+                                    Ok(edit) => manifest_edit = Some(edit),
+                                    Err(e) => {
+                                        error_popup = Some(ErrorPopup {
+                                            message: e.to_string(),
+                                            retry: None,
+                                        })
+                                    }
+                                }
+                            }
+                        }
+                        feature_picker = None;
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => feature_picker = None,
+                    _ => (),
+                }
+                num_input = None;
+                continue;
+            }
+
+            // and the manifest change confirmation
+            if let Some(edit) = &mut manifest_edit {
+                match key_event.code {
+                    KeyCode::Tab => edit.next_section(),
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        match edit.write() {
+                            // the new dependency shows up in the results
+                            Ok(()) => {
+                                workspace = std::env::current_dir()
+                                    .ok()
+                                    .and_then(|dir| Workspace::load(&dir))
+                            }
+                            Err(e) => {
+                                error_popup = Some(ErrorPopup {
+                                    message: format!(
+                                        "failed writing {}: {}",
+                                        edit.path.display(),
+                                        e
+                                    ),
+                                    retry: None,
+                                })
+                            }
+                        }
+                        manifest_edit = None;
+                    }
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => manifest_edit = None,
+                    _ => (),
+                }
+                num_input = None;
+                continue;
+            }
 
             match current_mode {
                 // bindings for when the cursor is focused on search
@@ -574,40 +1469,53 @@ fn main() -> Result<()> {
                                 }
                             }
                             KeyCode::Char('r') => current_mode = Mode::Results,
-                            KeyCode::Char('s') => {
-                                if search_block_text.len() > 0 {
-                                    if let Some(space_idx) = search_block_text.find(" ") {
-                                        search_block_text =
-                                            search_block_text[0..space_idx].to_string();
-                                    } else {
-                                        search_block_text = "".to_string();
-                                    }
-                                };
+                            KeyCode::Char('l') => {
+                                live_search = !live_search;
+                                live_input = search_block_text.clone();
+                            }
+                            KeyCode::Char('s') if !search_block_text.is_empty() => {
+                                if let Some(space_idx) = search_block_text.find(' ') {
+                                    search_block_text = search_block_text[0..space_idx].to_string();
+                                } else {
+                                    search_block_text = "".to_string();
+                                }
                             }
                             KeyCode::Char('q') | KeyCode::Char('c') => break,
                             _ => (),
                         }
                     } else {
                         match key_event.code {
+                            KeyCode::Esc if searcher.is_searching() => searcher.cancel(),
                             KeyCode::Esc => current_mode = Mode::Results,
                             KeyCode::Char(k) => {
                                 search_block_text = format!("{}{}", search_block_text, k);
                             }
                             KeyCode::Backspace => {
-                                if search_block_text.len() > 0 {
-                                    search_block_text = search_block_text
-                                        [0..search_block_text.len() - 1]
-                                        .to_string()
-                                };
+                                search_block_text.pop();
                             }
-                            KeyCode::Enter => {
-                                crates = CratesList::new(
-                                    crate_query(&search_block_text, &client).unwrap(),
-                                );
-                                crates.select(Some(0));
-                                show_info = None;
-                                current_mode = Mode::Results;
+                            KeyCode::Tab => {
+                                let vocabulary = vocabulary.lock().unwrap();
+                                completion = match completion.take() {
+                                    // repeated tab cycles through the candidates
+                                    Some(mut c) if c.current() == search_block_text => {
+                                        c.advance();
+                                        Some(c)
+                                    }
+                                    _ => Completion::new(&search_block_text, &vocabulary),
+                                };
+                                if let Some(c) = &completion {
+                                    search_block_text = c.current();
+                                }
                             }
+                            KeyCode::Enter => match query_error(&search_block_text, &vocabulary) {
+                                Some(e) => {
+                                    error_popup = Some(ErrorPopup {
+                                        message: format!("invalid query: {}", e),
+                                        retry: None,
+                                    })
+                                }
+                                None => pending_search = Some(search_block_text.clone()),
+                            },
                             _ => (),
                         }
                     }
@@ -669,17 +1577,27 @@ fn main() -> Result<()> {
                             }
                             KeyCode::Char('d') => {
                                 if results_current_tab == 2 {
-                                    crates.readme_scroll += README_SCROLL_AMOUNT;
+                                    crates.scroll += README_SCROLL_AMOUNT;
+                                }
+                                // keep the last version in view
+                                if results_current_tab == 6 {
+                                    if let Some(Remote::Ready(list)) =
+                                        crates.selected().map(|c| c.versions)
+                                    {
+                                        let last = list.len().saturating_sub(1) as u16;
+                                        crates.scroll =
+                                            (crates.scroll + README_SCROLL_AMOUNT).min(last);
+                                    }
                                 }
                             }
                             KeyCode::Char('u') => {
-                                if results_current_tab == 2 {
-                                    let mut sub = crates.readme_scroll as isize
-                                        - README_SCROLL_AMOUNT as isize;
+                                if matches!(results_current_tab, 2 | 6) {
+                                    let mut sub =
+                                        crates.scroll as isize - README_SCROLL_AMOUNT as isize;
                                     if sub < 0 {
                                         sub = 0;
                                     }
-                                    crates.readme_scroll = sub as u16;
+                                    crates.scroll = sub as u16;
                                 }
                             }
                             // quit the application altogether
@@ -690,25 +1608,29 @@ fn main() -> Result<()> {
                     } else {
                         match key_event.code {
                             // focus the search mode
+                            KeyCode::Esc if searcher.is_searching() => searcher.cancel(),
                             KeyCode::Esc => current_mode = Mode::Search,
                             KeyCode::Left | KeyCode::Char('h') => {
-                                if results_current_tab > 0 {
-                                    results_current_tab -= 1
-                                }
+                                results_current_tab = results_current_tab.saturating_sub(1)
                             }
-                            KeyCode::Right | KeyCode::Char('l') => {
-                                if results_current_tab < TAB_TITLES.len() - 1 {
-                                    results_current_tab += 1
-                                }
+                            KeyCode::Right | KeyCode::Char('l')
+                                if results_current_tab < TAB_TITLES.len() - 1 =>
+                            {
+                                results_current_tab += 1
                             }
                             KeyCode::Up | KeyCode::Char('k') => match num_input {
                                 None => crates.select_previous(None),
                                 Some(n) => crates.select_previous(Some(n as usize)),
                             },
-                            KeyCode::Down | KeyCode::Char('j') => match num_input {
-                                None => crates.select_next(None),
-                                Some(n) => crates.select_next(Some(n as usize)),
-                            },
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                // scrolling past the last result loads more
+                                let len = crates.items.lock().unwrap().len();
+                                let n = num_input.unwrap_or(1) as usize;
+                                if crates.list_state.selected().map(|s| s + n) >= Some(len) {
+                                    pending_more = true;
+                                }
+                                crates.select_next(Some(n));
+                            }
                             // open crate page in the browser
                             KeyCode::Enter => {
                                 if let Some(selected_crate) = crates.list_state.selected() {
@@ -725,11 +1647,31 @@ fn main() -> Result<()> {
                                 }
                             }
                             KeyCode::Char(ch) => match ch {
+                                // sort the results locally, repeat to reverse
+                                'n' | 'd' | 'r' | 'N' | 'u' | 'v'
+                                    if previous_key.code == KeyCode::Char('s') =>
+                                {
+                                    crates.sort_by(match ch {
+                                        'n' => SortKey::Name,
+                                        'd' => SortKey::Downloads,
+                                        'r' => SortKey::RecentDownloads,
+                                        'N' => SortKey::Created,
+                                        'u' => SortKey::Updated,
+                                        _ => SortKey::Version,
+                                    });
+                                }
+                                '0' if num_input.is_some() => {
+                                    num_input = num_input.map(|n| n.saturating_mul(10));
+                                    continue;
+                                }
                                 '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
                                     match num_input {
                                         None => num_input = ch.to_digit(10),
                                         Some(n) => {
-                                            num_input = Some(n * 10 + ch.to_digit(10).unwrap())
+                                            num_input = Some(
+                                                n.saturating_mul(10)
+                                                    .saturating_add(ch.to_digit(10).unwrap()),
+                                            )
                                         }
                                     }
                                     continue;
@@ -741,22 +1683,182 @@ fn main() -> Result<()> {
                                         }
                                     }
                                 }
+                                // follow a numbered readme link
+                                'o' if results_current_tab == 2 => {
+                                    let link = crates.list_state.selected().and_then(|n| {
+                                        match &crates.items.lock().unwrap().get(n)?.readme {
+                                            Remote::Ready(readme) => readme
+                                                .rendered
+                                                .links
+                                                .get((num_input? as usize).checked_sub(1)?)
+                                                .cloned(),
+                                            _ => None,
+                                        }
+                                    });
+                                    if let Some(link) = link {
+                                        match readme::linked_crate(&link) {
+                                            Some(name) => {
+                                                search_block_text = name.to_string();
+                                                pending_search = Some(search_block_text.clone());
+                                            }
+                                            None => {
+                                                webbrowser::open(&link);
+                                            }
+                                        }
+                                    }
+                                }
                                 'G' => {
-                                    let num = crates.items.lock().unwrap().len() - 1;
-                                    crates.select(Some(num));
+                                    let num = crates.items.lock().unwrap().len();
+                                    crates.select(num.checked_sub(1));
+                                }
+                                'm' => pending_more = true,
+                                // pin the crate for comparison across searches
+                                'p' => {
+                                    if let Some(item) = crates.selected() {
+                                        match pinned.toggle(item.clone()) {
+                                            // unpinned crates leave the pinned view
+                                            Ok(false) if search_results.is_some() => {
+                                                crates.remove(&item.id)
+                                            }
+                                            Ok(_) => (),
+                                            Err(e) => {
+                                                error_popup = Some(ErrorPopup {
+                                                    message: format!(
+                                                        "failed saving pinned crates: {}",
+                                                        e
+                                                    ),
+                                                    retry: None,
+                                                })
+                                            }
+                                        }
+                                    }
+                                }
+                                // switch between the search results and the
+                                // pinned crates
+                                'P' => match search_results.take() {
+                                    Some(results) => crates = results,
+                                    None => {
+                                        // stored crates don't carry any of
+                                        // the lazily fetched data, which
+                                        // can't be fetched offline
+                                        let items = match &dump {
+                                            Some(dump) => pinned
+                                                .crates
+                                                .iter()
+                                                .map(|c| dump.offline(c))
+                                                .collect(),
+                                            None => pinned.crates.clone(),
+                                        };
+                                        let page = Page {
+                                            crates: items,
+                                            number: 1,
+                                            total: pinned.crates.len() as u64,
+                                            filtered: 0,
+                                        };
+                                        let mut list =
+                                            CratesList::new(String::new(), page, &cache, &dump);
+                                        list.select(Some(0));
+                                        search_results = Some(std::mem::replace(&mut crates, list));
+                                    }
+                                },
+                                // mark the crate to compare the dependencies
+                                // of, then show the diff with the next one
+                                'D' if dump.is_some() => {
+                                    error_popup = Some(ErrorPopup {
+                                        message: "dependency diff is not available in offline mode"
+                                            .to_string(),
+                                        retry: None,
+                                    })
+                                }
+                                'D' => {
+                                    if let Some(item) = crates.selected() {
+                                        match diff_base.take() {
+                                            // marking the same crate again cancels
+                                            Some(base) if base.id == item.id => (),
+                                            Some(base) => {
+                                                dep_diff =
+                                                    Some(DepDiff::start([base, item], &cache))
+                                            }
+                                            None => diff_base = Some(item),
+                                        }
+                                    }
+                                }
+                                // dependency tree navigation
+                                'J' if results_current_tab == 5 => {
+                                    if let Some(Remote::Ready(resolve)) =
+                                        crates.selected().map(|c| c.tree)
+                                    {
+                                        tree_view.select_next(&resolve);
+                                    }
+                                }
+                                'K' if results_current_tab == 5 => tree_view.select_previous(),
+                                ' ' if results_current_tab == 5 => {
+                                    if let Some(Remote::Ready(resolve)) =
+                                        crates.selected().map(|c| c.tree)
+                                    {
+                                        tree_view.toggle(&resolve);
+                                    }
+                                }
+                                // list the crates depending on the crate
+                                'R' if dump.is_some() => {
+                                    error_popup = Some(ErrorPopup {
+                                        message:
+                                            "reverse dependencies are not available in offline mode"
+                                                .to_string(),
+                                        retry: None,
+                                    })
+                                }
+                                'R' => {
+                                    if let Some(item) = crates.selected() {
+                                        dependents = Some(Dependents::start(&item.id, &item.name));
+                                    }
+                                }
+                                // compare tab column selection and sorting
+                                '[' if results_current_tab == 1 => compare.select_previous(),
+                                ']' if results_current_tab == 1 => compare.select_next(),
+                                'S' if results_current_tab == 1 => {
+                                    crates.sort_by(compare.selected_column().sort_key())
                                 }
+                                'C' if results_current_tab == 1 => compare.picker = Some(0),
+                                // pick the features and add the crate to the
+                                // project manifest
+                                'a' => {
+                                    if let Some(item) = crates.selected() {
+                                        match find_manifest() {
+                                            Ok(path) => {
+                                                let existing = std::fs::read_to_string(path)
+                                                    .ok()
+                                                    .and_then(|manifest| {
+                                                        manifest::existing_dependency(
+                                                            &manifest,
+                                                            Section::Normal,
+                                                            &item.id,
+                                                        )
+                                                    });
+                                                feature_picker = Some(FeaturePicker::new(
+                                                    &item,
+                                                    Target::Manifest,
+                                                    existing.as_ref(),
+                                                ))
+                                            }
+                                            Err(e) => {
+                                                error_popup = Some(ErrorPopup {
+                                                    message: e.to_string(),
+                                                    retry: None,
+                                                })
+                                            }
+                                        }
+                                    }
+                                }
+                                // pick the features and copy the dependency line
                                 #[cfg(feature = "clipboard")]
                                 'c' => {
-                                    if let Some(selection) = crates.list_state.selected() {
-                                        if let Some(sel_crate) =
-                                            crates.items.lock().unwrap().get(selection)
-                                        {
-                                            let clip_text = format!(
-                                                "{} = \"{}\"",
-                                                sel_crate.id, sel_crate.max_version
-                                            );
-                                            clipboard.set_contents(clip_text);
-                                        }
+                                    if let Some(item) = crates.selected() {
+                                        feature_picker = Some(FeaturePicker::new(
+                                            &item,
+                                            Target::Clipboard,
+                                            None,
+                                        ));
                                     }
                                 }
                                 #[cfg(feature = "clipboard")]
@@ -766,12 +1868,11 @@ fn main() -> Result<()> {
                                             crates.items.lock().unwrap().get(selection)
                                         {
                                             if let Some(repo) = &sel_crate.repository {
-                                                let uri = Uri::try_from(repo.as_str())?;
-                                                let repo_name = uri
-                                                    .path()
-                                                    .unwrap()
+                                                let repo_name = repo
+                                                    .trim_end_matches('/')
                                                     .rsplit('/')
-                                                    .collect::<Vec<&str>>()[0];
+                                                    .next()
+                                                    .unwrap_or_default();
                                                 let clip_text = format!(
                                                     "git clone {} && cd {} && cargo run --release",
                                                     repo, repo_name
@@ -795,21 +1896,246 @@ fn main() -> Result<()> {
         // reset combos
         num_input = None;
 
-        stdout.flush().unwrap();
+        stdout.flush()?;
     }
 
-    // clean up the terminal before exit
-    terminal.clear().unwrap();
-    crossterm::terminal::disable_raw_mode()?;
-    crossterm::execute!(io::stdout(), crossterm::terminal::LeaveAlternateScreen)?;
+    // clean up the terminal before exit, the rest is restored by the guard
+    terminal.clear()?;
 
     Ok(())
 }
 
-/// Queries crates from the client using a simple string input.
-fn crate_query(input: &str, client: &Client) -> Result<Vec<Crate>> {
-    let mut query = consecrates::Query::from_str(input);
-    let crates_response: Crates = client.get_crates(query)?;
+/// Renders an error popup in the middle of the screen.
+fn render_error_popup<B: Backend>(f: &mut tui::Frame<B>, popup: &ErrorPopup) {
+    let rect = centered_rect(f.size(), f.size().width.saturating_sub(4).min(64), 8);
+    let hint = match popup.retry {
+        Some(_) => "<Enter> retry | <Esc> dismiss",
+        None => "<Esc> dismiss",
+    };
+    let text = Text::from(vec![
+        Spans::from(popup.message.as_str()),
+        Spans::from(""),
+        Spans::from(Span::styled(hint, Style::default().fg(Color::DarkGray))),
+    ]);
+    f.render_widget(widgets::Clear, rect);
+    f.render_widget(
+        Paragraph::new(text).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title("Error")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Red)),
+        ),
+        rect,
+    );
+}
+
+/// Renders the dependency diff, both crates side by side.
+fn render_dep_diff<B: Backend>(f: &mut tui::Frame<B>, diff: &DepDiff, rect: Rect) {
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(rect);
+    f.render_widget(widgets::Clear, rect);
+    for (n, lines) in Vec::from(diff.lines()).into_iter().enumerate() {
+        let title = match n {
+            0 => "Dependencies <j/k> scroll | <Esc> close",
+            _ => "Dependencies",
+        };
+        f.render_widget(
+            Paragraph::new(Text::from(lines))
+                .scroll((diff.scroll, 0))
+                .block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::White)),
+                ),
+            halves[n],
+        );
+    }
+}
+
+/// Renders the feature picker in the middle of the screen, scrolled so that
+/// the cursor stays in view.
+fn render_feature_picker<B: Backend>(
+    f: &mut tui::Frame<B>,
+    picker: &FeaturePicker,
+    features: &Remote<features::Features>,
+) {
+    let lines = picker.lines(features);
+    let rect = centered_rect(
+        f.size(),
+        f.size().width.saturating_sub(4).min(96),
+        lines.len() as u16 + 2,
+    );
+    let scroll = (picker.cursor() as u16 + 3).saturating_sub(rect.height);
+    let title = match picker.target {
+        Target::Clipboard => format!("Copy {}", picker.id),
+        Target::Manifest => format!("Add {}", picker.id),
+    };
+    f.render_widget(widgets::Clear, rect);
+    f.render_widget(
+        Paragraph::new(Text::from(lines)).scroll((scroll, 0)).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White)),
+        ),
+        rect,
+    );
+}
+
+/// Renders the manifest change waiting for confirmation in the middle of
+/// the screen.
+fn render_manifest_edit<B: Backend>(f: &mut tui::Frame<B>, edit: &ManifestEdit) {
+    let dimmed = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Spans::from(vec![
+            Span::raw(format!("Table: [{}]", edit.section.table())),
+            Span::styled("  <Tab> change", dimmed),
+        ]),
+        Spans::from(""),
+    ];
+    if let Some(error) = edit.error() {
+        lines.push(Spans::from(Span::styled(
+            error.to_string(),
+            Style::default().fg(Color::Red),
+        )));
+    } else if edit.is_noop() {
+        lines.push(Spans::from("already up to date, nothing to change"));
+    }
+    for line in edit.diff(2) {
+        lines.push(match line {
+            DiffLine::Context(line) => Spans::from(Span::styled(format!("  {}", line), dimmed)),
+            DiffLine::Removed(line) => Spans::from(Span::styled(
+                format!("- {}", line),
+                Style::default().fg(Color::Red),
+            )),
+            DiffLine::Added(line) => Spans::from(Span::styled(
+                format!("+ {}", line),
+                Style::default().fg(Color::Green),
+            )),
+        });
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "<Enter> write | <Esc> cancel",
+        dimmed,
+    )));
+
+    let rect = centered_rect(
+        f.size(),
+        f.size().width.saturating_sub(4).min(96),
+        lines.len() as u16 + 2,
+    );
+    f.render_widget(widgets::Clear, rect);
+    f.render_widget(
+        Paragraph::new(Text::from(lines)).block(
+            Block::default()
+                .title(format!("Add {} to {}", edit.name, edit.path.display()))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White)),
+        ),
+        rect,
+    );
+}
+
+/// Renders the compare tab column picker in the middle of the screen.
+fn render_column_picker<B: Backend>(f: &mut tui::Frame<B>, compare: &CompareView, cursor: usize) {
+    let rect = centered_rect(f.size(), 32, Column::ALL.len() as u16 + 4);
+    let mut lines: Vec<Spans> = Column::ALL
+        .iter()
+        .enumerate()
+        .map(|(n, column)| {
+            let mark = if compare.columns.contains(column) {
+                "x"
+            } else {
+                " "
+            };
+            let line = format!("[{}] {}", mark, column.title());
+            match n == cursor {
+                true => Spans::from(Span::styled(line, Style::default().bg(Color::DarkGray))),
+                false => Spans::from(line),
+            }
+        })
+        .collect();
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "<Space> toggle | <Esc> close",
+        Style::default().fg(Color::DarkGray),
+    )));
+    f.render_widget(widgets::Clear, rect);
+    f.render_widget(
+        Paragraph::new(Text::from(lines)).block(
+            Block::default()
+                .title("Columns")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White)),
+        ),
+        rect,
+    );
+}
+
+/// Finds the manifest of the project in the working directory.
+fn find_manifest() -> Result<PathBuf> {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| manifest::find(&dir))
+        .ok_or_else(|| Error::msg("no Cargo.toml found in the current directory or its parents"))
+}
+
+/// Returns an area of the given size in the middle of the screen, shrunk
+/// to fit if needed.
+fn centered_rect(size: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(size.width);
+    let height = height.min(size.height);
+    Rect::new(
+        (size.width - width) / 2,
+        (size.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Validates the query input, returning the reason it's invalid.
+fn query_error(input: &str, vocabulary: &Mutex<Vocabulary>) -> Option<String> {
+    let mut query = Query::parse(input);
+    query.validate(&vocabulary.lock().unwrap());
+    query.error().map(|e| e.to_string())
+}
+
+/// Queries crates from the client, or the database dump in offline mode,
+/// using a simple string input.
+///
+/// Filters not supported by crates.io are applied to the page of results
+/// found.
+fn crate_query(
+    input: &str,
+    page: Option<usize>,
+    cache: &Cache,
+    dump: Option<&Dump>,
+) -> Result<Page> {
+    let query = Query::parse(input);
+    let mut page = match dump {
+        Some(dump) => dump.search(&query, page),
+        None => fetch_page(&query, input, page, cache)?,
+    };
+    let now = Utc::now();
+    let len = page.crates.len();
+    page.crates.retain(|c| query.matches(c, now));
+    page.filtered = (len - page.crates.len()) as u64;
+    Ok(page)
+}
+
+/// Gets a page of results from the cache or crates.io.
+fn fetch_page(query: &Query, input: &str, page: Option<usize>, cache: &Cache) -> Result<Page> {
+    // page given explicitly takes precedence over the one in the input
+    let number = page.or_else(|| query.page()).unwrap_or(1);
+    if let Some(page) = cache.search(input, number) {
+        return Ok(page);
+    }
+
+    let crates_response = api::search_crates(query, number)?;
 
     let mut crates = Vec::new();
     for crate_response in &crates_response.crates {
@@ -822,7 +2148,7 @@ fn crate_query(input: &str, client: &Client) -> Result<Vec<Crate>> {
             homepage: crate_response.homepage.clone(),
             repository: crate_response.repository.clone(),
             downloads: crate_response.downloads,
-            recent_downloads: crate_response.recent_downloads.clone(),
+            recent_downloads: crate_response.recent_downloads,
             categories: crate_response.categories.clone(),
             keywords: crate_response.keywords.clone(),
             max_version: crate_response.max_version.clone(),
@@ -830,52 +2156,26 @@ fn crate_query(input: &str, client: &Client) -> Result<Vec<Crate>> {
             created_at: crate_response.created_at,
             updated_at: crate_response.updated_at,
             exact_match: crate_response.exact_match,
-            readme: None,
+            readme: Remote::Missing,
+            repo_info: Remote::Missing,
+            stats: Remote::Missing,
+            dependencies: Remote::Missing,
+            reverse_dependencies: Remote::Missing,
+            msrv: Remote::Missing,
+            tree: Remote::Missing,
+            versions: Remote::Missing,
+            features: Remote::Missing,
         })
     }
-
-    Ok(crates)
-}
-
-/// Creates a new results list item string using a bunch of arguments.
-///
-/// Organizes text into a left and right column, where the right column
-/// consists of zero or more elements.
-fn create_list_item_string(
-    left_string: String,
-    right_strings: Vec<String>,
-    right_strings_width: Vec<usize>,
-    space_char: char,
-    div_char: char,
-    rect_width: usize,
-) -> String {
-    let mut item_string = left_string.to_string();
-
-    // calculate space between the end of the left and beginning of the right
-    let mut left_right_delta = rect_width - left_string.len();
-    for right_string_width in &right_strings_width {
-        left_right_delta = left_right_delta
-            .checked_sub(right_string_width + 4)
-            .unwrap_or(0);
-    }
-
-    // push the right amount of space characters
-    for _ in 0..left_right_delta {
-        item_string.push(space_char);
-    }
-
-    // push all the right column strings
-    for (i, right_string) in right_strings.iter().enumerate() {
-        if let Some(width_delta) = right_strings_width[i].checked_sub(right_string.len()) {
-            let rs = format!("{} {}", div_char, right_string);
-            item_string.push_str(&rs);
-            for _ in 0..width_delta {
-                item_string.push(space_char);
-            }
-        }
-    }
-
-    item_string
+    let page = Page {
+        crates,
+        number,
+        total: crates_response.meta.total,
+        filtered: 0,
+    };
+    cache.store_search(input, &page);
+
+    Ok(page)
 }
 
 fn create_intro_string(summary: consecrates::api::Summary) -> Result<String> {
@@ -0,0 +1,1081 @@
+//! Reading and adding dependencies in the manifest of the current project.
+//!
+//! The manifest is edited line by line rather than parsed and written back,
+//! so that everything besides the dependency line, including comments and
+//! the order of the entries, stays the way it was. Only the parts of the
+//! format that dependency tables use in practice are understood.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Error, Result};
+
+use crate::cache;
+
+/// Name of the manifest file.
+pub const MANIFEST: &str = "Cargo.toml";
+
+/// Finds the manifest closest to the given directory, looking in the
+/// directory itself and then its parents.
+pub(crate) fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(MANIFEST))
+        .find(|path| path.is_file())
+}
+
+/// Dependency table of the manifest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Section {
+    Normal,
+    Dev,
+    Build,
+}
+
+impl Section {
+    pub fn table(self) -> &'static str {
+        match self {
+            Section::Normal => "dependencies",
+            Section::Dev => "dev-dependencies",
+            Section::Build => "build-dependencies",
+        }
+    }
+
+    pub fn next(self) -> Section {
+        match self {
+            Section::Normal => Section::Dev,
+            Section::Dev => Section::Build,
+            Section::Build => Section::Normal,
+        }
+    }
+}
+
+/// Requirement put on a dependency. Features and `default-features` that
+/// are `None` are left as they are on existing entries, and omitted from
+/// new ones.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DependencySpec {
+    pub version: String,
+    pub features: Option<Vec<String>>,
+    pub default_features: Option<bool>,
+}
+
+impl DependencySpec {
+    pub fn new(version: &str) -> Self {
+        DependencySpec {
+            version: version.to_string(),
+            features: None,
+            default_features: None,
+        }
+    }
+
+    /// Returns the key-value pairs of the inline table form.
+    fn pairs(&self) -> Vec<(String, String)> {
+        let mut pairs = vec![("version".to_string(), quote(&self.version))];
+        if let Some(features) = self.features.as_ref().filter(|f| !f.is_empty()) {
+            pairs.push(("features".to_string(), array(features)));
+        }
+        if self.default_features == Some(false) {
+            pairs.push(("default-features".to_string(), "false".to_string()));
+        }
+        pairs
+    }
+}
+
+/// Formats the spec the way it's written after `name = `, using the short
+/// form when there's only the version.
+impl fmt::Display for DependencySpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pairs().as_slice() {
+            [(_, version)] => write!(f, "{}", version),
+            pairs => write!(f, "{}", inline_table(pairs)),
+        }
+    }
+}
+
+/// Line of the manifest diff.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Pending change to the manifest, shown before it's written.
+#[derive(Debug, Clone)]
+pub(crate) struct ManifestEdit {
+    pub path: PathBuf,
+    pub name: String,
+    pub spec: DependencySpec,
+    pub section: Section,
+    original: String,
+    /// Edited manifest, or why the dependency can't be added to the table.
+    updated: Result<String, String>,
+}
+
+impl ManifestEdit {
+    /// Reads the manifest and prepares adding the dependency to the given
+    /// table, or updating it if it's already there.
+    pub fn new(path: &Path, name: &str, spec: DependencySpec, section: Section) -> Result<Self> {
+        let original = fs::read_to_string(path)
+            .map_err(|e| Error::msg(format!("failed reading {}: {}", path.display(), e)))?;
+        Ok(ManifestEdit::with_original(
+            path, original, name, spec, section,
+        ))
+    }
+
+    fn with_original(
+        path: &Path,
+        original: String,
+        name: &str,
+        spec: DependencySpec,
+        section: Section,
+    ) -> Self {
+        let updated = add_dependency(&original, section, name, &spec).map_err(|e| e.to_string());
+        ManifestEdit {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+            spec,
+            section,
+            original,
+            updated,
+        }
+    }
+
+    /// Moves the dependency to the next table.
+    pub fn next_section(&mut self) {
+        self.section = self.section.next();
+        self.updated = add_dependency(&self.original, self.section, &self.name, &self.spec)
+            .map_err(|e| e.to_string());
+    }
+
+    pub fn is_noop(&self) -> bool {
+        self.updated.as_ref() == Ok(&self.original)
+    }
+
+    /// Returns the reason the dependency can't be added to the table.
+    pub fn error(&self) -> Option<&str> {
+        self.updated.as_ref().err().map(|e| e.as_str())
+    }
+
+    /// Returns the changed lines along with a few lines around them.
+    pub fn diff(&self, context: usize) -> Vec<DiffLine> {
+        let updated = match &self.updated {
+            Ok(updated) => updated,
+            Err(_) => return Vec::new(),
+        };
+        let old: Vec<&str> = self.original.lines().collect();
+        let new: Vec<&str> = updated.lines().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut lines = Vec::new();
+        for line in &old[prefix.saturating_sub(context)..prefix] {
+            lines.push(DiffLine::Context(line.to_string()));
+        }
+        for line in &old[prefix..old.len() - suffix] {
+            lines.push(DiffLine::Removed(line.to_string()));
+        }
+        for line in &new[prefix..new.len() - suffix] {
+            lines.push(DiffLine::Added(line.to_string()));
+        }
+        let end = old.len() - suffix;
+        for line in &old[end..(end + context).min(old.len())] {
+            lines.push(DiffLine::Context(line.to_string()));
+        }
+        lines
+    }
+
+    /// Writes the edited manifest. Fails if the file was changed since it
+    /// was read.
+    pub fn write(&self) -> Result<()> {
+        let updated = self.updated.as_ref().map_err(|e| Error::msg(e.clone()))?;
+        if fs::read_to_string(&self.path)? != self.original {
+            return Err(Error::msg("the file was changed in the meantime"));
+        }
+        cache::write(&self.path, updated.as_bytes())
+    }
+}
+
+/// Adds the dependency to the given table of the manifest, or updates it
+/// if it's already there. Returns the edited manifest.
+///
+/// Fails for dependencies inherited from the workspace, as their version
+/// can only be set in the root manifest, and for virtual manifests which
+/// have no package to add the dependency to.
+pub(crate) fn add_dependency(
+    manifest: &str,
+    section: Section,
+    name: &str,
+    spec: &DependencySpec,
+) -> Result<String> {
+    let newline = if manifest.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines: Vec<String> = manifest.lines().map(|l| l.to_string()).collect();
+
+    let has_table = |table: &str| {
+        lines
+            .iter()
+            .any(|l| header_name(l).as_deref() == Some(table))
+    };
+    if has_table("workspace") && !has_table("package") {
+        return Err(Error::msg(
+            "the manifest is a virtual workspace manifest, add the dependency to one of the members",
+        ));
+    }
+
+    let table = section.table();
+    let dotted = format!("{}.{}", table, name);
+    if let Some(header) = lines
+        .iter()
+        .position(|l| header_name(l).as_deref() == Some(&dotted))
+    {
+        update_dotted_table(&mut lines, header, name, "", spec)?;
+    } else if let Some(header) = lines
+        .iter()
+        .position(|l| header_name(l).as_deref() == Some(table))
+    {
+        let entries = entries(&lines, header);
+        let existing = entries
+            .iter()
+            .find(|&&(n, _)| key_name(&lines[n]).as_deref() == Some(name));
+        match existing {
+            // `name.version = ...` dotted keys
+            Some(&(n, _)) if key_path(&lines[n]).is_some_and(|path| path.len() > 1) => {
+                update_dotted_table(&mut lines, header, name, &format!("{}.", name), spec)?
+            }
+            Some(&(n, last)) => {
+                let updated = update_entry(&entry_text(&lines, n, last), name, spec)?;
+                lines.splice(n..=last, updated.split('\n').map(|l| l.to_string()));
+            }
+            None => {
+                // new entries go after the last one, keeping any blank
+                // lines and comments separating the next table
+                let last = entries.last().map(|&(_, last)| last).unwrap_or(header);
+                lines.insert(last + 1, format!("{} = {}", name, spec));
+            }
+        }
+    } else {
+        if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(format!("[{}]", table));
+        lines.push(format!("{} = {}", name, spec));
+    }
+
+    let mut edited = lines.join(newline);
+    if manifest.ends_with('\n') || !manifest.contains('\n') {
+        edited.push_str(newline);
+    }
+    Ok(edited)
+}
+
+/// Returns the dependency if it's already in the given table of the
+/// manifest, with the features and `default-features` it declares.
+pub(crate) fn existing_dependency(
+    manifest: &str,
+    section: Section,
+    name: &str,
+) -> Option<DependencySpec> {
+    let lines: Vec<String> = manifest.lines().map(|l| l.to_string()).collect();
+    let table = section.table();
+    let dotted = format!("{}.{}", table, name);
+    let find_header = |table: &str| {
+        lines
+            .iter()
+            .position(|l| header_name(l).as_deref() == Some(table))
+    };
+
+    // keys of the dependency along with their values
+    let mut pairs = Vec::new();
+    if let Some(header) = find_header(&dotted) {
+        for (n, last) in entries(&lines, header) {
+            pairs.extend(split_pair(&entry_text(&lines, n, last)));
+        }
+    } else {
+        let header = find_header(table)?;
+        for (n, last) in entries(&lines, header) {
+            let path = key_path(&lines[n]).unwrap_or_default();
+            if path.first().map(|k| k.as_str()) != Some(name) {
+                continue;
+            }
+            let (_, value) = split_pair(&entry_text(&lines, n, last))?;
+            // `name.version = ...` dotted keys
+            if path.len() > 1 {
+                pairs.push((path[1..].join("."), value));
+                continue;
+            }
+            match value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+                Some(inner) => {
+                    pairs.extend(split_top_level(inner).into_iter().filter_map(split_pair))
+                }
+                None => pairs.push(("version".to_string(), value)),
+            }
+        }
+        if pairs.is_empty() {
+            return None;
+        }
+    }
+
+    let value = |key: &str| {
+        pairs
+            .iter()
+            .find(|(k, _)| is_key(k, key))
+            .map(|(_, v)| v.as_str())
+    };
+    Some(DependencySpec {
+        version: value("version").map(unquote).unwrap_or_default(),
+        features: Some(value("features").map(array_items).unwrap_or_default()),
+        default_features: Some(value("default-features") != Some("false")),
+    })
+}
+
+/// Returns the names of the crates the manifest depends on, from all of the
+/// dependency tables including the target specific ones, each listed once.
+/// Renamed dependencies are listed under the name of the crate. The shared
+/// `[workspace.dependencies]` are left out, as only the members using them
+/// depend on them.
+pub(crate) fn dependency_names(manifest: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut table = String::new();
+    // index of the dependency of a `[dependencies.name]` table
+    let mut dotted = None;
+    for line in manifest.lines() {
+        if line.trim_start().starts_with('[') {
+            table = header_name(line).unwrap_or_default();
+            dotted = None;
+            if let Some((parent, name)) = table.rsplit_once('.') {
+                if is_dependency_table(parent) {
+                    names.push(name.to_string());
+                    dotted = Some(names.len() - 1);
+                }
+            }
+            continue;
+        }
+        let key = match key_name(line) {
+            Some(key) => key,
+            None => continue,
+        };
+        let value = line.split_once('=').map(|(_, v)| v.trim()).unwrap_or("");
+        match dotted {
+            Some(n) if key == "package" => names[n] = unquote(&value[..value_end(value)]),
+            Some(_) => (),
+            None if is_dependency_table(&table) => {
+                names.push(package_name(value).unwrap_or(key));
+            }
+            None => (),
+        }
+    }
+    // dependencies using dotted keys are found once per key
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+/// Returns the member patterns of the workspace, `None` if the manifest
+/// isn't a workspace root.
+pub(crate) fn workspace_members(manifest: &str) -> Option<Vec<String>> {
+    workspace_array(manifest, "members")
+}
+
+/// Returns the paths excluded from the workspace members.
+pub(crate) fn workspace_excludes(manifest: &str) -> Vec<String> {
+    workspace_array(manifest, "exclude").unwrap_or_default()
+}
+
+/// Returns the strings of the array under the key of the `[workspace]`
+/// table, empty if the key is missing and `None` if the table is.
+fn workspace_array(manifest: &str, key: &str) -> Option<Vec<String>> {
+    let lines: Vec<String> = manifest.lines().map(|l| l.to_string()).collect();
+    let header = lines
+        .iter()
+        .position(|l| header_name(l).as_deref() == Some("workspace"))?;
+    // the array can span several lines
+    let items = entries(&lines, header)
+        .into_iter()
+        .find(|&(n, _)| key_name(&lines[n]).as_deref() == Some(key))
+        .and_then(|(n, last)| split_pair(&entry_text(&lines, n, last)))
+        .map(|(_, value)| array_items(&value))
+        .unwrap_or_default();
+    Some(items)
+}
+
+/// Checks whether the table lists dependencies.
+fn is_dependency_table(table: &str) -> bool {
+    let kind = table.rsplit('.').next().unwrap_or(table);
+    let kinds = [
+        Section::Normal.table(),
+        Section::Dev.table(),
+        Section::Build.table(),
+    ];
+    kinds.contains(&kind) && (table == kind || table.starts_with("target."))
+}
+
+/// Returns the name of the crate a renamed dependency refers to.
+fn package_name(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('{')?;
+    let inner = &inner[..inner.find('}')?];
+    split_top_level(inner).into_iter().find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        is_key(k, "package").then(|| unquote(v.trim()))
+    })
+}
+
+/// Returns the name of the table if the line is a table header.
+fn header_name(line: &str) -> Option<String> {
+    let line = strip_comment(line).trim();
+    let name = line.strip_prefix('[')?.strip_suffix(']')?;
+    // arrays of tables aren't dependency tables
+    if name.starts_with('[') {
+        return None;
+    }
+    Some(
+        name.split('.')
+            .map(|part| unquote(part.trim()))
+            .collect::<Vec<_>>()
+            .join("."),
+    )
+}
+
+/// Returns the index of the line following the last line of the table
+/// starting at the given header.
+fn table_end(lines: &[String], header: usize) -> usize {
+    (header + 1..lines.len())
+        .find(|&n| lines[n].trim_start().starts_with('['))
+        .unwrap_or(lines.len())
+}
+
+/// Returns the first part of the key if the line is a key-value pair, which
+/// for dotted keys like `name.version` is the name of the dependency.
+fn key_name(line: &str) -> Option<String> {
+    key_path(line).and_then(|path| path.into_iter().next())
+}
+
+/// Returns the parts of the key if the line is a key-value pair.
+fn key_path(line: &str) -> Option<Vec<String>> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') || trimmed.starts_with('[') {
+        return None;
+    }
+    let (key, _) = trimmed.split_once('=')?;
+    Some(key.split('.').map(|part| unquote(part.trim())).collect())
+}
+
+/// Returns the first and last line of each key-value pair in the table
+/// starting at the given header. Values like arrays can span several lines.
+fn entries(lines: &[String], header: usize) -> Vec<(usize, usize)> {
+    let end = table_end(lines, header);
+    let mut entries = Vec::new();
+    let mut n = header + 1;
+    while n < end {
+        if key_name(&lines[n]).is_none() {
+            n += 1;
+            continue;
+        }
+        let mut depth = 0;
+        let mut last = n;
+        for (m, line) in lines.iter().enumerate().take(end).skip(n) {
+            let line = strip_comment(line);
+            let line = match m == n {
+                true => line.split_once('=').map(|(_, v)| v).unwrap_or(""),
+                false => line,
+            };
+            depth += nesting(line);
+            if depth <= 0 {
+                last = m;
+                break;
+            }
+        }
+        entries.push((n, last));
+        n = last + 1;
+    }
+    entries
+}
+
+/// Returns by how much the string opens more brackets than it closes,
+/// leaving out the ones in quotes.
+fn nesting(s: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    for c in s.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '{' | '[' => depth += 1,
+                '}' | ']' => depth -= 1,
+                _ => (),
+            },
+        }
+    }
+    depth
+}
+
+/// Joins the lines of the entry, including the comments inside of a value
+/// spanning several lines.
+fn entry_text(lines: &[String], first: usize, last: usize) -> String {
+    lines[first..=last].join("\n")
+}
+
+/// Checks whether the value of the entry is `true`.
+fn is_true(entry: &str) -> bool {
+    entry
+        .split_once('=')
+        .is_some_and(|(_, v)| &v.trim_start()[..value_end(v.trim_start())] == "true")
+}
+
+fn inherited_error(name: &str) -> Error {
+    Error::msg(format!(
+        "{} is inherited from the workspace, change it in [workspace.dependencies] of the root manifest",
+        name
+    ))
+}
+
+/// Replaces the value of the `name = value` entry, keeping the keys of an
+/// inline table and any trailing comment.
+fn update_entry(entry: &str, name: &str, spec: &DependencySpec) -> Result<String> {
+    let (key, rest) = match entry.split_once('=') {
+        Some(split) => split,
+        None => return Ok(entry.to_string()),
+    };
+    let value = rest.trim_start();
+    let end = value_end(value);
+    let (value, suffix) = value.split_at(end);
+
+    let updated = match value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+        Some(inner) => {
+            let mut pairs: Vec<(String, String)> = split_top_level(inner)
+                .into_iter()
+                .filter_map(|pair| {
+                    let (k, v) = pair.split_once('=')?;
+                    Some((k.trim().to_string(), v.trim().to_string()))
+                })
+                .collect();
+            if pairs
+                .iter()
+                .any(|(k, v)| is_key(k, "workspace") && v == "true")
+            {
+                return Err(inherited_error(name));
+            }
+            for (k, v) in spec.pairs() {
+                set_pair(&mut pairs, &k, v);
+            }
+            if spec.features.as_ref().is_some_and(|f| f.is_empty()) {
+                pairs.retain(|(k, _)| !is_key(k, "features"));
+            }
+            if spec.default_features == Some(true) {
+                pairs.retain(|(k, _)| !is_key(k, "default-features"));
+            }
+            inline_table(&pairs)
+        }
+        None => spec.to_string(),
+    };
+    Ok(format!("{}= {}{}", key, updated, suffix))
+}
+
+/// Updates the version and features in a `[dependencies.name]` table, or
+/// in the `name.version` dotted keys of the dependency table when the
+/// prefix is `name.`.
+fn update_dotted_table(
+    lines: &mut Vec<String>,
+    header: usize,
+    name: &str,
+    prefix: &str,
+    spec: &DependencySpec,
+) -> Result<()> {
+    // entries of the dependency along with their key without the prefix
+    let find = |lines: &[String], key: &str| {
+        entries(lines, header).into_iter().find(|&(n, _)| {
+            key_path(&lines[n])
+                .map(|path| path.join("."))
+                .and_then(|k| k.strip_prefix(prefix).map(|k| is_key(k, key)))
+                .unwrap_or(false)
+        })
+    };
+    if find(lines, "workspace").is_some_and(|(n, last)| is_true(&entry_text(lines, n, last))) {
+        return Err(inherited_error(name));
+    }
+
+    for (key, value) in spec.pairs() {
+        match find(lines, &key) {
+            Some((n, last)) => {
+                let entry = entry_text(lines, n, last);
+                let suffix = entry
+                    .split_once('=')
+                    .map(|(_, r)| r.trim_start())
+                    .map(|r| r[value_end(r)..].to_string())
+                    .unwrap_or_default();
+                let indent: String = lines[n].chars().take_while(|c| c.is_whitespace()).collect();
+                lines.splice(
+                    n..=last,
+                    [format!("{}{}{} = {}{}", indent, prefix, key, value, suffix)],
+                );
+            }
+            None => {
+                let last = entries(lines, header)
+                    .into_iter()
+                    .rev()
+                    .find(|&(n, _)| {
+                        key_path(&lines[n]).is_some_and(|path| path.join(".").starts_with(prefix))
+                    })
+                    .map(|(_, last)| last)
+                    .unwrap_or(header);
+                lines.insert(last + 1, format!("{}{} = {}", prefix, key, value));
+            }
+        }
+    }
+    let remove = |key: &str, lines: &mut Vec<String>| {
+        if let Some((n, last)) = find(lines, key) {
+            lines.drain(n..=last);
+        }
+    };
+    if spec.features.as_ref().is_some_and(|f| f.is_empty()) {
+        remove("features", lines);
+    }
+    if spec.default_features == Some(true) {
+        remove("default-features", lines);
+    }
+    Ok(())
+}
+
+/// Splits the `key = value` pair, leaving out any comment after the value.
+fn split_pair(pair: &str) -> Option<(String, String)> {
+    let (key, value) = pair.split_once('=')?;
+    let value = value.trim_start();
+    Some((
+        key.trim().to_string(),
+        value[..value_end(value)].to_string(),
+    ))
+}
+
+/// Returns the strings listed in the array value.
+fn array_items(value: &str) -> Vec<String> {
+    let lines: Vec<&str> = value.lines().map(strip_comment).collect();
+    let text = lines.join("\n");
+    let inner = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .unwrap_or("");
+    split_top_level(inner)
+        .into_iter()
+        .map(|item| unquote(item.trim()))
+        .collect()
+}
+
+/// Sets the value of the key in the inline table pairs, appending the key
+/// if it's missing.
+fn set_pair(pairs: &mut Vec<(String, String)>, key: &str, value: String) {
+    match pairs.iter_mut().find(|(k, _)| is_key(k, key)) {
+        Some(pair) => pair.1 = value,
+        None => pairs.push((key.to_string(), value)),
+    }
+}
+
+/// Checks whether the possibly quoted key is the given one.
+fn is_key(k: &str, key: &str) -> bool {
+    let k = unquote(k.trim());
+    // `default_features` is an older spelling of `default-features`
+    k == key || (key == "default-features" && k == "default_features")
+}
+
+/// Returns the length of the value at the start of the string.
+fn value_end(value: &str) -> usize {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut comment = false;
+    for (n, c) in value.char_indices() {
+        if comment {
+            comment = c != '\n';
+            continue;
+        }
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                    if depth == 0 {
+                        return n + 1;
+                    }
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '{' | '[' => depth += 1,
+                '}' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return n + 1;
+                    }
+                }
+                '#' if depth == 0 => return value[..n].trim_end().len(),
+                // comments can follow the items of arrays spanning lines
+                '#' => comment = true,
+                _ => (),
+            },
+        }
+    }
+    value.trim_end().len()
+}
+
+/// Splits the string on the commas that aren't inside quotes, brackets or
+/// comments.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut comment = false;
+    let mut start = 0;
+    for (n, c) in s.char_indices() {
+        if comment {
+            comment = c != '\n';
+            continue;
+        }
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '#' => comment = true,
+                '{' | '[' => depth += 1,
+                '}' | ']' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(&s[start..n]);
+                    start = n + 1;
+                }
+                _ => (),
+            },
+        }
+    }
+    parts.push(&s[start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
+
+/// Removes the comment at the end of the line, if any.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (n, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..n],
+            None => (),
+        }
+    }
+    line
+}
+
+fn unquote(s: &str) -> String {
+    s.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn array(items: &[String]) -> String {
+    let items: Vec<String> = items.iter().map(|i| quote(i)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn inline_table(pairs: &[(String, String)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(k, v)| format!("{} = {}", k, v))
+        .collect();
+    format!("{{ {} }}", pairs.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(manifest: &str, name: &str, spec: &DependencySpec) -> String {
+        add_dependency(manifest, Section::Normal, name, spec).unwrap()
+    }
+
+    fn spec(version: &str, features: &[&str]) -> DependencySpec {
+        DependencySpec {
+            version: version.to_string(),
+            features: Some(features.iter().map(|f| f.to_string()).collect()),
+            default_features: None,
+        }
+    }
+
+    #[test]
+    fn add_to_existing_table() {
+        let manifest = "[package]\nname = \"x\"\n\n[dependencies]\nanyhow = \"1\"\n\n# dev\n[dev-dependencies]\n";
+        assert_eq!(
+            add(manifest, "tokio", &DependencySpec::new("1.40.0")),
+            "[package]\nname = \"x\"\n\n[dependencies]\nanyhow = \"1\"\ntokio = \"1.40.0\"\n\n# dev\n[dev-dependencies]\n"
+        );
+    }
+
+    #[test]
+    fn add_new_table() {
+        assert_eq!(
+            add("[package]\nname = \"x\"\n", "tokio", &spec("1.40.0", &["full"])),
+            "[package]\nname = \"x\"\n\n[dependencies]\ntokio = { version = \"1.40.0\", features = [\"full\"] }\n"
+        );
+        assert_eq!(
+            add_dependency(
+                "[package]\r\nname = \"x\"\r\n",
+                Section::Dev,
+                "a",
+                &DependencySpec::new("1")
+            )
+            .unwrap(),
+            "[package]\r\nname = \"x\"\r\n\r\n[dev-dependencies]\r\na = \"1\"\r\n"
+        );
+    }
+
+    #[test]
+    fn update_plain_string() {
+        assert_eq!(
+            add(
+                "[dependencies]\ntokio = \"1.0\" # async\n",
+                "tokio",
+                &DependencySpec::new("1.40.0")
+            ),
+            "[dependencies]\ntokio = \"1.40.0\" # async\n"
+        );
+    }
+
+    #[test]
+    fn update_inline_table() {
+        let manifest = "[dependencies]\nfoo = { version = \"1\", optional = true, default-features = false }\n";
+        assert_eq!(
+            add(manifest, "foo", &spec("1.40.0", &["a"])),
+            "[dependencies]\nfoo = { version = \"1.40.0\", optional = true, default-features = false, features = [\"a\"] }\n"
+        );
+        // only the version is given, the features are kept
+        assert_eq!(
+            add(manifest, "foo", &DependencySpec::new("2")),
+            "[dependencies]\nfoo = { version = \"2\", optional = true, default-features = false }\n"
+        );
+    }
+
+    #[test]
+    fn update_multiline_inline_table() {
+        let manifest = "[dependencies]\nfoo = { version = \"1\", features = [\n    \"a\", # first, [see] \"docs\n    \"b\",\n] } # foo\nbar = \"1\"\n";
+        assert_eq!(
+            add(manifest, "foo", &DependencySpec::new("1.40.0")),
+            "[dependencies]\nfoo = { version = \"1.40.0\", features = [\n    \"a\", # first, [see] \"docs\n    \"b\",\n] } # foo\nbar = \"1\"\n"
+        );
+        assert_eq!(
+            add(manifest, "foo", &spec("1.40.0", &["c"])),
+            "[dependencies]\nfoo = { version = \"1.40.0\", features = [\"c\"] } # foo\nbar = \"1\"\n"
+        );
+    }
+
+    #[test]
+    fn add_after_multiline_entry() {
+        let manifest = "[dependencies]\nfoo = { version = \"1\", features = [\n    \"a\",\n] }\n";
+        assert_eq!(
+            add(manifest, "bar", &DependencySpec::new("2")),
+            "[dependencies]\nfoo = { version = \"1\", features = [\n    \"a\",\n] }\nbar = \"2\"\n"
+        );
+    }
+
+    #[test]
+    fn update_dotted_table() {
+        let manifest = "[dependencies.foo]\nversion = \"1\"\nfeatures = [\n  \"a\",\n]\n\n[dev-dependencies]\n";
+        assert_eq!(
+            add(manifest, "foo", &spec("1.40.0", &["b"])),
+            "[dependencies.foo]\nversion = \"1.40.0\"\nfeatures = [\"b\"]\n\n[dev-dependencies]\n"
+        );
+        assert_eq!(
+            add(manifest, "foo", &spec("1.40.0", &[])),
+            "[dependencies.foo]\nversion = \"1.40.0\"\n\n[dev-dependencies]\n"
+        );
+    }
+
+    #[test]
+    fn update_dotted_keys() {
+        let manifest = "[dependencies]\ntokio.version = \"1\"\ntokio.default-features = false\nserde = \"1\"\n";
+        assert_eq!(
+            add(manifest, "tokio", &spec("1.40.0", &["rt"])),
+            "[dependencies]\ntokio.version = \"1.40.0\"\ntokio.default-features = false\ntokio.features = [\"rt\"]\nserde = \"1\"\n"
+        );
+    }
+
+    #[test]
+    fn dependency_names_of_all_tables() {
+        let manifest = "[package]\nname = \"x\"\n\n[dependencies]\ntokio.version = \"1\"\ntokio.features = [\"rt\"]\nserde = \"1\"\nrenamed = { package = \"rand\", version = \"0.8\" }\n\n[dependencies.log]\nversion = \"0.4\"\n\n[dependencies.other]\npackage = \"anyhow\"\n\n[target.'cfg(unix)'.dependencies]\nlibc = \"0.2\"\n\n[dev-dependencies]\nserde = \"1\"\n\n[workspace.dependencies]\nregex = \"1\"\n\n[features]\nstd = []\n";
+        assert_eq!(
+            dependency_names(manifest),
+            vec!["tokio", "serde", "rand", "log", "anyhow", "libc"]
+        );
+    }
+
+    #[test]
+    fn workspace_tables() {
+        let manifest = "[workspace]\nmembers = [\n    \"app\", # the binary\n    \"crates/*\",\n]\nexclude = [\"crates/old\"]\n";
+        assert_eq!(
+            workspace_members(manifest),
+            Some(vec!["app".to_string(), "crates/*".to_string()])
+        );
+        assert_eq!(workspace_excludes(manifest), vec!["crates/old"]);
+        assert_eq!(workspace_members("[workspace]\n"), Some(vec![]));
+        assert_eq!(workspace_members("[package]\nname = \"x\"\n"), None);
+        assert!(workspace_excludes("[package]\n").is_empty());
+    }
+
+    #[test]
+    fn refuse_workspace_dependencies() {
+        for manifest in [
+            "[dependencies]\ntokio.workspace = true\n",
+            "[dependencies]\nserde = { workspace = true }\n",
+            "[dependencies]\nserde = { workspace = true, features = [\n  \"derive\",\n] }\n",
+            "[dependencies.serde]\nworkspace = true\n",
+        ] {
+            let name = match manifest.contains("tokio") {
+                true => "tokio",
+                false => "serde",
+            };
+            let error = add_dependency(
+                manifest,
+                Section::Normal,
+                name,
+                &DependencySpec::new("1.40.0"),
+            )
+            .unwrap_err();
+            assert!(
+                error.to_string().contains("inherited from the workspace"),
+                "{}",
+                manifest
+            );
+        }
+        // the other tables can still be changed
+        assert!(add_dependency(
+            "[dependencies]\ntokio.workspace = true\n",
+            Section::Dev,
+            "tokio",
+            &DependencySpec::new("1.40.0")
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn existing_dependencies() {
+        let existing = |manifest: &str, name: &str| {
+            existing_dependency(manifest, Section::Normal, name).map(|spec| {
+                (
+                    spec.version,
+                    spec.features.unwrap(),
+                    spec.default_features.unwrap(),
+                )
+            })
+        };
+        let features = |f: &[&str]| f.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+
+        let manifest = "[dependencies]\nanyhow = \"1\" # errors\nfoo = { version = \"1.2\", features = [\n    \"a\", # first, [see]\n    \"b\",\n], default-features = false }\ntokio.version = \"1\"\ntokio.features = [\"rt\"]\n\n[dev-dependencies]\nrand = \"0.8\"\n";
+        assert_eq!(
+            existing(manifest, "anyhow"),
+            Some(("1".to_string(), vec![], true))
+        );
+        assert_eq!(
+            existing(manifest, "foo"),
+            Some(("1.2".to_string(), features(&["a", "b"]), false))
+        );
+        assert_eq!(
+            existing(manifest, "tokio"),
+            Some(("1".to_string(), features(&["rt"]), true))
+        );
+        // other tables aren't looked at
+        assert_eq!(existing(manifest, "rand"), None);
+        assert_eq!(existing("[package]\nname = \"x\"\n", "rand"), None);
+
+        let manifest = "[dependencies.serde]\nversion = \"1\"\ndefault_features = false\nfeatures = [\"derive\"]\n";
+        assert_eq!(
+            existing(manifest, "serde"),
+            Some(("1".to_string(), features(&["derive"]), false))
+        );
+    }
+
+    #[test]
+    fn refuse_virtual_manifests() {
+        let error = add_dependency(
+            "[workspace]\nmembers = [\"a\"]\n\n[workspace.dependencies]\nserde = \"1\"\n",
+            Section::Normal,
+            "tokio",
+            &DependencySpec::new("1.40.0"),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("virtual workspace manifest"));
+        // workspace roots with a package of their own are fine
+        assert!(add_dependency(
+            "[package]\nname = \"x\"\n\n[workspace]\n",
+            Section::Normal,
+            "tokio",
+            &DependencySpec::new("1.40.0")
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn diff() {
+        let manifest = "[package]\nname = \"x\"\n\n[dependencies]\nfoo = { version = \"1\", features = [\n    \"a\",\n] }\nbar = \"1\"\n";
+        let edit = ManifestEdit::with_original(
+            Path::new(MANIFEST),
+            manifest.to_string(),
+            "foo",
+            DependencySpec::new("2"),
+            Section::Normal,
+        );
+        assert_eq!(
+            edit.diff(1),
+            vec![
+                DiffLine::Context("[dependencies]".to_string()),
+                DiffLine::Removed("foo = { version = \"1\", features = [".to_string()),
+                DiffLine::Added("foo = { version = \"2\", features = [".to_string()),
+                DiffLine::Context("    \"a\",".to_string()),
+            ]
+        );
+        assert!(!edit.is_noop());
+
+        let edit = ManifestEdit::with_original(
+            Path::new(MANIFEST),
+            manifest.to_string(),
+            "bar",
+            DependencySpec::new("1"),
+            Section::Normal,
+        );
+        assert!(edit.is_noop());
+
+        let mut edit = ManifestEdit::with_original(
+            Path::new(MANIFEST),
+            "[dependencies]\nserde = { workspace = true }\n".to_string(),
+            "serde",
+            DependencySpec::new("1"),
+            Section::Normal,
+        );
+        assert!(edit.error().is_some());
+        assert!(edit.diff(2).is_empty());
+        edit.next_section();
+        assert_eq!(edit.error(), None);
+        assert_eq!(
+            edit.diff(0),
+            vec![
+                DiffLine::Added(String::new()),
+                DiffLine::Added("[dev-dependencies]".to_string()),
+                DiffLine::Added("serde = \"1\"".to_string()),
+            ]
+        );
+    }
+}
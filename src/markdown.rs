@@ -0,0 +1,919 @@
+//! Rendering markdown into styled terminal text.
+//!
+//! This is not a complete CommonMark implementation, it only aims to make the
+//! common readme constructs readable: headings, emphasis, inline code, fenced
+//! code blocks (with Rust syntax highlighting), lists, block quotes, tables
+//! and links. Links are replaced with footnote numbers, images are collapsed
+//! and lines consisting only of images (usually badges) are dropped
+//! altogether. Common html tags are stripped.
+
+use std::collections::HashMap;
+
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+
+use crate::highlight;
+
+/// Background color used for code.
+const CODE_BG: Color = Color::Indexed(236);
+
+/// Markdown rendered into lines of styled text.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Rendered {
+    pub lines: Vec<Spans<'static>>,
+    /// Link targets, the footnote number of a link is its index plus one
+    pub links: Vec<String>,
+}
+
+/// Renders markdown into styled lines of text.
+pub(crate) fn render(markdown: &str) -> Rendered {
+    let source = strip_comments(markdown).replace('\t', "    ");
+
+    // reference definitions can appear anywhere in the document
+    let mut refs = HashMap::new();
+    let mut lines = Vec::new();
+    for line in source.lines() {
+        match parse_ref_def(line) {
+            Some((label, url)) => {
+                refs.insert(label, url);
+            }
+            None => lines.push(line),
+        }
+    }
+
+    let mut renderer = Renderer {
+        refs,
+        ..Default::default()
+    };
+    for line in lines {
+        renderer.line(line);
+    }
+    renderer.finish()
+}
+
+/// Block of text possibly spanning multiple source lines.
+enum Block {
+    Paragraph,
+    Quote(usize),
+    ListItem(String),
+}
+
+struct CodeBlock {
+    fence: String,
+    /// Indentation of the opening fence, stripped from the code lines
+    indent: usize,
+    lang: String,
+    lines: Vec<String>,
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Spans<'static>>,
+    links: Vec<String>,
+    refs: HashMap<String, String>,
+    /// Currently collected block along with its text
+    block: Option<(Block, String)>,
+    code: Option<CodeBlock>,
+}
+
+impl Renderer {
+    /// Processes a single source line.
+    fn line(&mut self, line: &str) {
+        if let Some(code) = &mut self.code {
+            let trimmed = line.trim();
+            if trimmed.starts_with(&code.fence)
+                && trimmed.chars().all(|c| code.fence.starts_with(c))
+            {
+                self.flush_code();
+            } else {
+                let indent = line.len() - line.trim_start_matches(' ').len();
+                code.lines
+                    .push(line[indent.min(code.indent)..].trim_end().to_string());
+            }
+            return;
+        }
+
+        let trimmed = line.trim();
+        let indent = line.len() - line.trim_start_matches(' ').len();
+
+        if trimmed.is_empty() {
+            self.flush();
+            self.blank();
+        } else if let Some((fence, lang)) = parse_fence(trimmed) {
+            self.flush();
+            self.code = Some(CodeBlock {
+                fence,
+                indent,
+                lang,
+                lines: Vec::new(),
+            });
+        } else if let Some((level, text)) = parse_heading(trimmed) {
+            self.flush();
+            self.heading(level, text);
+        } else if is_setext_underline(trimmed) && matches!(self.block, Some((Block::Paragraph, _)))
+        {
+            if let Some((_, text)) = self.block.take() {
+                let level = if trimmed.starts_with('=') { 1 } else { 2 };
+                self.heading(level, &text);
+            }
+        } else if is_rule(trimmed) {
+            self.flush();
+            self.blank();
+            self.lines.push(Spans::from(Span::styled(
+                "─".repeat(40),
+                Style::default().fg(Color::DarkGray),
+            )));
+            self.blank();
+        } else if trimmed.starts_with('|') {
+            self.flush();
+            if !trimmed.chars().all(|c| matches!(c, '|' | '-' | ':' | ' ')) {
+                self.table_row(trimmed);
+            }
+        } else if trimmed.starts_with('>') {
+            let depth = trimmed
+                .chars()
+                .take_while(|c| *c == '>' || *c == ' ')
+                .filter(|c| *c == '>')
+                .count();
+            let text = trimmed.trim_start_matches(['>', ' ']);
+            match &mut self.block {
+                Some((Block::Quote(d), content)) if *d == depth => {
+                    content.push(' ');
+                    content.push_str(text);
+                }
+                _ => {
+                    self.flush();
+                    self.block = Some((Block::Quote(depth), text.to_string()));
+                }
+            }
+        } else if let Some((marker, text)) = parse_list_item(trimmed) {
+            self.flush();
+            let marker = format!("{}{}", "  ".repeat(indent / 2), marker);
+            self.block = Some((Block::ListItem(marker), text.to_string()));
+        } else {
+            match &mut self.block {
+                Some((_, content)) => {
+                    content.push(' ');
+                    content.push_str(trimmed);
+                }
+                None => self.block = Some((Block::Paragraph, trimmed.to_string())),
+            }
+        }
+    }
+
+    /// Finishes rendering, appending the list of links at the end.
+    fn finish(mut self) -> Rendered {
+        self.flush();
+        if self.code.is_some() {
+            self.flush_code();
+        }
+
+        if !self.links.is_empty() {
+            self.blank();
+            self.lines.push(Spans::from(Span::styled(
+                "Links",
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            self.blank();
+            for (n, link) in self.links.iter().enumerate() {
+                self.lines.push(Spans::from(vec![
+                    Span::styled(
+                        format!("[{}] ", n + 1),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(link.clone()),
+                ]));
+            }
+        }
+
+        while let Some(true) = self.lines.last().map(|l| l.width() == 0) {
+            self.lines.pop();
+        }
+
+        Rendered {
+            lines: self.lines,
+            links: self.links,
+        }
+    }
+
+    /// Pushes an empty line, unless there already is one.
+    fn blank(&mut self) {
+        if let Some(last) = self.lines.last() {
+            if last.width() > 0 {
+                self.lines.push(Spans::default());
+            }
+        }
+    }
+
+    /// Renders the currently collected block.
+    fn flush(&mut self) {
+        let (block, text) = match self.block.take() {
+            Some(b) => b,
+            None => return,
+        };
+
+        let (prefix, style) = match block {
+            Block::Paragraph => (vec![], Style::default()),
+            Block::Quote(depth) => (
+                vec![Span::styled(
+                    "│ ".repeat(depth),
+                    Style::default().fg(Color::DarkGray),
+                )],
+                Style::default()
+                    .fg(Color::Gray)
+                    .add_modifier(Modifier::ITALIC),
+            ),
+            Block::ListItem(marker) => (
+                vec![Span::styled(
+                    format!("{} ", marker),
+                    Style::default().fg(Color::Yellow),
+                )],
+                Style::default(),
+            ),
+        };
+
+        let (spans, has_text) = self.inline(&text, style);
+        // lines made up solely of images are most likely badges
+        if !has_text {
+            return;
+        }
+        let mut line = prefix;
+        line.extend(spans);
+        self.lines.push(Spans::from(line));
+    }
+
+    fn heading(&mut self, level: usize, text: &str) {
+        let style = match level {
+            1 => Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            2 => Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            _ => Style::default().add_modifier(Modifier::BOLD),
+        };
+        let (spans, has_text) = self.inline(text, style);
+        if has_text {
+            self.blank();
+            self.lines.push(Spans::from(spans));
+            self.blank();
+        }
+    }
+
+    fn table_row(&mut self, row: &str) {
+        let cells: Vec<&str> = row
+            .trim_matches('|')
+            .split('|')
+            .map(|cell| cell.trim())
+            .collect();
+        let (spans, _) = self.inline(&cells.join(" │ "), Style::default());
+        self.lines.push(Spans::from(spans));
+    }
+
+    /// Renders the current code block with a distinct background, padding
+    /// all the lines to the same width.
+    fn flush_code(&mut self) {
+        let code = match self.code.take() {
+            Some(c) => c,
+            None => return,
+        };
+        let width = code
+            .lines
+            .iter()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        let style = Style::default().bg(CODE_BG);
+
+        let rust = highlight::is_rust(&code.lang);
+        let mut state = highlight::State::default();
+
+        self.blank();
+        for line in code.lines {
+            let padding = width - line.chars().count();
+            let mut spans = vec![Span::styled(" ", style)];
+            if rust {
+                spans.extend(highlight::highlight_line(&line, &mut state, style));
+            } else {
+                spans.push(Span::styled(line, style));
+            }
+            spans.push(Span::styled(" ".repeat(padding + 1), style));
+            self.lines.push(Spans::from(spans));
+        }
+        self.blank();
+    }
+
+    /// Returns the footnote number for the given link target.
+    fn link_number(&mut self, url: &str) -> usize {
+        match self.links.iter().position(|l| l == url) {
+            Some(n) => n + 1,
+            None => {
+                self.links.push(url.to_string());
+                self.links.len()
+            }
+        }
+    }
+
+    /// Renders inline markdown. Also returns whether there was any text
+    /// content other than images.
+    fn inline(&mut self, text: &str, base: Style) -> (Vec<Span<'static>>, bool) {
+        let chars: Vec<char> = text.chars().collect();
+        let mut spans = Vec::new();
+        let mut buf = String::new();
+        let mut has_text = false;
+        let (mut bold, mut italic, mut strike) = (false, false, false);
+
+        let style_of = |bold: bool, italic: bool, strike: bool| {
+            let mut style = base;
+            if bold {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if italic {
+                style = style.add_modifier(Modifier::ITALIC);
+            }
+            if strike {
+                style = style.add_modifier(Modifier::CROSSED_OUT);
+            }
+            style
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let style = style_of(bold, italic, strike);
+            match c {
+                '\\' if chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                    buf.push(chars[i + 1]);
+                    has_text = true;
+                    i += 2;
+                }
+                '`' => {
+                    let n = run_length(&chars, i);
+                    match find_run(&chars, i + n, '`', n) {
+                        Some(end) => {
+                            push_span(&mut spans, &mut buf, style);
+                            let code: String = chars[i + n..end].iter().collect();
+                            spans.push(Span::styled(code.trim().to_string(), style.bg(CODE_BG)));
+                            has_text = true;
+                            i = end + n;
+                        }
+                        None => {
+                            buf.extend(&chars[i..i + n]);
+                            i += n;
+                        }
+                    }
+                }
+                '*' | '_' | '~' => {
+                    let n = run_length(&chars, i).min(2);
+                    let prev = if i > 0 { chars.get(i - 1) } else { None };
+                    let next = chars.get(i + n);
+                    let intraword = c == '_'
+                        && prev.is_some_and(|p| p.is_alphanumeric())
+                        && next.is_some_and(|n| n.is_alphanumeric());
+                    let flag = match (c, n) {
+                        ('~', 2) => Some(&mut strike),
+                        ('~', _) => None,
+                        (_, 2) => Some(&mut bold),
+                        _ => Some(&mut italic),
+                    };
+                    match flag {
+                        Some(flag) if !intraword => {
+                            let opening = !*flag;
+                            // only open if there's a matching closing marker
+                            // and the marker isn't followed by whitespace
+                            if !opening
+                                || (next.is_some_and(|n| !n.is_whitespace())
+                                    && find_run(&chars, i + n, c, n).is_some())
+                            {
+                                push_span(&mut spans, &mut buf, style);
+                                *flag = opening;
+                            } else {
+                                buf.extend(&chars[i..i + n]);
+                            }
+                        }
+                        _ => buf.extend(&chars[i..i + n]),
+                    }
+                    i += n;
+                }
+                '!' if chars.get(i + 1) == Some(&'[') => match self.parse_link(&chars, i + 1) {
+                    Some((alt, _, end)) => {
+                        push_span(&mut spans, &mut buf, style);
+                        spans.push(image_span(&alt));
+                        i = end;
+                    }
+                    None => {
+                        buf.push(c);
+                        i += 1;
+                    }
+                },
+                '[' => match self.parse_link(&chars, i) {
+                    Some((label, Some(url), end)) => {
+                        push_span(&mut spans, &mut buf, style);
+                        let (label_spans, label_has_text) = self.inline(
+                            &label,
+                            style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+                        );
+                        // links wrapping images are usually badges, drop
+                        // them altogether
+                        if label_has_text {
+                            spans.extend(label_spans);
+                            spans.push(footnote_span(self.link_number(&url)));
+                            has_text = true;
+                        }
+                        i = end;
+                    }
+                    _ => {
+                        buf.push(c);
+                        has_text = true;
+                        i += 1;
+                    }
+                },
+                '<' => {
+                    let end = chars[i..].iter().position(|c| *c == '>').map(|e| e + i);
+                    let tag: String = match end {
+                        Some(end) => chars[i + 1..end].iter().collect(),
+                        None => String::new(),
+                    };
+                    match end {
+                        Some(end) if tag.starts_with("http://") || tag.starts_with("https://") => {
+                            push_span(&mut spans, &mut buf, style);
+                            spans.push(Span::styled(
+                                tag.clone(),
+                                style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+                            ));
+                            spans.push(footnote_span(self.link_number(&tag)));
+                            has_text = true;
+                            i = end + 1;
+                        }
+                        Some(end) if is_html_tag(&tag) => {
+                            if tag.to_lowercase().starts_with("img") {
+                                push_span(&mut spans, &mut buf, style);
+                                spans.push(image_span(&html_attr(&tag, "alt").unwrap_or_default()));
+                            }
+                            i = end + 1;
+                        }
+                        _ => {
+                            buf.push(c);
+                            has_text = true;
+                            i += 1;
+                        }
+                    }
+                }
+                _ => {
+                    if !c.is_whitespace() {
+                        has_text = true;
+                    }
+                    buf.push(c);
+                    i += 1;
+                }
+            }
+        }
+        push_span(&mut spans, &mut buf, style_of(bold, italic, strike));
+
+        (spans, has_text)
+    }
+
+    /// Parses a link starting at the opening bracket at the given position.
+    ///
+    /// Returns the label, the target url if it could be resolved, and the
+    /// position right after the link.
+    fn parse_link(&self, chars: &[char], start: usize) -> Option<(String, Option<String>, usize)> {
+        let label_end = find_closing(chars, start, '[', ']')?;
+        let label: String = chars[start + 1..label_end].iter().collect();
+
+        match chars.get(label_end + 1) {
+            Some('(') => {
+                let target_end = find_closing(chars, label_end + 1, '(', ')')?;
+                let target: String = chars[label_end + 2..target_end].iter().collect();
+                // drop the optional title
+                let url = target
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or("")
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string();
+                Some((label, Some(url), target_end + 1))
+            }
+            Some('[') => {
+                let ref_end = find_closing(chars, label_end + 1, '[', ']')?;
+                let mut reference: String = chars[label_end + 2..ref_end].iter().collect();
+                if reference.is_empty() {
+                    reference = label.clone();
+                }
+                let url = self.refs.get(&reference.to_lowercase()).cloned();
+                Some((label, url, ref_end + 1))
+            }
+            _ => {
+                let url = self.refs.get(&label.to_lowercase()).cloned()?;
+                Some((label, Some(url), label_end + 1))
+            }
+        }
+    }
+}
+
+fn push_span(spans: &mut Vec<Span<'static>>, buf: &mut String, style: Style) {
+    if !buf.is_empty() {
+        spans.push(Span::styled(std::mem::take(buf), style));
+    }
+}
+
+fn footnote_span(n: usize) -> Span<'static> {
+    Span::styled(format!("[{}]", n), Style::default().fg(Color::DarkGray))
+}
+
+fn image_span(alt: &str) -> Span<'static> {
+    let text = match alt.trim() {
+        "" => "[image]".to_string(),
+        alt => format!("[image: {}]", alt),
+    };
+    Span::styled(text, Style::default().fg(Color::DarkGray))
+}
+
+/// Counts the repeated characters starting at the given position.
+fn run_length(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .take_while(|c| **c == chars[start])
+        .count()
+}
+
+/// Finds a run of exactly `n` of the given characters, starting the search
+/// at the given position.
+fn find_run(chars: &[char], start: usize, c: char, n: usize) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == c {
+            let len = run_length(chars, i);
+            if len == n || (c != '`' && len > n) {
+                return Some(i);
+            }
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Finds the closing bracket matching the opening one at the given position.
+fn find_closing(chars: &[char], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Html elements stripped from inline text. Anything else in angle brackets,
+/// e.g. `Vec<T>`, is kept as text.
+const HTML_TAGS: [&str; 49] = [
+    "a",
+    "abbr",
+    "b",
+    "big",
+    "blockquote",
+    "br",
+    "center",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "font",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "ol",
+    "p",
+    "picture",
+    "pre",
+    "s",
+    "samp",
+    "small",
+    "source",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// Checks whether the inside of angle brackets is an html tag or comment.
+fn is_html_tag(tag: &str) -> bool {
+    if tag.starts_with('!') {
+        return true;
+    }
+    let name: String = tag
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    HTML_TAGS.contains(&name.as_str())
+}
+
+/// Extracts an attribute value from the inside of an html tag.
+fn html_attr(tag: &str, name: &str) -> Option<String> {
+    // searched in the tag itself, lowercasing could change the offsets
+    let key = format!("{}=", name);
+    let start = tag.char_indices().map(|(n, _)| n).find(|&n| {
+        tag[n..]
+            .get(..key.len())
+            .is_some_and(|k| k.eq_ignore_ascii_case(&key))
+    })? + key.len();
+    let rest = &tag[start..];
+    let value = match rest.chars().next() {
+        Some(q) if q == '"' || q == '\'' => rest[1..].split(q).next()?,
+        _ => rest.split_whitespace().next()?,
+    };
+    Some(value.to_string())
+}
+
+/// Removes html comments, which can span multiple lines.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<!--") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start..].find("-->") {
+            Some(end) => &rest[start + end + 3..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses a link reference definition, e.g. `[docs]: https://docs.rs/cns`.
+fn parse_ref_def(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim();
+    if line.len() - line.trim_start().len() > 3 || !trimmed.starts_with('[') {
+        return None;
+    }
+    let label_end = trimmed.find("]:")?;
+    let label = &trimmed[1..label_end];
+    let url = trimmed[label_end + 2..].split_whitespace().next()?;
+    if label.is_empty() || label.starts_with('^') {
+        return None;
+    }
+    Some((
+        label.to_lowercase(),
+        url.trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_string(),
+    ))
+}
+
+/// Parses an opening code fence, returning the fence and the language tag.
+fn parse_fence(line: &str) -> Option<(String, String)> {
+    let c = line.chars().next()?;
+    if c != '`' && c != '~' {
+        return None;
+    }
+    let n = line.chars().take_while(|ch| *ch == c).count();
+    if n < 3 {
+        return None;
+    }
+    let info = line[n..].trim();
+    // backtick fences can't contain backticks in the info string
+    if c == '`' && info.contains('`') {
+        return None;
+    }
+    let lang = info
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    Some((line[..n].to_string(), lang))
+}
+
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_setext_underline(line: &str) -> bool {
+    let first = match line.chars().next() {
+        Some(c) if c == '=' || c == '-' => c,
+        _ => return false,
+    };
+    line.chars().all(|c| c == first)
+}
+
+fn is_rule(line: &str) -> bool {
+    let first = match line.chars().next() {
+        Some(c) if c == '-' || c == '*' || c == '_' => c,
+        _ => return false,
+    };
+    line.chars().all(|c| c == first || c == ' ')
+        && line.chars().filter(|c| *c == first).count() >= 3
+}
+
+/// Parses a list item, returning the marker to display and the item text.
+fn parse_list_item(line: &str) -> Option<(String, &str)> {
+    let (marker, rest) = if let Some(rest) = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+    {
+        ("•".to_string(), rest)
+    } else {
+        let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 || digits > 9 {
+            return None;
+        }
+        let rest = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))?;
+        (format!("{}.", &line[..digits]), rest)
+    };
+
+    // task list items
+    let rest = rest.trim_start();
+    if let Some(task) = rest.strip_prefix("[ ] ") {
+        return Some((format!("{} ☐", marker), task));
+    }
+    if let Some(task) = rest
+        .strip_prefix("[x] ")
+        .or_else(|| rest.strip_prefix("[X] "))
+    {
+        return Some((format!("{} ☑", marker), task));
+    }
+    Some((marker, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the rendered lines as plain text.
+    fn text(rendered: &Rendered) -> Vec<String> {
+        rendered
+            .lines
+            .iter()
+            .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn headings() {
+        let rendered = render("# Title\n\nSub\n---\n### Deep ###\n#nospace");
+        assert_eq!(
+            text(&rendered),
+            vec!["Title", "", "Sub", "", "Deep", "", "#nospace"]
+        );
+        let title = &rendered.lines[0].0[0];
+        assert!(title.style.add_modifier.contains(Modifier::UNDERLINED));
+        assert!(!rendered.lines[2].0[0]
+            .style
+            .add_modifier
+            .contains(Modifier::UNDERLINED));
+    }
+
+    #[test]
+    fn fenced_code() {
+        let rendered = render("```rust\nfn a() {}\n  let long = 1;\n```\n~~~\n*not emphasis*\n~~~");
+        assert_eq!(
+            text(&rendered),
+            vec![
+                " fn a() {}       ",
+                "   let long = 1; ",
+                "",
+                " *not emphasis* ",
+            ]
+        );
+        assert!(rendered.lines[0]
+            .0
+            .iter()
+            .any(|span| span.content == "fn" && span.style.fg == Some(Color::Magenta)));
+        assert!(rendered.lines[0]
+            .0
+            .iter()
+            .all(|span| span.style.bg == Some(CODE_BG)));
+    }
+
+    #[test]
+    fn lists() {
+        let rendered = render("- one\n  continued\n    - nested\n1. first\n- [x] done");
+        assert_eq!(
+            text(&rendered),
+            vec!["• one continued", "    • nested", "1. first", "• ☑ done"]
+        );
+    }
+
+    #[test]
+    fn block_quotes() {
+        let rendered = render("> quoted\n> text\n>> nested");
+        assert_eq!(text(&rendered), vec!["│ quoted text", "│ │ nested"]);
+        assert!(rendered.lines[0].0[1]
+            .style
+            .add_modifier
+            .contains(Modifier::ITALIC));
+    }
+
+    #[test]
+    fn link_footnotes() {
+        let rendered = render(
+            "See [docs](https://docs.rs \"title\") and [ref][r], [docs](https://docs.rs).\n\n\
+             [r]: https://crates.io",
+        );
+        assert_eq!(
+            text(&rendered),
+            vec![
+                "See docs[1] and ref[2], docs[1].",
+                "",
+                "Links",
+                "",
+                "[1] https://docs.rs",
+                "[2] https://crates.io",
+            ]
+        );
+        assert_eq!(rendered.links, vec!["https://docs.rs", "https://crates.io"]);
+    }
+
+    #[test]
+    fn badge_lines_dropped() {
+        let rendered = render(
+            "[![build](https://ci/badge.svg)](https://ci) ![docs](https://docs/badge.svg)\n\n\
+             Text ![logo](logo.png)",
+        );
+        assert_eq!(text(&rendered), vec!["Text [image: logo]"]);
+        assert!(rendered.links.is_empty());
+    }
+
+    #[test]
+    fn angle_brackets_in_text() {
+        let rendered = render("Returns Vec<T> or <name>, <b>bold</b><br/> and <!-- x -->end");
+        assert_eq!(
+            text(&rendered),
+            vec!["Returns Vec<T> or <name>, bold and end"]
+        );
+        let rendered = render("Escaped \\<T\\> and `Option<T>`");
+        assert_eq!(text(&rendered), vec!["Escaped <T> and Option<T>"]);
+    }
+
+    #[test]
+    fn attr_case_insensitive() {
+        assert_eq!(
+            html_attr(r#"img SRC="logo.png" Alt='Logo'"#, "src").as_deref(),
+            Some("logo.png")
+        );
+        assert_eq!(
+            html_attr(r#"img SRC="logo.png" Alt='Logo'"#, "alt").as_deref(),
+            Some("Logo")
+        );
+        assert_eq!(
+            html_attr("img width=100 src=x", "width").as_deref(),
+            Some("100")
+        );
+        assert_eq!(html_attr("img src=x", "alt"), None);
+    }
+
+    #[test]
+    fn attr_after_multibyte_text() {
+        // `İ` is longer lowercased than it is in the tag
+        let tag = r#"img alt="İİİİ" title="ŞİŞ" src="badge.svg""#;
+        assert_eq!(html_attr(tag, "src").as_deref(), Some("badge.svg"));
+        assert_eq!(html_attr(tag, "title").as_deref(), Some("ŞİŞ"));
+    }
+}
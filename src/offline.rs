@@ -0,0 +1,939 @@
+//! Offline mode backed by the crates.io database dump.
+//!
+//! crates.io publishes a daily dump of its database at
+//! `https://static.crates.io/db-dump.tar.gz`. Once unpacked, its csv tables
+//! hold enough to search crates and show most of their data without network
+//! access. Crate metadata is loaded into memory on startup, while readmes,
+//! download statistics and version histories are read from the dump on demand.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Error, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use consecrates::api::CrateLinks;
+
+use crate::api::{Publisher, Version};
+use crate::dirs;
+use crate::features::Features;
+use crate::items::{Crate, Remote};
+use crate::query::{Query, Sort, Vocabulary, DEFAULT_PER_PAGE};
+use crate::readme::Readme;
+use crate::search::Page;
+use crate::stats::{self, Stats};
+use crate::version::SemVer;
+
+/// Where the dump can be downloaded from.
+pub const DUMP_URL: &str = "https://static.crates.io/db-dump.tar.gz";
+
+/// Reason given for data that can't be shown in offline mode.
+const NOT_AVAILABLE: &str = "not available in offline mode";
+
+/// Returns the default location of the unpacked dump, i.e.
+/// `$XDG_DATA_HOME/cns/db-dump`.
+pub(crate) fn default_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("db-dump"))
+}
+
+/// Crate as stored in the dump.
+struct DumpCrate {
+    /// Numeric id used for relations between the dump tables
+    id: u64,
+    name: String,
+    description: Option<String>,
+    documentation: Option<String>,
+    homepage: Option<String>,
+    repository: Option<String>,
+    downloads: u64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    categories: Vec<String>,
+    keywords: Vec<String>,
+    /// Lowercase logins of the users and teams owning the crate
+    owners: Vec<String>,
+    max_version: String,
+    license: Option<String>,
+    /// Minimum supported Rust version of the max version
+    msrv: Option<String>,
+    /// Normalized name, description and keywords used for matching queries
+    haystack: String,
+    /// Offset of the crate record in `crates.csv`, used to read the readme
+    offset: u64,
+    /// Offsets of the records of all the crate versions in `versions.csv`
+    versions: Vec<u64>,
+}
+
+/// Unpacked crates.io database dump.
+pub(crate) struct Dump {
+    /// Directory containing the csv tables
+    dir: PathBuf,
+    crates: Vec<DumpCrate>,
+    by_name: HashMap<String, usize>,
+    /// Statistics read so far by crate id, the daily downloads can only be
+    /// found by scanning the whole downloads table
+    stats: Mutex<HashMap<u64, Stats>>,
+    /// Logins of the users by user id, naming the publishers of versions
+    logins: HashMap<u64, String>,
+}
+
+impl Dump {
+    /// Loads the dump unpacked into the given directory.
+    ///
+    /// Accepts either the directory with the csv tables, or any of its
+    /// parents up to the directory the dump archive was unpacked into.
+    pub fn open(dir: &Path) -> Result<Dump> {
+        let dir = find_tables(dir).ok_or_else(|| {
+            Error::msg(format!(
+                "crates.io database dump not found in {}, download it from {}",
+                dir.display(),
+                DUMP_URL
+            ))
+        })?;
+
+        let mut crates = Vec::new();
+        let mut by_id = HashMap::new();
+        read_table(&dir.join("crates.csv"), |offset, row| {
+            by_id.insert(row.num("id"), crates.len());
+            crates.push(DumpCrate {
+                id: row.num("id"),
+                name: row.get("name").to_string(),
+                description: row.opt("description"),
+                documentation: row.opt("documentation"),
+                homepage: row.opt("homepage"),
+                repository: row.opt("repository"),
+                downloads: row.num("downloads"),
+                created_at: parse_time(row.get("created_at")),
+                updated_at: parse_time(row.get("updated_at")),
+                categories: Vec::new(),
+                keywords: Vec::new(),
+                owners: Vec::new(),
+                max_version: String::new(),
+                license: None,
+                msrv: None,
+                haystack: String::new(),
+                offset,
+                versions: Vec::new(),
+            });
+        })?;
+
+        // crates.io shows the highest version that's neither yanked nor
+        // a pre-release, unless there isn't any
+        let mut max_versions: HashMap<usize, (bool, bool, SemVer)> = HashMap::new();
+        read_table(&dir.join("versions.csv"), |offset, row| {
+            let n = match by_id.get(&row.num("crate_id")) {
+                Some(n) => *n,
+                None => return,
+            };
+            crates[n].versions.push(offset);
+            let semver = SemVer::parse(row.get("num"));
+            let rank = (row.get("yanked") != "t", semver.release, semver);
+            let higher = match max_versions.get(&n) {
+                Some(max) => rank > *max,
+                None => true,
+            };
+            if higher {
+                crates[n].max_version = row.get("num").to_string();
+                crates[n].license = row.opt("license");
+                crates[n].msrv = row.opt("rust_version");
+                max_versions.insert(n, rank);
+            }
+        })?;
+        // like crates.io, report crates with all versions yanked as 0.0.0
+        for (n, (not_yanked, _, _)) in &max_versions {
+            if !not_yanked {
+                crates[*n].max_version = "0.0.0".to_string();
+            }
+        }
+
+        // categories and keywords are optional, the search will just be
+        // less precise without them
+        let mut categories = HashMap::new();
+        let _ = read_table(&dir.join("categories.csv"), |_, row| {
+            categories.insert(row.num("id"), row.get("slug").to_string());
+        });
+        let _ = read_table(&dir.join("crates_categories.csv"), |_, row| {
+            if let (Some(n), Some(slug)) = (
+                by_id.get(&row.num("crate_id")),
+                categories.get(&row.num("category_id")),
+            ) {
+                crates[*n].categories.push(slug.clone());
+            }
+        });
+        let mut keywords = HashMap::new();
+        let _ = read_table(&dir.join("keywords.csv"), |_, row| {
+            keywords.insert(row.num("id"), row.get("keyword").to_string());
+        });
+        let _ = read_table(&dir.join("crates_keywords.csv"), |_, row| {
+            if let (Some(n), Some(keyword)) = (
+                by_id.get(&row.num("crate_id")),
+                keywords.get(&row.num("keyword_id")),
+            ) {
+                crates[*n].keywords.push(keyword.clone());
+            }
+        });
+
+        let mut logins = HashMap::new();
+        let _ = read_table(&dir.join("users.csv"), |_, row| {
+            logins.insert(row.num("id"), row.get("gh_login").to_string());
+        });
+        let users: HashMap<u64, String> = logins
+            .iter()
+            .map(|(id, login)| (*id, login.to_lowercase()))
+            .collect();
+        let mut teams = HashMap::new();
+        let _ = read_table(&dir.join("teams.csv"), |_, row| {
+            teams.insert(row.num("id"), row.get("login").to_lowercase());
+        });
+        let _ = read_table(&dir.join("crate_owners.csv"), |_, row| {
+            // owner kind is 0 for users and 1 for teams
+            let logins = match row.get("owner_kind") {
+                "1" => &teams,
+                _ => &users,
+            };
+            if let (Some(n), Some(login)) = (
+                by_id.get(&row.num("crate_id")),
+                logins.get(&row.num("owner_id")),
+            ) {
+                crates[*n].owners.push(login.clone());
+            }
+        });
+
+        for item in &mut crates {
+            item.haystack = normalize(&format!(
+                "{} {} {}",
+                item.name,
+                item.description.as_deref().unwrap_or(""),
+                item.keywords.join(" ")
+            ));
+        }
+        let by_name = crates
+            .iter()
+            .enumerate()
+            .map(|(n, c)| (c.name.clone(), n))
+            .collect();
+
+        Ok(Dump {
+            dir,
+            crates,
+            by_name,
+            stats: Mutex::new(HashMap::new()),
+            logins,
+        })
+    }
+
+    /// Searches the dump using the parsed query. The page number given in
+    /// the query can be overridden.
+    pub fn search(&self, query: &Query, page_number: Option<usize>) -> Page {
+        let text = query.text();
+        let words: Vec<String> = text.split_whitespace().map(normalize).collect();
+        let phrase = words.join("-");
+        let category = query.category();
+        let keyword = query.keyword();
+        let user = query.user().map(|u| u.to_lowercase());
+        let team = query.team().map(|t| t.to_lowercase());
+        let page = page_number.or_else(|| query.page()).unwrap_or(1);
+        let per_page = query.per_page().unwrap_or(DEFAULT_PER_PAGE);
+
+        let mut found: Vec<&DumpCrate> = self
+            .crates
+            .iter()
+            .filter(|c| words.iter().all(|w| c.haystack.contains(w.as_str())))
+            .filter(|c| match &keyword {
+                Some(k) => c.keywords.iter().any(|kw| kw == k),
+                None => true,
+            })
+            .filter(|c| match (&user, &team) {
+                (Some(owner), _) | (None, Some(owner)) => c.owners.contains(owner),
+                (None, None) => true,
+            })
+            .filter(|c| match &category {
+                Some(cat) => c
+                    .categories
+                    .iter()
+                    .any(|c| c == cat || c.starts_with(&format!("{}::", cat))),
+                None => true,
+            })
+            .collect();
+
+        match query.sort() {
+            Some(Sort::Alphabetical) => found.sort_by(|a, b| a.name.cmp(&b.name)),
+            // recent downloads are not part of the dump
+            Some(Sort::AllTimeDownloads) | Some(Sort::RecentDownloads) => {
+                found.sort_by_key(|c| Reverse(c.downloads))
+            }
+            Some(Sort::RecentUpdates) => found.sort_by_key(|c| Reverse(c.updated_at)),
+            Some(Sort::NewlyAdded) => found.sort_by_key(|c| Reverse(c.created_at)),
+            // relevance, exact matches first, then crates with the phrase
+            // in their name
+            None => found.sort_by_key(|c| {
+                let name = normalize(&c.name);
+                (
+                    Reverse(name == phrase),
+                    Reverse(name.contains(&phrase)),
+                    Reverse(c.downloads),
+                )
+            }),
+        }
+
+        Page {
+            total: found.len() as u64,
+            crates: found
+                .into_iter()
+                .skip((page - 1) * per_page)
+                .take(per_page)
+                .map(|c| self.to_crate(c, &phrase))
+                .collect(),
+            number: page,
+            filtered: 0,
+        }
+    }
+
+    /// Returns the crate with the given name.
+    pub fn get(&self, name: &str) -> Option<Crate> {
+        let item = &self.crates[*self.by_name.get(name)?];
+        Some(self.to_crate(item, &normalize(name)))
+    }
+
+    /// Brings a crate stored outside of the dump, e.g. a pinned one, into
+    /// offline mode. The crate is taken from the dump if it's there,
+    /// otherwise the data that would have to be fetched is marked as not
+    /// available.
+    pub fn offline(&self, item: &Crate) -> Crate {
+        if let Some(found) = self.get(&item.id) {
+            return found;
+        }
+        let not_available = || NOT_AVAILABLE.to_string();
+        Crate {
+            readme: Remote::Failed(not_available()),
+            repo_info: Remote::Failed(not_available()),
+            stats: Remote::Failed(not_available()),
+            dependencies: Remote::Failed(not_available()),
+            reverse_dependencies: Remote::Failed(not_available()),
+            msrv: Remote::Failed(not_available()),
+            tree: Remote::Failed(not_available()),
+            versions: Remote::Failed(not_available()),
+            features: Remote::Failed(not_available()),
+            ..item.clone()
+        }
+    }
+
+    /// Returns the categories and keywords found in the dump.
+    pub fn vocabulary(&self) -> Vocabulary {
+        let mut keywords: HashMap<&str, usize> = HashMap::new();
+        for item in &self.crates {
+            for keyword in &item.keywords {
+                *keywords.entry(keyword).or_default() += 1;
+            }
+        }
+        let mut keywords: Vec<(&str, usize)> = keywords.into_iter().collect();
+        keywords.sort_by_key(|(keyword, count)| (Reverse(*count), *keyword));
+
+        let mut categories: Vec<String> = self
+            .crates
+            .iter()
+            .flat_map(|c| c.categories.iter().cloned())
+            .collect();
+        categories.sort();
+        categories.dedup();
+
+        Vocabulary {
+            categories,
+            keywords: keywords.into_iter().map(|(k, _)| k.to_string()).collect(),
+        }
+    }
+
+    /// Reads the readme of the given crate from `crates.csv`.
+    pub fn readme(&self, name: &str) -> Result<Readme> {
+        let item = self.find(name)?;
+        let mut readme = None;
+        read_records(&self.dir.join("crates.csv"), &[item.offset], |row| {
+            readme = row.opt("readme").filter(|r| !r.trim().is_empty());
+        })?;
+        match readme {
+            Some(markdown) => Ok(Readme::new(markdown)),
+            None => Err(Error::msg("crate has no readme")),
+        }
+    }
+
+    /// Reads download statistics of the given crate from the dump.
+    pub fn stats(&self, name: &str) -> Result<Stats> {
+        let item = self.find(name)?;
+        if let Some(stats) = self.stats.lock().unwrap().get(&item.id) {
+            return Ok(stats.clone());
+        }
+
+        let mut versions = Vec::new();
+        read_records(&self.dir.join("versions.csv"), &item.versions, |row| {
+            versions.push((
+                parse_time(row.get("created_at")),
+                row.num("id"),
+                row.get("num").to_string(),
+                row.num("downloads"),
+            ));
+        })?;
+        versions.sort_by_key(|v| Reverse(v.0));
+
+        // daily downloads only cover the last few months before the dump
+        // was made
+        let ids: HashSet<u64> = versions.iter().map(|v| v.1).collect();
+        let mut per_day: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+        let mut last_day = None;
+        let path = self.dir.join("version_downloads.csv");
+        if path.exists() {
+            read_table(&path, |_, row| {
+                let day = match NaiveDate::parse_from_str(row.get("date"), "%Y-%m-%d") {
+                    Ok(d) => d,
+                    Err(_) => return,
+                };
+                last_day = last_day.max(Some(day));
+                if ids.contains(&row.num("version_id")) {
+                    *per_day.entry(day).or_default() += row.num("downloads");
+                }
+            })?;
+        }
+
+        let stats = Stats {
+            daily: stats::daily_downloads(
+                &per_day,
+                last_day.unwrap_or_else(|| Utc::now().date_naive()),
+            ),
+            versions: versions
+                .into_iter()
+                .map(|(_, _, num, downloads)| (num, downloads))
+                .collect(),
+        };
+        self.stats.lock().unwrap().insert(item.id, stats.clone());
+        Ok(stats)
+    }
+
+    /// Reads all the published versions of the given crate from the dump,
+    /// newest first.
+    pub fn versions(&self, name: &str) -> Result<Vec<Version>> {
+        let item = self.find(name)?;
+        let mut versions = Vec::new();
+        read_records(&self.dir.join("versions.csv"), &item.versions, |row| {
+            // publishers are referred to by user ids
+            let published_by = row
+                .opt("published_by")
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| self.logins.get(&id))
+                .map(|login| Publisher {
+                    login: login.clone(),
+                });
+            versions.push(Version {
+                id: row.num("id"),
+                num: row.get("num").to_string(),
+                created_at: parse_time(row.get("created_at")),
+                downloads: row.num("downloads"),
+                yanked: row.get("yanked") == "t",
+                rust_version: row.opt("rust_version"),
+                published_by,
+                crate_size: row.opt("crate_size").and_then(|size| size.parse().ok()),
+                license: row.opt("license"),
+            });
+        })?;
+        versions.sort_by_key(|v| Reverse(v.created_at));
+        Ok(versions)
+    }
+
+    /// Reads the features declared by the max version of the given crate
+    /// from the dump.
+    pub fn features(&self, name: &str) -> Result<Features> {
+        let item = self.find(name)?;
+        let mut features = None;
+        read_records(&self.dir.join("versions.csv"), &item.versions, |row| {
+            if row.get("num") == item.max_version {
+                features = Some(row.get("features").to_string());
+            }
+        })?;
+        match features.as_deref() {
+            Some("") | None => Ok(Features::new()),
+            Some(features) => Ok(serde_json::from_str(features)?),
+        }
+    }
+
+    /// Finds the crate with the given name.
+    fn find(&self, name: &str) -> Result<&DumpCrate> {
+        self.by_name
+            .get(name)
+            .map(|n| &self.crates[*n])
+            .ok_or_else(|| Error::msg(format!("crate not found in the dump: {}", name)))
+    }
+
+    /// Maps a crate from the dump into a crate item. The readme is read
+    /// separately, as it's stored along with the crate record.
+    fn to_crate(&self, item: &DumpCrate, phrase: &str) -> Crate {
+        let api_path = format!("/api/v1/crates/{}", item.name);
+        Crate {
+            id: item.name.clone(),
+            name: item.name.clone(),
+            description: item.description.clone(),
+            license: item.license.clone(),
+            documentation: item.documentation.clone(),
+            homepage: item.homepage.clone(),
+            repository: item.repository.clone(),
+            downloads: item.downloads,
+            recent_downloads: None,
+            categories: Some(item.categories.clone()),
+            keywords: Some(item.keywords.clone()),
+            max_version: item.max_version.clone(),
+            links: CrateLinks {
+                owner_team: format!("{}/owner_team", api_path),
+                owner_user: format!("{}/owner_user", api_path),
+                owners: format!("{}/owners", api_path),
+                reverse_dependencies: format!("{}/reverse_dependencies", api_path),
+                version_downloads: format!("{}/downloads", api_path),
+                versions: Some(format!("{}/versions", api_path)),
+            },
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+            exact_match: Some(normalize(&item.name) == phrase),
+            readme: Remote::Missing,
+            repo_info: Remote::Failed(NOT_AVAILABLE.to_string()),
+            stats: Remote::Missing,
+            dependencies: Remote::Failed(NOT_AVAILABLE.to_string()),
+            reverse_dependencies: Remote::Failed(NOT_AVAILABLE.to_string()),
+            msrv: Remote::Ready(item.msrv.clone()),
+            tree: Remote::Failed(NOT_AVAILABLE.to_string()),
+            versions: Remote::Missing,
+            features: Remote::Missing,
+        }
+    }
+}
+
+/// Finds the directory containing the dump tables. The dump archive unpacks
+/// into a directory named after the dump date, with the tables in its `data`
+/// subdirectory.
+fn find_tables(dir: &Path) -> Option<PathBuf> {
+    let is_tables = |d: &Path| d.join("crates.csv").is_file();
+    if is_tables(dir) {
+        return Some(dir.to_path_buf());
+    }
+    if is_tables(&dir.join("data")) {
+        return Some(dir.join("data"));
+    }
+    // use the most recent dump if there are multiple
+    let mut dumps: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().join("data"))
+        .filter(|d| is_tables(d))
+        .collect();
+    dumps.sort();
+    dumps.pop()
+}
+
+/// Normalizes crate names for comparison, crates.io treats `-` and `_` as
+/// the same character.
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace('_', "-")
+}
+
+/// Parses postgres timestamps, e.g. `2021-03-06 19:35:12.456789+00`.
+fn parse_time(s: &str) -> DateTime<Utc> {
+    s.get(..19)
+        .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
+        .map(|t| t.and_utc())
+        .unwrap_or_default()
+}
+
+/// Single record of a dump table, with fields accessible by column name.
+struct Row<'a> {
+    columns: &'a HashMap<String, usize>,
+    fields: Vec<String>,
+}
+
+impl Row<'_> {
+    fn column(&self, name: &str) -> Option<usize> {
+        self.columns.get(name).cloned()
+    }
+
+    fn get(&self, name: &str) -> &str {
+        self.column(name)
+            .and_then(|n| self.fields.get(n))
+            .map(|f| f.as_str())
+            .unwrap_or("")
+    }
+
+    fn opt(&self, name: &str) -> Option<String> {
+        Some(self.get(name).to_string()).filter(|f| !f.is_empty())
+    }
+
+    fn num(&self, name: &str) -> u64 {
+        self.get(name).parse().unwrap_or(0)
+    }
+}
+
+/// Dump table opened for reading, positioned right after the header.
+struct Table {
+    reader: CsvReader<BufReader<File>>,
+    columns: HashMap<String, usize>,
+}
+
+impl Table {
+    /// Opens the table, returns `None` if it's empty.
+    fn open(path: &Path) -> Result<Option<Table>> {
+        let file = File::open(path)
+            .map_err(|e| Error::msg(format!("failed opening {}: {}", path.display(), e)))?;
+        let mut reader = CsvReader::new(BufReader::new(file));
+        Ok(reader.next_record()?.map(|(_, header)| Table {
+            reader,
+            columns: header
+                .into_iter()
+                .enumerate()
+                .map(|(n, c)| (c, n))
+                .collect(),
+        }))
+    }
+}
+
+/// Reads all the records of a dump table, passing each one along with its
+/// offset in the file.
+fn read_table(path: &Path, mut f: impl FnMut(u64, &Row)) -> Result<()> {
+    let Table {
+        mut reader,
+        columns,
+    } = match Table::open(path)? {
+        Some(table) => table,
+        None => return Ok(()),
+    };
+    while let Some((offset, fields)) = reader.next_record()? {
+        f(
+            offset,
+            &Row {
+                columns: &columns,
+                fields,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Reads the records of a dump table found at the given offsets, as passed
+/// by `read_table`.
+fn read_records(path: &Path, offsets: &[u64], mut f: impl FnMut(&Row)) -> Result<()> {
+    let Table {
+        mut reader,
+        columns,
+    } = match Table::open(path)? {
+        Some(table) => table,
+        None => return Ok(()),
+    };
+    for offset in offsets {
+        reader.seek(*offset)?;
+        if let Some((_, fields)) = reader.next_record()? {
+            f(&Row {
+                columns: &columns,
+                fields,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CsvState {
+    Unquoted,
+    Quoted,
+    /// Quote encountered inside a quoted field, either closing the field
+    /// or escaping another quote
+    QuoteEnd,
+}
+
+/// Minimal reader of the csv files produced by postgres.
+struct CsvReader<R> {
+    inner: R,
+    /// Number of bytes read so far
+    offset: u64,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    fn new(inner: R) -> Self {
+        CsvReader { inner, offset: 0 }
+    }
+
+    /// Reads the next record, returning its offset along with the fields.
+    fn next_record(&mut self) -> Result<Option<(u64, Vec<String>)>> {
+        let start = self.offset;
+        let mut fields = Vec::new();
+        let mut field = Vec::new();
+        let mut state = CsvState::Unquoted;
+        let mut empty = true;
+        loop {
+            let buf = self.inner.fill_buf()?;
+            if buf.is_empty() {
+                if empty {
+                    return Ok(None);
+                }
+                fields.push(String::from_utf8_lossy(&field).into_owned());
+                return Ok(Some((start, fields)));
+            }
+
+            let mut consumed = 0;
+            let mut done = false;
+            for &b in buf {
+                consumed += 1;
+                empty = false;
+                match state {
+                    CsvState::Quoted => {
+                        match b {
+                            b'"' => state = CsvState::QuoteEnd,
+                            _ => field.push(b),
+                        }
+                        continue;
+                    }
+                    CsvState::QuoteEnd if b == b'"' => {
+                        field.push(b);
+                        state = CsvState::Quoted;
+                        continue;
+                    }
+                    _ => state = CsvState::Unquoted,
+                }
+                match b {
+                    b'"' if field.is_empty() => state = CsvState::Quoted,
+                    b',' => {
+                        let f = std::mem::take(&mut field);
+                        fields.push(String::from_utf8_lossy(&f).into_owned());
+                    }
+                    b'\n' => {
+                        if field.last() == Some(&b'\r') {
+                            field.pop();
+                        }
+                        done = true;
+                        break;
+                    }
+                    _ => field.push(b),
+                }
+            }
+            self.inner.consume(consumed);
+            self.offset += consumed as u64;
+            if done {
+                fields.push(String::from_utf8_lossy(&field).into_owned());
+                return Ok(Some((start, fields)));
+            }
+        }
+    }
+}
+
+impl<R: BufRead + Seek> CsvReader<R> {
+    /// Moves to the record starting at the given offset.
+    fn seek(&mut self, offset: u64) -> Result<()> {
+        self.inner.seek(SeekFrom::Start(offset))?;
+        self.offset = offset;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    /// Writes a small dump into a temporary directory and loads it.
+    fn dump(name: &str) -> Dump {
+        let dir = dirs::test_dir(name).join("2024-01-02-020000");
+        let tables = [
+            (
+                "crates.csv",
+                "id,name,description,documentation,homepage,repository,downloads,\
+                 created_at,updated_at,readme\n\
+                 1,serde,\"A generic serialization framework, \"\"fast\"\"\",,,,500,\
+                 2015-01-01 00:00:00.0+00,2024-01-01 00:00:00.0+00,\"# Serde\n\nreadme\"\n\
+                 2,serde_json,JSON support for serde,,,,300,\
+                 2015-06-01 00:00:00.0+00,2023-06-01 00:00:00.0+00,\n\
+                 3,tokio,An async runtime,,,,400,\
+                 2016-01-01 00:00:00.0+00,2023-12-01 00:00:00.0+00,\n",
+            ),
+            (
+                "versions.csv",
+                "id,crate_id,num,yanked,license,rust_version,created_at,downloads,\
+                 published_by,crate_size,features\n\
+                 10,1,1.0.0,f,MIT,,2015-01-01 00:00:00.0+00,100,7,1000,{}\n\
+                 11,1,1.1.0,f,MIT OR Apache-2.0,1.31,2020-01-01 00:00:00.0+00,300,7,2000,\
+                 \"{\"\"std\"\":[]}\"\n\
+                 12,1,2.0.0-alpha,f,MIT,,2023-01-01 00:00:00.0+00,50,,,{}\n\
+                 13,1,1.2.0,t,MIT,,2022-01-01 00:00:00.0+00,50,,,{}\n\
+                 20,2,0.1.0,t,MIT,,2015-06-01 00:00:00.0+00,300,,,{}\n\
+                 30,3,1.0.0,f,MIT,1.63,2016-01-01 00:00:00.0+00,400,,,{}\n",
+            ),
+            (
+                "version_downloads.csv",
+                "version_id,downloads,date\n\
+                 11,5,2024-01-01\n\
+                 10,2,2024-01-01\n\
+                 11,3,2023-12-31\n\
+                 30,9,2024-01-02\n",
+            ),
+            ("keywords.csv", "id,keyword\n1,serialization\n2,async\n"),
+            (
+                "crates_keywords.csv",
+                "crate_id,keyword_id\n1,1\n2,1\n3,2\n",
+            ),
+            ("users.csv", "id,gh_login\n7,DTolnay\n"),
+        ];
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        for (file, contents) in tables {
+            std::fs::write(dir.join("data").join(file), contents).unwrap();
+        }
+        Dump::open(dir.parent().unwrap()).unwrap()
+    }
+
+    fn names(page: &Page) -> Vec<&str> {
+        page.crates.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn csv_records() {
+        let csv = "a,b,c\r\n\"quoted, comma\",\"escaped \"\"quote\"\"\",\"multi\nline\"\n,,last";
+        let mut reader = CsvReader::new(Cursor::new(csv));
+        let record = |r: Option<(u64, Vec<String>)>| r.unwrap();
+        assert_eq!(
+            record(reader.next_record().unwrap()),
+            (0, vec!["a".into(), "b".into(), "c".into()])
+        );
+        let (offset, fields) = record(reader.next_record().unwrap());
+        assert_eq!(offset, 7);
+        assert_eq!(
+            fields,
+            vec!["quoted, comma", "escaped \"quote\"", "multi\nline"]
+        );
+        let (offset, fields) = record(reader.next_record().unwrap());
+        assert_eq!(&csv[offset as usize..], ",,last");
+        assert_eq!(fields, vec!["", "", "last"]);
+        assert_eq!(reader.next_record().unwrap(), None);
+
+        // records can be read again from their offsets
+        reader.seek(7).unwrap();
+        assert_eq!(record(reader.next_record().unwrap()).1[2], "multi\nline");
+    }
+
+    #[test]
+    fn max_versions() {
+        let dump = dump("offline-versions");
+        let serde = dump.get("serde").unwrap();
+        // yanked and pre-release versions are skipped
+        assert_eq!(serde.max_version, "1.1.0");
+        assert_eq!(serde.license.as_deref(), Some("MIT OR Apache-2.0"));
+        assert!(matches!(serde.msrv, Remote::Ready(Some(ref v)) if v == "1.31"));
+        assert_eq!(
+            serde.description.as_deref(),
+            Some("A generic serialization framework, \"fast\"")
+        );
+        // crates with all versions yanked are reported as 0.0.0
+        assert_eq!(dump.get("serde_json").unwrap().max_version, "0.0.0");
+    }
+
+    #[test]
+    fn search() {
+        let dump = dump("offline-search");
+        let search = |query: &str| dump.search(&Query::parse(query), None);
+
+        // exact matches first, then crates with the phrase in the name
+        let page = search("serde");
+        assert_eq!(names(&page), vec!["serde", "serde_json"]);
+        assert_eq!(page.crates[0].exact_match, Some(true));
+        assert_eq!(names(&search("SERDE-JSON")), vec!["serde_json"]);
+        // keywords are matched too
+        assert_eq!(names(&search("serialization")), vec!["serde", "serde_json"]);
+        assert_eq!(names(&search("keyword=async")), vec!["tokio"]);
+        assert_eq!(
+            names(&search("sort=downloads")),
+            vec!["serde", "tokio", "serde_json"]
+        );
+        assert_eq!(names(&search("nothing")), Vec::<&str>::new());
+
+        let page = dump.search(&Query::parse("sort=alpha per-page=2"), Some(2));
+        assert_eq!(
+            (names(&page), page.total, page.number),
+            (vec!["tokio"], 3, 2)
+        );
+    }
+
+    #[test]
+    fn default_page_size() {
+        let dir = dirs::test_dir("offline-paging");
+        let mut crates = "id,name,downloads\n".to_string();
+        for n in 1..=15 {
+            crates.push_str(&format!("{},crate{},{}\n", n, n, n));
+        }
+        std::fs::write(dir.join("crates.csv"), crates).unwrap();
+        std::fs::write(dir.join("versions.csv"), "id,crate_id,num\n").unwrap();
+        let dump = Dump::open(&dir).unwrap();
+
+        // crates.io default unless the query asks for more
+        let page = dump.search(&Query::parse("crate"), None);
+        assert_eq!((page.crates.len(), page.total), (DEFAULT_PER_PAGE, 15));
+        let page = dump.search(&Query::parse("crate per-page=20"), None);
+        assert_eq!(page.crates.len(), 15);
+    }
+
+    #[test]
+    fn readmes() {
+        let dump = dump("offline-readmes");
+        // readmes are only read once they're needed
+        let page = dump.search(&Query::parse("serde"), None);
+        assert!(matches!(page.crates[0].readme, Remote::Missing));
+        assert_eq!(dump.readme("serde").unwrap().markdown, "# Serde\n\nreadme");
+        assert_eq!(
+            dump.readme("tokio").unwrap_err().to_string(),
+            "crate has no readme"
+        );
+        assert!(dump.readme("rand").is_err());
+    }
+
+    #[test]
+    fn versions_and_publishers() {
+        let dump = dump("offline-publishers");
+        let versions = dump.versions("serde").unwrap();
+        let nums: Vec<&str> = versions.iter().map(|v| v.num.as_str()).collect();
+        assert_eq!(nums, vec!["2.0.0-alpha", "1.2.0", "1.1.0", "1.0.0"]);
+        assert!(versions[1].yanked);
+        assert_eq!(versions[2].crate_size, Some(2000));
+        assert_eq!(versions[2].rust_version.as_deref(), Some("1.31"));
+        // logins keep their case
+        let publisher = |v: &Version| v.published_by.as_ref().map(|p| p.login.clone());
+        assert_eq!(publisher(&versions[2]).as_deref(), Some("DTolnay"));
+        assert_eq!(publisher(&versions[0]), None);
+        assert_eq!(dump.versions("tokio").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn max_version_features() {
+        let dump = dump("offline-features");
+        let features = dump.features("serde").unwrap();
+        assert_eq!(features.keys().collect::<Vec<_>>(), vec!["std"]);
+        assert!(dump.features("tokio").unwrap().is_empty());
+        assert!(dump.features("rand").is_err());
+    }
+
+    #[test]
+    fn download_stats() {
+        let dump = dump("offline-stats");
+        let stats = dump.stats("serde").unwrap();
+        assert_eq!(
+            stats.versions,
+            vec![
+                ("2.0.0-alpha".to_string(), 50),
+                ("1.2.0".to_string(), 50),
+                ("1.1.0".to_string(), 300),
+                ("1.0.0".to_string(), 100),
+            ]
+        );
+        // the history ends with the last day found in the dump
+        let day = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(
+            &stats.daily[stats.daily.len() - 3..],
+            &[
+                (day("2023-12-31"), 3),
+                (day("2024-01-01"), 7),
+                (day("2024-01-02"), 0)
+            ]
+        );
+        assert!(dump.stats.lock().unwrap().contains_key(&1));
+    }
+}
@@ -0,0 +1,146 @@
+//! Crates pinned for comparison.
+//!
+//! Pinned crates are kept across searches and sessions, so that crates that
+//! never show up in the results of a single query can still be compared.
+//! They're stored in `$XDG_DATA_HOME/cns/pinned.json` as of the moment they
+//! were pinned, and refreshed whenever they show up in search results.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::cache;
+use crate::dirs;
+use crate::items::Crate;
+
+/// Set of pinned crates, in the order they were pinned.
+#[derive(Debug, Default)]
+pub(crate) struct Pinned {
+    /// File the crates are stored in, `None` if there's no place for it
+    path: Option<PathBuf>,
+    pub crates: Vec<Crate>,
+}
+
+impl Pinned {
+    /// Loads the crates pinned in previous sessions. Missing or unreadable
+    /// file results in an empty set.
+    pub fn load() -> Self {
+        Pinned::load_from(dirs::data_dir().map(|d| d.join("pinned.json")))
+    }
+
+    fn load_from(path: Option<PathBuf>) -> Self {
+        let crates = path
+            .as_ref()
+            .and_then(|p| fs::read(p).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Pinned { path, crates }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.crates.iter().any(|c| c.id == id)
+    }
+
+    /// Pins the crate, or unpins it if it's already pinned. Returns whether
+    /// the crate ends up pinned.
+    pub fn toggle(&mut self, item: Crate) -> Result<bool> {
+        let pinned = match self.crates.iter().position(|c| c.id == item.id) {
+            Some(n) => {
+                self.crates.remove(n);
+                false
+            }
+            None => {
+                self.crates.push(item);
+                true
+            }
+        };
+        self.save()?;
+        Ok(pinned)
+    }
+
+    /// Updates the stored data of pinned crates found among the given ones,
+    /// saving them only if any of it changed.
+    pub fn refresh(&mut self, items: &[Crate]) {
+        let mut changed = false;
+        for pinned in &mut self.crates {
+            if let Some(item) = items.iter().find(|c| c.id == pinned.id) {
+                // lazily fetched data isn't stored, leave it out of the
+                // comparison
+                if serde_json::to_value(&*pinned).ok() != serde_json::to_value(item).ok() {
+                    *pinned = item.clone();
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            // failing to store the refreshed data is harmless
+            let _ = self.save();
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => cache::write(path, &serde_json::to_vec(&self.crates)?),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::items::{test_crate, Remote};
+
+    fn ids(pinned: &Pinned) -> Vec<&str> {
+        pinned.crates.iter().map(|c| c.id.as_str()).collect()
+    }
+
+    #[test]
+    fn toggle_round_trip() {
+        let path = dirs::test_dir("pinned-toggle").join("pinned.json");
+        let mut pinned = Pinned::load_from(Some(path.clone()));
+        assert!(pinned.crates.is_empty());
+
+        assert!(pinned.toggle(test_crate("serde", "1.0.0")).unwrap());
+        assert!(pinned.toggle(test_crate("tokio", "1.0.0")).unwrap());
+        assert!(pinned.contains("serde"));
+        let loaded = Pinned::load_from(Some(path.clone()));
+        assert_eq!(ids(&loaded), vec!["serde", "tokio"]);
+        assert_eq!(loaded.crates[0].max_version, "1.0.0");
+
+        assert!(!pinned.toggle(test_crate("serde", "1.0.0")).unwrap());
+        assert!(!pinned.contains("serde"));
+        assert_eq!(ids(&Pinned::load_from(Some(path))), vec!["tokio"]);
+    }
+
+    #[test]
+    fn refresh_saves_changes_only() {
+        let path = dirs::test_dir("pinned-refresh").join("pinned.json");
+        let mut pinned = Pinned::load_from(Some(path.clone()));
+        pinned.toggle(test_crate("serde", "1.0.0")).unwrap();
+
+        // same data isn't written again
+        fs::remove_file(&path).unwrap();
+        let mut found = test_crate("serde", "1.0.0");
+        found.readme = Remote::Loading;
+        pinned.refresh(&[found, test_crate("rand", "0.8.0")]);
+        assert!(!path.exists());
+
+        pinned.refresh(&[test_crate("serde", "1.0.1")]);
+        let loaded = Pinned::load_from(Some(path));
+        assert_eq!(ids(&loaded), vec!["serde"]);
+        assert_eq!(loaded.crates[0].max_version, "1.0.1");
+    }
+
+    #[test]
+    fn unreadable_file() {
+        let path = dirs::test_dir("pinned-unreadable").join("pinned.json");
+        fs::write(&path, "not json").unwrap();
+        assert!(Pinned::load_from(Some(path)).crates.is_empty());
+        // without a place to store them crates are only pinned in memory
+        let mut pinned = Pinned::load_from(None);
+        assert!(pinned.toggle(test_crate("serde", "1.0.0")).unwrap());
+    }
+}
@@ -0,0 +1,161 @@
+//! Bounded pool of worker threads fetching crate data in the background.
+//!
+//! Jobs are processed in queue order. Each job is tagged with a key (usually
+//! the crate id) which allows moving all the jobs related to a particular
+//! crate to the front of the queue. Dropping the pool cancels all the
+//! pending jobs and lets the workers exit once they finish their current
+//! job. Requests made by the jobs are expected to use their own timeouts.
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+
+use anyhow::{Error, Result};
+
+/// Default number of worker threads.
+pub const DEFAULT_WORKERS: usize = 4;
+
+struct Job {
+    key: String,
+    task: Box<dyn FnOnce() + Send>,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    /// Number of worker threads spawned so far
+    workers: usize,
+    cancelled: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+/// Pool of worker threads processing a shared job queue.
+///
+/// Worker threads are only spawned once there are jobs to process.
+pub(crate) struct FetchPool {
+    shared: Arc<Shared>,
+    /// Maximum number of worker threads
+    size: usize,
+}
+
+impl Default for FetchPool {
+    fn default() -> Self {
+        FetchPool::new(DEFAULT_WORKERS)
+    }
+}
+
+impl FetchPool {
+    /// Creates a new pool with the given maximum number of workers.
+    pub fn new(size: usize) -> Self {
+        FetchPool {
+            shared: Arc::new(Shared::default()),
+            size,
+        }
+    }
+
+    /// Adds a job to the back of the queue.
+    pub fn push(&self, key: &str, task: impl FnOnce() + Send + 'static) {
+        self.enqueue(key, Box::new(task), false);
+    }
+
+    /// Adds a job to the front of the queue.
+    pub fn push_front(&self, key: &str, task: impl FnOnce() + Send + 'static) {
+        self.enqueue(key, Box::new(task), true);
+    }
+
+    fn enqueue(&self, key: &str, task: Box<dyn FnOnce() + Send>, front: bool) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.cancelled {
+            return;
+        }
+        let job = Job {
+            key: key.to_string(),
+            task,
+        };
+        if front {
+            queue.jobs.push_front(job);
+        } else {
+            queue.jobs.push_back(job);
+        }
+
+        if queue.workers < self.size {
+            queue.workers += 1;
+            let shared = self.shared.clone();
+            std::thread::spawn(move || work(shared));
+        }
+        self.shared.available.notify_one();
+    }
+
+    /// Moves all the queued jobs with the given key to the front of the
+    /// queue, keeping their relative order.
+    pub fn prioritize(&self, key: &str) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let (mut prioritized, rest): (VecDeque<Job>, VecDeque<Job>) =
+            queue.jobs.drain(..).partition(|job| job.key == key);
+        prioritized.extend(rest);
+        queue.jobs = prioritized;
+    }
+
+    /// Drops all the pending jobs and stops the workers.
+    pub fn cancel(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.cancelled = true;
+        queue.jobs.clear();
+        self.shared.available.notify_all();
+    }
+}
+
+impl Drop for FetchPool {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Runs the work, turning a panic into an error so that it can be reported
+/// like any other failure instead of taking down the background thread.
+pub(crate) fn catch_panic<T>(work: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(work)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        Err(Error::msg(format!("internal error: {}", message)))
+    })
+}
+
+/// Processes jobs from the queue until the pool is cancelled.
+fn work(shared: Arc<Shared>) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.cancelled {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop_front() {
+                    break job;
+                }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+        (job.task)();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panic_becomes_error() {
+        let result: Result<()> = catch_panic(|| panic!("boom {}", 1));
+        assert_eq!(result.unwrap_err().to_string(), "internal error: boom 1");
+        assert_eq!(catch_panic(|| Ok(1)).unwrap(), 1);
+    }
+}
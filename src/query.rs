@@ -0,0 +1,716 @@
+//! Search query language.
+//!
+//! Queries consist of whitespace separated tokens, each being either plain
+//! text searched for, or a `key=value` filter:
+//!
+//! - `cat=` / `category=` crates in the given category
+//! - `key=` / `keyword=` / `kw=` crates with the given keyword
+//! - `sort=` sorting of the results
+//! - `user=` crates owned by the given user
+//! - `team=` crates owned by the given team, e.g. `github:org:team`
+//! - `page=` / `per-page=` / `num=` pagination of the results
+//!
+//! Some filters aren't supported by crates.io, they're applied to each page
+//! of results as it comes in instead:
+//!
+//! - `license=MIT|Apache-2.0` crates usable under one of the licenses
+//! - `updated<180d` / `updated>2y` time since the last update, in days,
+//!   weeks, months or years
+//! - `dl>100000` / `dl<10k` all-time downloads
+//! - `stable` crates at version 1.0 or later
+//! - `!yanked` crates with at least one version that's not yanked
+//!
+//! Queries are parsed locally so that mistakes can be pointed out before
+//! sending the query to crates.io.
+
+use std::num::{IntErrorKind, ParseIntError};
+
+use chrono::{DateTime, Duration, Utc};
+use consecrates::Category;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+
+use crate::items::Crate;
+
+/// Maximum number of results per page accepted by crates.io.
+pub const MAX_PER_PAGE: usize = 100;
+
+/// Number of results per page crates.io returns unless asked otherwise.
+pub const DEFAULT_PER_PAGE: usize = 10;
+
+/// Filter keys offered by completion.
+const FILTERS: [&str; 12] = [
+    "cat=",
+    "key=",
+    "sort=",
+    "user=",
+    "team=",
+    "page=",
+    "per-page=",
+    "license=",
+    "updated<",
+    "dl>",
+    "stable",
+    "!yanked",
+];
+
+/// Sorting of the search results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Sort {
+    Alphabetical,
+    AllTimeDownloads,
+    RecentDownloads,
+    RecentUpdates,
+    NewlyAdded,
+}
+
+impl Sort {
+    /// Names used for completion, one per sorting.
+    pub const NAMES: [&'static str; 5] = [
+        "alpha",
+        "downloads",
+        "recent-downloads",
+        "recent-updates",
+        "new",
+    ];
+
+    /// Parses the sorting, accepting a few aliases for each one.
+    pub fn from_str(s: &str) -> Option<Sort> {
+        let sort = match s {
+            "alpha" | "alphabet" | "alphabetic" | "alphabetical" => Sort::Alphabetical,
+            "downloads" | "download" | "dl" | "all-time" => Sort::AllTimeDownloads,
+            "recent-downloads" | "rdl" | "new-downloads" => Sort::RecentDownloads,
+            "recent-updates" | "new-updates" | "updates" | "update" | "rup" => Sort::RecentUpdates,
+            "newly-added" | "new" | "newest" | "latest" => Sort::NewlyAdded,
+            _ => return None,
+        };
+        Some(sort)
+    }
+
+    /// Returns the value of the sort parameter of the crates.io search.
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Sort::Alphabetical => "alpha",
+            Sort::AllTimeDownloads => "downloads",
+            Sort::RecentDownloads => "recent-downloads",
+            Sort::RecentUpdates => "recent-updates",
+            Sort::NewlyAdded => "new",
+        }
+    }
+}
+
+/// Direction of a comparison filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Comparison {
+    Less,
+    Greater,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, value: T, bound: T) -> bool {
+        match self {
+            Comparison::Less => value < bound,
+            Comparison::Greater => value > bound,
+        }
+    }
+}
+
+/// Filter applied locally to the search results.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Filter {
+    /// Allowed license identifiers
+    License(Vec<String>),
+    /// Time since the last update, in days
+    Updated(Comparison, i64),
+    Downloads(Comparison, u64),
+    Stable,
+    NotYanked,
+}
+
+impl Filter {
+    /// Checks whether the crate passes the filter.
+    pub fn matches(&self, item: &Crate, now: DateTime<Utc>) -> bool {
+        match self {
+            Filter::License(allowed) => item
+                .license
+                .as_deref()
+                .is_some_and(|license| license_allowed(license, allowed)),
+            // the age is checked to be in range when parsing
+            Filter::Updated(cmp, days) => {
+                Duration::try_days(*days).is_some_and(|age| cmp.holds(now - item.updated_at, age))
+            }
+            Filter::Downloads(cmp, downloads) => cmp.holds(item.downloads, *downloads),
+            Filter::Stable => {
+                !item.max_version.contains('-')
+                    && item
+                        .max_version
+                        .split('.')
+                        .next()
+                        .and_then(|major| major.parse::<u64>().ok())
+                        .is_some_and(|major| major >= 1)
+            }
+            // crates.io reports 0.0.0 for crates with all versions yanked
+            Filter::NotYanked => item.max_version != "0.0.0",
+        }
+    }
+}
+
+/// Checks whether the SPDX license expression can be satisfied using only
+/// the allowed licenses, e.g. `MIT OR Apache-2.0` is allowed with just
+/// `MIT`, while `MIT AND Apache-2.0` needs both. Parentheses are ignored and
+/// license exceptions are treated as part of the license they modify.
+fn license_allowed(expression: &str, allowed: &[String]) -> bool {
+    let expression = expression
+        .replace(['(', ')'], " ")
+        // old style `MIT/Apache-2.0` expressions
+        .replace('/', " OR ");
+    expression.split(" OR ").any(|alternative| {
+        alternative.split(" AND ").all(|license| {
+            let id = license.split(" WITH ").next().unwrap_or("").trim();
+            allowed.iter().any(|a| a.eq_ignore_ascii_case(id))
+        })
+    })
+}
+
+/// Single term of the query.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Term {
+    Text(String),
+    Category(String),
+    Keyword(String),
+    Sort(Sort),
+    User(String),
+    Team(String),
+    Page(usize),
+    PerPage(usize),
+    Filter(Filter),
+}
+
+/// Token of the query input, along with its location in the input.
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub start: usize,
+    pub end: usize,
+    /// Parsed term, or the reason the token is invalid
+    pub term: Result<Term, String>,
+}
+
+/// Vocabulary known to crates.io, used for validation and completion.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Vocabulary {
+    /// Category slugs, including subcategories
+    pub categories: Vec<String>,
+    /// Keywords, most popular first
+    pub keywords: Vec<String>,
+}
+
+/// Parsed search query.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Query {
+    pub tokens: Vec<Token>,
+}
+
+impl Query {
+    /// Parses the query input. Only the syntax is checked, use `validate`
+    /// to also check the values against the crates.io vocabulary.
+    pub fn parse(input: &str) -> Query {
+        let mut tokens = Vec::new();
+        let mut rest = input;
+        while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+            let len = rest[start..]
+                .find(char::is_whitespace)
+                .unwrap_or(rest.len() - start);
+            let offset = input.len() - rest.len() + start;
+            tokens.push(Token {
+                start: offset,
+                end: offset + len,
+                term: parse_term(&rest[start..start + len]),
+            });
+            rest = &rest[start + len..];
+        }
+        Query { tokens }
+    }
+
+    /// Marks categories not known to crates.io as invalid. The list of
+    /// categories built into the `consecrates` client is used until the
+    /// vocabulary is fetched.
+    pub fn validate(&mut self, vocabulary: &Vocabulary) {
+        for token in &mut self.tokens {
+            if let Ok(Term::Category(slug)) = &token.term {
+                let known = match vocabulary.categories.is_empty() {
+                    true => Category::from_str(slug).is_some(),
+                    false => vocabulary.categories.contains(slug),
+                };
+                if !known {
+                    token.term = Err(format!("unknown category `{}`", slug));
+                }
+            }
+        }
+    }
+
+    /// Returns the reason the first invalid token is invalid.
+    pub fn error(&self) -> Option<&str> {
+        self.tokens
+            .iter()
+            .find_map(|t| t.term.as_ref().err().map(|e| e.as_str()))
+    }
+
+    /// Returns the valid terms of the query.
+    pub fn terms(&self) -> impl Iterator<Item = &Term> {
+        self.tokens.iter().filter_map(|t| t.term.as_ref().ok())
+    }
+
+    /// Returns all the text terms joined together.
+    pub fn text(&self) -> String {
+        self.terms()
+            .filter_map(|t| match t {
+                Term::Text(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the last value given for a filter, as extracted by `f`.
+    pub fn last<T>(&self, f: impl Fn(&Term) -> Option<T>) -> Option<T> {
+        self.terms().filter_map(f).last()
+    }
+
+    pub fn category(&self) -> Option<String> {
+        self.last(|t| match t {
+            Term::Category(c) => Some(c.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn keyword(&self) -> Option<String> {
+        self.last(|t| match t {
+            Term::Keyword(k) => Some(k.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn sort(&self) -> Option<Sort> {
+        self.last(|t| match t {
+            Term::Sort(s) => Some(*s),
+            _ => None,
+        })
+    }
+
+    pub fn user(&self) -> Option<String> {
+        self.last(|t| match t {
+            Term::User(u) => Some(u.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn team(&self) -> Option<String> {
+        self.last(|t| match t {
+            Term::Team(t) => Some(t.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn page(&self) -> Option<usize> {
+        self.last(|t| match t {
+            Term::Page(p) => Some(*p),
+            _ => None,
+        })
+    }
+
+    pub fn per_page(&self) -> Option<usize> {
+        self.last(|t| match t {
+            Term::PerPage(p) => Some(*p),
+            _ => None,
+        })
+    }
+
+    /// Checks whether the crate passes all the local filters of the query.
+    pub fn matches(&self, item: &Crate, now: DateTime<Utc>) -> bool {
+        self.terms().all(|t| match t {
+            Term::Filter(filter) => filter.matches(item, now),
+            _ => true,
+        })
+    }
+
+    /// Creates styled text of the query input, with invalid tokens
+    /// highlighted in red.
+    pub fn highlight(&self, input: &str) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        let mut last = 0;
+        for token in &self.tokens {
+            spans.push(Span::raw(input[last..token.start].to_string()));
+            let text = input[token.start..token.end].to_string();
+            let style = match &token.term {
+                Err(_) => Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::UNDERLINED),
+                Ok(Term::Text(_)) => Style::default(),
+                Ok(_) => Style::default().fg(Color::Cyan),
+            };
+            spans.push(Span::styled(text, style));
+            last = token.end;
+        }
+        spans.push(Span::raw(input[last..].to_string()));
+        spans
+    }
+}
+
+/// Creates the search block line with the highlighted query input.
+pub(crate) fn highlighted_input(
+    input: &str,
+    cursor: bool,
+    vocabulary: &Vocabulary,
+) -> Spans<'static> {
+    let mut query = Query::parse(input);
+    query.validate(vocabulary);
+    let mut spans = query.highlight(input);
+    if cursor {
+        spans.push(Span::raw("|"));
+    }
+    Spans::from(spans)
+}
+
+fn parse_term(token: &str) -> Result<Term, String> {
+    match token {
+        "stable" => return Ok(Term::Filter(Filter::Stable)),
+        "!yanked" => return Ok(Term::Filter(Filter::NotYanked)),
+        _ => (),
+    }
+    let split = match token.find(['=', '<', '>']) {
+        Some(n) => n,
+        None => return Ok(Term::Text(token.to_string())),
+    };
+    let (key, operator, value) = (
+        &token[..split],
+        &token[split..split + 1],
+        &token[split + 1..],
+    );
+    if value.is_empty() {
+        return Err(format!("missing value for `{}`", key));
+    }
+    if operator != "=" {
+        let cmp = match operator {
+            "<" => Comparison::Less,
+            _ => Comparison::Greater,
+        };
+        return match key {
+            "updated" => parse_age(value).map(|days| Term::Filter(Filter::Updated(cmp, days))),
+            "dl" | "downloads" => {
+                parse_count(value).map(|n| Term::Filter(Filter::Downloads(cmp, n)))
+            }
+            _ => Err(format!("`{}` can't be compared with `{}`", key, operator)),
+        };
+    }
+    match key {
+        "license" => {
+            let licenses: Vec<String> = value
+                .split('|')
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .collect();
+            match licenses.is_empty() {
+                true => Err("missing value for `license`".to_string()),
+                false => Ok(Term::Filter(Filter::License(licenses))),
+            }
+        }
+        // resolve the aliases supported by the `consecrates` client
+        "cat" | "category" => Ok(Term::Category(
+            Category::from_str(value)
+                .map(|c| c.to_str().to_string())
+                .unwrap_or_else(|| value.to_lowercase()),
+        )),
+        "key" | "keyword" | "kw" => {
+            let valid = value.chars().next().is_some_and(|c| c.is_alphanumeric())
+                && value
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '+');
+            match valid {
+                true => Ok(Term::Keyword(value.to_lowercase())),
+                false => Err(format!("invalid keyword `{}`", value)),
+            }
+        }
+        "sort" => Sort::from_str(value)
+            .map(Term::Sort)
+            .ok_or_else(|| format!("unknown sorting `{}`", value)),
+        "user" => match value.chars().all(|c| c.is_alphanumeric() || c == '-') {
+            true => Ok(Term::User(value.to_string())),
+            false => Err(format!("invalid user name `{}`", value)),
+        },
+        "team" => match value.split(':').count() {
+            3 => Ok(Term::Team(value.to_string())),
+            // github is the only team provider
+            2 => Ok(Term::Team(format!("github:{}", value))),
+            _ => Err(format!(
+                "teams are given as `github:org:team`, not `{}`",
+                value
+            )),
+        },
+        "page" => match value.parse() {
+            Ok(n) if n > 0 => Ok(Term::Page(n)),
+            _ => Err(format!("invalid page number `{}`", value)),
+        },
+        "per-page" | "num" => match value.parse() {
+            Ok(n) if n > 0 && n <= MAX_PER_PAGE => Ok(Term::PerPage(n)),
+            _ => Err(format!(
+                "results per page must be between 1 and {}",
+                MAX_PER_PAGE
+            )),
+        },
+        _ => Err(format!("unknown filter `{}`", key)),
+    }
+}
+
+/// Parses an age like `180d`, returning the number of days. The age has to
+/// fit in a `Duration`.
+fn parse_age(value: &str) -> Result<i64, String> {
+    let (number, days) = match value.char_indices().last() {
+        Some((n, 'd')) => (&value[..n], 1),
+        Some((n, 'w')) => (&value[..n], 7),
+        Some((n, 'm')) => (&value[..n], 30),
+        Some((n, 'y')) => (&value[..n], 365),
+        _ => (value, 1),
+    };
+    match number.parse::<i64>() {
+        Ok(n) => n
+            .checked_mul(days)
+            .filter(|&days| Duration::try_days(days).is_some())
+            .ok_or_else(|| out_of_range(value)),
+        Err(e) if is_overflow(&e) => Err(out_of_range(value)),
+        Err(_) => Err(format!("invalid age `{}`, use e.g. `180d` or `2y`", value)),
+    }
+}
+
+/// Parses a count like `100000` or `100k`.
+fn parse_count(value: &str) -> Result<u64, String> {
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((n, 'k')) => (&value[..n], 1_000),
+        Some((n, 'm')) => (&value[..n], 1_000_000),
+        _ => (value, 1),
+    };
+    match number.parse::<u64>() {
+        Ok(n) => n.checked_mul(multiplier).ok_or_else(|| out_of_range(value)),
+        Err(e) if is_overflow(&e) => Err(out_of_range(value)),
+        Err(_) => Err(format!("invalid download count `{}`", value)),
+    }
+}
+
+fn is_overflow(e: &ParseIntError) -> bool {
+    matches!(
+        e.kind(),
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+    )
+}
+
+fn out_of_range(value: &str) -> String {
+    format!("value out of range `{}`", value)
+}
+
+/// Tab completion of the last token of the search input, cycling through
+/// the matching candidates on repeated use.
+pub(crate) struct Completion {
+    /// Input preceding the completed part
+    base: String,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl Completion {
+    /// Finds completions for the last token of the input. Filter values
+    /// are completed from the vocabulary, other tokens are completed into
+    /// filter keys.
+    pub fn new(input: &str, vocabulary: &Vocabulary) -> Option<Completion> {
+        // whitespace can be longer than a byte, e.g. an ideographic space
+        let token_start = input
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map(|(n, c)| n + c.len_utf8())
+            .unwrap_or(0);
+        let token = &input[token_start..];
+        if token.is_empty() {
+            return None;
+        }
+
+        let (base, candidates): (String, Vec<String>) = match token.split_once('=') {
+            Some((key, partial)) => {
+                let values: Vec<&str> = match key {
+                    "cat" | "category" => {
+                        vocabulary.categories.iter().map(|s| s.as_str()).collect()
+                    }
+                    "key" | "keyword" | "kw" => {
+                        vocabulary.keywords.iter().map(|s| s.as_str()).collect()
+                    }
+                    "sort" => Sort::NAMES.to_vec(),
+                    _ => return None,
+                };
+                let partial = partial.to_lowercase();
+                (
+                    input[..token_start + key.len() + 1].to_string(),
+                    values
+                        .into_iter()
+                        .filter(|v| v.starts_with(&partial))
+                        .map(|v| v.to_string())
+                        .collect(),
+                )
+            }
+            None => (
+                input[..token_start].to_string(),
+                FILTERS
+                    .iter()
+                    .filter(|f| f.starts_with(token))
+                    .map(|f| f.to_string())
+                    .collect(),
+            ),
+        };
+
+        match candidates.is_empty() {
+            true => None,
+            false => Some(Completion {
+                base,
+                candidates,
+                index: 0,
+            }),
+        }
+    }
+
+    /// Returns the input with the current candidate filled in.
+    pub fn current(&self) -> String {
+        format!("{}{}", self.base, self.candidates[self.index])
+    }
+
+    /// Moves on to the next candidate.
+    pub fn advance(&mut self) {
+        self.index = (self.index + 1) % self.candidates.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary() -> Vocabulary {
+        Vocabulary {
+            categories: vec!["gui".to_string(), "game-development".to_string()],
+            keywords: vec!["tui".to_string(), "terminal".to_string()],
+        }
+    }
+
+    #[test]
+    fn parse_tokens() {
+        let query = Query::parse("  tui  cat=gui sort=downloads ");
+        let spans: Vec<(usize, usize)> = query.tokens.iter().map(|t| (t.start, t.end)).collect();
+        assert_eq!(spans, vec![(2, 5), (7, 14), (15, 29)]);
+        assert_eq!(query.text(), "tui");
+        assert_eq!(query.category().as_deref(), Some("gui"));
+        assert_eq!(query.sort(), Some(Sort::AllTimeDownloads));
+        assert_eq!(query.error(), None);
+    }
+
+    #[test]
+    fn parse_filters() {
+        let query = Query::parse("license=MIT|Apache-2.0 updated<2y dl>10k stable !yanked");
+        let terms: Vec<&Term> = query.terms().collect();
+        assert_eq!(
+            terms,
+            vec![
+                &Term::Filter(Filter::License(vec![
+                    "MIT".to_string(),
+                    "Apache-2.0".to_string()
+                ])),
+                &Term::Filter(Filter::Updated(Comparison::Less, 730)),
+                &Term::Filter(Filter::Downloads(Comparison::Greater, 10_000)),
+                &Term::Filter(Filter::Stable),
+                &Term::Filter(Filter::NotYanked),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_pagination_and_owners() {
+        let query = Query::parse("page=2 num=50 user=dtolnay team=rust-lang:libs");
+        assert_eq!(query.page(), Some(2));
+        assert_eq!(query.per_page(), Some(50));
+        assert_eq!(query.user().as_deref(), Some("dtolnay"));
+        assert_eq!(query.team().as_deref(), Some("github:rust-lang:libs"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (input, error) in [
+            ("cat=", "missing value for `cat`"),
+            ("foo=bar", "unknown filter `foo`"),
+            ("page=0", "invalid page number `0`"),
+            ("num=101", "results per page must be between 1 and 100"),
+            ("sort=random", "unknown sorting `random`"),
+            (
+                "updated<soon",
+                "invalid age `soon`, use e.g. `180d` or `2y`",
+            ),
+            ("cat<gui", "`cat` can't be compared with `<`"),
+            ("kw=-x", "invalid keyword `-x`"),
+            (
+                "updated<99999999999999999y",
+                "value out of range `99999999999999999y`",
+            ),
+            (
+                "updated<200000000000000d",
+                "value out of range `200000000000000d`",
+            ),
+            (
+                "updated<99999999999999999999d",
+                "value out of range `99999999999999999999d`",
+            ),
+            (
+                "dl>99999999999999999m",
+                "value out of range `99999999999999999m`",
+            ),
+            ("dl>lots", "invalid download count `lots`"),
+        ]
+        .iter()
+        {
+            assert_eq!(Query::parse(input).error(), Some(*error), "{}", input);
+        }
+    }
+
+    #[test]
+    fn validate_categories() {
+        let mut query = Query::parse("cat=gui cat=nope");
+        query.validate(&vocabulary());
+        assert_eq!(query.error(), Some("unknown category `nope`"));
+        assert_eq!(query.category().as_deref(), Some("gui"));
+
+        // the built-in categories are used until the vocabulary is fetched
+        let mut query = Query::parse("cat=gui");
+        query.validate(&Vocabulary::default());
+        assert_eq!(query.error(), None);
+    }
+
+    #[test]
+    fn complete_filter_keys() {
+        let mut completion = Completion::new("tui s", &vocabulary()).unwrap();
+        assert_eq!(completion.current(), "tui sort=");
+        completion.advance();
+        assert_eq!(completion.current(), "tui stable");
+        completion.advance();
+        assert_eq!(completion.current(), "tui sort=");
+    }
+
+    #[test]
+    fn complete_filter_values() {
+        let completion = Completion::new("cat=ga", &vocabulary()).unwrap();
+        assert_eq!(completion.current(), "cat=game-development");
+        let completion = Completion::new("tui kw=TE", &vocabulary()).unwrap();
+        assert_eq!(completion.current(), "tui kw=terminal");
+        assert!(Completion::new("user=d", &vocabulary()).is_none());
+        assert!(Completion::new("tui ", &vocabulary()).is_none());
+    }
+
+    #[test]
+    fn complete_after_multibyte_whitespace() {
+        let completion = Completion::new("tui\u{3000}so", &vocabulary()).unwrap();
+        assert_eq!(completion.current(), "tui\u{3000}sort=");
+        let completion = Completion::new("tui\u{a0}cat=g", &vocabulary()).unwrap();
+        assert_eq!(completion.current(), "tui\u{a0}cat=gui");
+        assert!(Completion::new("tui\u{3000}", &vocabulary()).is_none());
+    }
+}
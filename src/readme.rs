@@ -0,0 +1,216 @@
+//! Fetching crate readmes.
+//!
+//! Readmes are primarily sourced from crates.io, which stores the readme
+//! published with each crate version. Repository is only used as a fallback
+//! for crates that didn't publish their readme.
+
+use anyhow::{Error, Result};
+use serde::Deserialize;
+
+use crate::cache::Cache;
+use crate::items::{Crate, Remote};
+use crate::markdown::{self, Rendered};
+use crate::repo::RepoLocation;
+use crate::{api, html, http};
+
+/// Readme file names tried when fetching from the repository.
+const README_FILE_NAMES: [&str; 2] = ["README.md", "readme.md"];
+
+/// Crate readme along with its rendered version.
+#[derive(Debug, Clone)]
+pub(crate) struct Readme {
+    pub markdown: String,
+    pub rendered: Rendered,
+}
+
+impl Readme {
+    /// Creates a new readme, rendering the given markdown.
+    pub fn new(markdown: String) -> Self {
+        Readme {
+            rendered: markdown::render(&markdown),
+            markdown,
+        }
+    }
+}
+
+/// Fetches the readme of the given crate, unless it's already cached.
+pub(crate) fn fetch_readme(item: &Crate, cache: &Cache) -> Result<Readme> {
+    cache
+        .readme(item, |item| {
+            fetch_crates_io_readme(item).or_else(|e| fetch_repo_readme(item).map_err(|_| e))
+        })
+        .map(Readme::new)
+}
+
+#[derive(Deserialize)]
+struct ReadmeUrl {
+    url: String,
+}
+
+/// Fetches the readme published along with the crate's max version.
+fn fetch_crates_io_readme(item: &Crate) -> Result<String> {
+    // asking for json gets us the readme location instead of a redirect
+    let location: ReadmeUrl = http::get_json(
+        &format!(
+            "{}crates/{}/{}/readme",
+            api::BASE_URL,
+            item.id,
+            item.max_version
+        ),
+        &[],
+    )?;
+    let body = http::get(&location.url, &[("Accept", "text/html")])?;
+    Ok(html::to_markdown(&String::from_utf8(body)?))
+}
+
+/// Fetches the readme from the repository, trying the crate's subdirectory
+/// in case the repository is a workspace.
+fn fetch_repo_readme(item: &Crate) -> Result<String> {
+    let repo_url = item
+        .repository
+        .as_ref()
+        .ok_or_else(|| Error::msg("crate doesn't specify a repository"))?;
+    let location = RepoLocation::from_url(repo_url)
+        .ok_or_else(|| Error::msg(format!("unrecognized repository url: {}", repo_url)))?;
+
+    // use the default branch if we already know it, otherwise rely on the
+    // hosts resolving `HEAD` to the default branch
+    let branches = match &item.repo_info {
+        Remote::Ready(info) if info.default_branch.is_some() => {
+            vec![info.default_branch.clone().unwrap()]
+        }
+        _ if location.host == "codeberg.org" => vec!["main".to_string(), "master".to_string()],
+        _ => vec!["HEAD".to_string()],
+    };
+
+    let mut dirs = vec![String::new()];
+    if let Some(subdir) = repo_subdir(repo_url) {
+        dirs.insert(0, format!("{}/", subdir));
+    }
+    dirs.push(format!("{}/", item.name));
+    dirs.push(format!("crates/{}/", item.name));
+
+    for branch in &branches {
+        for dir in &dirs {
+            for file_name in &README_FILE_NAMES {
+                let path = format!("{}{}", dir, file_name);
+                let url = match raw_file_url(&location, branch, &path) {
+                    Some(u) => u,
+                    None => return Err(Error::msg("unsupported repository host")),
+                };
+                if let Ok(body) = http::get(&url, &[("Accept", "text/plain")]) {
+                    return Ok(String::from_utf8(body)?);
+                }
+            }
+        }
+    }
+
+    Err(Error::msg("readme not found"))
+}
+
+/// Creates a url pointing to the raw content of a file in the repository.
+fn raw_file_url(location: &RepoLocation, branch: &str, path: &str) -> Option<String> {
+    let RepoLocation { host, owner, name } = location;
+    if host == "github.com" {
+        Some(format!(
+            "https://raw.githubusercontent.com/{}/{}/{}/{}",
+            owner, name, branch, path
+        ))
+    } else if host.contains("gitlab") {
+        Some(format!(
+            "https://{}/{}/{}/-/raw/{}/{}",
+            host, owner, name, branch, path
+        ))
+    } else if host == "codeberg.org" {
+        Some(format!(
+            "https://{}/{}/{}/raw/branch/{}/{}",
+            host, owner, name, branch, path
+        ))
+    } else {
+        None
+    }
+}
+
+/// Extracts the subdirectory from repository urls pointing inside the
+/// repository, e.g. `https://github.com/owner/repo/tree/master/subdir`.
+fn repo_subdir(repo_url: &str) -> Option<String> {
+    let segments: Vec<&str> = repo_url
+        .trim_end_matches('/')
+        .split('/')
+        .skip_while(|s| !["tree", "blob", "src"].contains(s))
+        .collect();
+    // skip the marker and the branch name, gitea urls also include the ref
+    // type, e.g. `/src/branch/main/subdir`
+    let skip = match segments.get(1) {
+        Some(&"branch") | Some(&"tag") | Some(&"commit") => 3,
+        _ => 2,
+    };
+    match segments.len() > skip {
+        true => Some(segments[skip..].join("/")),
+        false => None,
+    }
+}
+
+/// Extracts the crate name from links pointing to a crate page on crates.io,
+/// e.g. `https://crates.io/crates/serde/1.0.0`.
+pub(crate) fn linked_crate(url: &str) -> Option<&str> {
+    let path = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.")
+        .strip_prefix("crates.io/crates/")?;
+    let name = path.split(['/', '?', '#']).next()?;
+    match name.is_empty() {
+        true => None,
+        false => Some(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_links() {
+        assert_eq!(
+            linked_crate("https://crates.io/crates/serde"),
+            Some("serde")
+        );
+        assert_eq!(
+            linked_crate("https://www.crates.io/crates/serde_json/1.0.0"),
+            Some("serde_json")
+        );
+        assert_eq!(
+            linked_crate("http://crates.io/crates/tokio?tab=readme"),
+            Some("tokio")
+        );
+        assert_eq!(
+            linked_crate("https://crates.io/crates/rand#features"),
+            Some("rand")
+        );
+        assert_eq!(linked_crate("https://crates.io/crates/"), None);
+        assert_eq!(linked_crate("https://crates.io/users/adamsky"), None);
+        assert_eq!(linked_crate("https://docs.rs/crates/serde"), None);
+    }
+
+    #[test]
+    fn repository_subdirectories() {
+        assert_eq!(
+            repo_subdir("https://github.com/owner/repo/tree/master/crates/core/").as_deref(),
+            Some("crates/core")
+        );
+        assert_eq!(
+            repo_subdir("https://gitlab.com/owner/repo/-/tree/main/sub").as_deref(),
+            Some("sub")
+        );
+        assert_eq!(
+            repo_subdir("https://codeberg.org/owner/repo/src/branch/main/sub").as_deref(),
+            Some("sub")
+        );
+        assert_eq!(
+            repo_subdir("https://github.com/owner/repo/tree/master"),
+            None
+        );
+        assert_eq!(repo_subdir("https://github.com/owner/repo"), None);
+    }
+}
@@ -0,0 +1,711 @@
+//! Repository information fetched from the APIs of popular code hosting
+//! services.
+//!
+//! Each supported service is implemented as a separate `RepoHost`. Hosts are
+//! collected in a `RepoHosts` registry which picks the right one based on the
+//! repository url. Every host can be created with a custom base url, which
+//! makes it possible to point it at a different API instance or a local mock
+//! server.
+
+use std::convert::TryFrom;
+use std::ops::Sub;
+
+use anyhow::{Error, Result};
+use chrono::{DateTime, Utc};
+use http_req::uri::Uri;
+use serde::Deserialize;
+
+use crate::http;
+
+/// Repository facts gathered from the hosting service.
+#[derive(Debug, Clone)]
+pub(crate) struct RepoInfo {
+    pub host: String,
+    pub owner: String,
+    pub name: String,
+    pub default_branch: Option<String>,
+    pub stars: u64,
+    pub forks: u64,
+    pub open_issues: Option<u64>,
+    pub last_commit: Option<DateTime<Utc>>,
+    pub archived: bool,
+    pub license: Option<String>,
+}
+
+/// Location of a repository derived from its url.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RepoLocation {
+    /// Domain of the hosting service, e.g. `github.com`
+    pub host: String,
+    /// Owner of the repository, can include subgroups on some hosts
+    pub owner: String,
+    /// Name of the repository
+    pub name: String,
+}
+
+impl RepoLocation {
+    /// Parses a repository url, e.g. `https://github.com/adamsky/cns`.
+    ///
+    /// Trailing `.git` suffixes and paths pointing inside the repository,
+    /// like `/tree/master/subdir`, are ignored.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let uri = Uri::try_from(url.trim()).ok()?;
+        let host = uri.host()?.trim_start_matches("www.").to_lowercase();
+        let mut segments = Vec::new();
+        for segment in uri.path()?.split('/').filter(|s| !s.is_empty()) {
+            if ["-", "tree", "blob", "src"].contains(&segment) {
+                break;
+            }
+            segments.push(segment);
+        }
+        // only gitlab supports nested groups
+        if !host.contains("gitlab") {
+            segments.truncate(2);
+        }
+        if segments.len() < 2 {
+            return None;
+        }
+
+        let name = segments.pop()?.trim_end_matches(".git").to_string();
+        Some(RepoLocation {
+            host,
+            owner: segments.join("/"),
+            name,
+        })
+    }
+}
+
+/// Source of repository information.
+pub(crate) trait RepoHost: Send + Sync {
+    /// Checks whether repositories at the given domain are handled by this
+    /// host.
+    fn handles(&self, host: &str) -> bool;
+    /// Fetches information about the repository at the given location.
+    fn fetch(&self, location: &RepoLocation) -> Result<RepoInfo>;
+}
+
+/// Collection of repository hosts.
+pub(crate) struct RepoHosts {
+    hosts: Vec<Box<dyn RepoHost>>,
+}
+
+impl Default for RepoHosts {
+    /// Creates a registry of all the supported hosts using their public APIs.
+    fn default() -> Self {
+        RepoHosts::new()
+            .with(GitHub::new())
+            .with(GitLab::new())
+            .with(Codeberg::new())
+    }
+}
+
+impl RepoHosts {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        RepoHosts { hosts: Vec::new() }
+    }
+
+    /// Adds a host to the registry. Hosts added earlier take precedence.
+    pub fn with(mut self, host: impl RepoHost + 'static) -> Self {
+        self.hosts.push(Box::new(host));
+        self
+    }
+
+    /// Fetches information about the repository at the given url.
+    pub fn fetch(&self, repo_url: &str) -> Result<RepoInfo> {
+        let location = RepoLocation::from_url(repo_url)
+            .ok_or_else(|| Error::msg(format!("unrecognized repository url: {}", repo_url)))?;
+        let host = self
+            .hosts
+            .iter()
+            .find(|h| h.handles(&location.host))
+            .ok_or_else(|| Error::msg(format!("unsupported repository host: {}", location.host)))?;
+        host.fetch(&location)
+    }
+}
+
+/// GitHub REST API.
+pub(crate) struct GitHub {
+    base_url: String,
+    /// Optional access token, raises the rather strict rate limit
+    token: Option<String>,
+}
+
+impl GitHub {
+    /// Creates a new GitHub host using the public API. Reads an access token
+    /// from the `GITHUB_TOKEN` environment variable if available.
+    pub fn new() -> Self {
+        let mut github = Self::new_with_base_url("https://api.github.com/");
+        github.token = std::env::var("GITHUB_TOKEN").ok();
+        github
+    }
+
+    /// Creates a new GitHub host with the given base url.
+    pub fn new_with_base_url(base_url: &str) -> Self {
+        GitHub {
+            base_url: with_trailing_slash(base_url),
+            token: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    default_branch: Option<String>,
+    stargazers_count: u64,
+    forks_count: u64,
+    open_issues_count: Option<u64>,
+    pushed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    archived: bool,
+    license: Option<GitHubLicense>,
+}
+
+#[derive(Deserialize)]
+struct GitHubLicense {
+    spdx_id: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubCommit {
+    commit: GitHubCommitDetails,
+}
+
+#[derive(Deserialize)]
+struct GitHubCommitDetails {
+    committer: Option<GitHubSignature>,
+}
+
+#[derive(Deserialize)]
+struct GitHubSignature {
+    date: DateTime<Utc>,
+}
+
+impl RepoHost for GitHub {
+    fn handles(&self, host: &str) -> bool {
+        host == "github.com"
+    }
+
+    fn fetch(&self, location: &RepoLocation) -> Result<RepoInfo> {
+        let auth = self.token.as_ref().map(|t| format!("Bearer {}", t));
+        let mut headers = vec![("Accept", "application/vnd.github+json")];
+        if let Some(auth) = &auth {
+            headers.push(("Authorization", auth.as_str()));
+        }
+
+        let repo_url = format!(
+            "{}repos/{}/{}",
+            self.base_url, location.owner, location.name
+        );
+        let repo: GitHubRepo = http::get_json(&repo_url, &headers)?;
+
+        // last push is a decent approximation if the commit can't be fetched
+        let mut last_commit = repo.pushed_at;
+        if let Some(branch) = &repo.default_branch {
+            let commit_url = format!("{}/commits/{}", repo_url, branch);
+            if let Ok(commit) = http::get_json::<GitHubCommit>(&commit_url, &headers) {
+                if let Some(committer) = commit.commit.committer {
+                    last_commit = Some(committer.date);
+                }
+            }
+        }
+
+        Ok(RepoInfo {
+            host: location.host.clone(),
+            owner: location.owner.clone(),
+            name: location.name.clone(),
+            default_branch: repo.default_branch,
+            stars: repo.stargazers_count,
+            forks: repo.forks_count,
+            open_issues: repo.open_issues_count,
+            last_commit,
+            archived: repo.archived,
+            license: repo
+                .license
+                .and_then(|l| l.spdx_id.filter(|id| id != "NOASSERTION").or(l.name)),
+        })
+    }
+}
+
+/// GitLab REST API, supports both gitlab.com and self-hosted instances
+/// living under a `gitlab.` subdomain.
+pub(crate) struct GitLab {
+    /// Base url, if not set it's derived from the repository host
+    base_url: Option<String>,
+}
+
+impl GitLab {
+    /// Creates a new GitLab host using the API of the instance the
+    /// repository lives on.
+    pub fn new() -> Self {
+        GitLab { base_url: None }
+    }
+
+    /// Creates a new GitLab host with the given base url.
+    pub fn new_with_base_url(base_url: &str) -> Self {
+        GitLab {
+            base_url: Some(with_trailing_slash(base_url)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    id: u64,
+    default_branch: Option<String>,
+    star_count: u64,
+    forks_count: u64,
+    open_issues_count: Option<u64>,
+    last_activity_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    archived: bool,
+    license: Option<GitLabLicense>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLicense {
+    key: Option<String>,
+    nickname: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabCommit {
+    committed_date: DateTime<Utc>,
+}
+
+impl RepoHost for GitLab {
+    fn handles(&self, host: &str) -> bool {
+        host == "gitlab.com" || host.starts_with("gitlab.")
+    }
+
+    fn fetch(&self, location: &RepoLocation) -> Result<RepoInfo> {
+        let base_url = self
+            .base_url
+            .clone()
+            .unwrap_or_else(|| format!("https://{}/api/v4/", location.host));
+        let project_path = format!("{}/{}", location.owner, location.name).replace('/', "%2F");
+        let project: GitLabProject = http::get_json(
+            &format!("{}projects/{}?license=true", base_url, project_path),
+            &[],
+        )?;
+
+        let mut last_commit = project.last_activity_at;
+        if let Some(branch) = &project.default_branch {
+            let commit_url = format!(
+                "{}projects/{}/repository/commits/{}",
+                base_url, project.id, branch
+            );
+            if let Ok(commit) = http::get_json::<GitLabCommit>(&commit_url, &[]) {
+                last_commit = Some(commit.committed_date);
+            }
+        }
+
+        Ok(RepoInfo {
+            host: location.host.clone(),
+            owner: location.owner.clone(),
+            name: location.name.clone(),
+            default_branch: project.default_branch,
+            stars: project.star_count,
+            forks: project.forks_count,
+            open_issues: project.open_issues_count,
+            last_commit,
+            archived: project.archived,
+            license: project.license.and_then(|l| {
+                l.key
+                    .filter(|key| key != "other")
+                    .map(|key| spdx_id(&key))
+                    .or(l.name)
+                    .or(l.nickname)
+            }),
+        })
+    }
+}
+
+/// Codeberg API, which is the standard Gitea/Forgejo API.
+pub(crate) struct Codeberg {
+    base_url: String,
+}
+
+impl Codeberg {
+    /// Creates a new Codeberg host using the public API.
+    pub fn new() -> Self {
+        Self::new_with_base_url("https://codeberg.org/api/v1/")
+    }
+
+    /// Creates a new Codeberg host with the given base url.
+    pub fn new_with_base_url(base_url: &str) -> Self {
+        Codeberg {
+            base_url: with_trailing_slash(base_url),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GiteaRepo {
+    default_branch: Option<String>,
+    stars_count: u64,
+    forks_count: u64,
+    open_issues_count: Option<u64>,
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    licenses: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GiteaBranch {
+    commit: GiteaCommit,
+}
+
+#[derive(Deserialize)]
+struct GiteaCommit {
+    timestamp: DateTime<Utc>,
+}
+
+impl RepoHost for Codeberg {
+    fn handles(&self, host: &str) -> bool {
+        host == "codeberg.org"
+    }
+
+    fn fetch(&self, location: &RepoLocation) -> Result<RepoInfo> {
+        let repo_url = format!(
+            "{}repos/{}/{}",
+            self.base_url, location.owner, location.name
+        );
+        let repo: GiteaRepo = http::get_json(&repo_url, &[])?;
+
+        let mut last_commit = repo.updated_at;
+        if let Some(branch) = &repo.default_branch {
+            let branch_url = format!("{}/branches/{}", repo_url, branch);
+            if let Ok(branch) = http::get_json::<GiteaBranch>(&branch_url, &[]) {
+                last_commit = Some(branch.commit.timestamp);
+            }
+        }
+
+        Ok(RepoInfo {
+            host: location.host.clone(),
+            owner: location.owner.clone(),
+            name: location.name.clone(),
+            default_branch: repo.default_branch,
+            stars: repo.stars_count,
+            forks: repo.forks_count,
+            open_issues: repo.open_issues_count,
+            last_commit,
+            archived: repo.archived,
+            license: match repo.licenses.is_empty() {
+                true => None,
+                false => Some(repo.licenses.join(" OR ")),
+            },
+        })
+    }
+}
+
+/// Returns the SPDX identifier for the lowercase license key used by GitLab,
+/// e.g. `Apache-2.0` for `apache-2.0`.
+fn spdx_id(key: &str) -> String {
+    const IDS: [&str; 16] = [
+        "0BSD",
+        "AGPL-3.0",
+        "Apache-2.0",
+        "BSD-2-Clause",
+        "BSD-3-Clause",
+        "BSL-1.0",
+        "CC0-1.0",
+        "EPL-2.0",
+        "GPL-2.0",
+        "GPL-3.0",
+        "ISC",
+        "LGPL-2.1",
+        "LGPL-3.0",
+        "MIT",
+        "MPL-2.0",
+        "Unlicense",
+    ];
+    IDS.iter()
+        .find(|id| id.eq_ignore_ascii_case(key))
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn with_trailing_slash(url: &str) -> String {
+    format!("{}/", url.trim_end_matches('/'))
+}
+
+/// Creates the text content of the repository tab.
+pub(crate) fn create_repo_string(info: &RepoInfo) -> String {
+    let last_commit = match info.last_commit {
+        Some(date) => format!("{} ({} days ago)", date, Utc::now().sub(date).num_days()),
+        None => "n/a".to_string(),
+    };
+    format!(
+        "{}/{}\n\n\n\
+        Host: {}\n\
+        Default branch: {}\n\n\
+        Stars: {}\n\
+        Forks: {}\n\
+        Open issues: {}\n\n\
+        Last commit: {}\n\
+        Archived: {}\n\
+        License: {}\n",
+        info.owner,
+        info.name,
+        info.host,
+        info.default_branch.as_deref().unwrap_or("n/a"),
+        info.stars,
+        info.forks,
+        info.open_issues
+            .map(|n| n.to_string())
+            .unwrap_or("n/a".to_string()),
+        last_commit,
+        if info.archived { "yes" } else { "no" },
+        info.license.as_deref().unwrap_or("n/a"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serves the canned json responses by request path on localhost,
+    /// returning the base url of the server.
+    fn serve(responses: &[(&str, &str)]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        let responses: Vec<(String, String)> = responses
+            .iter()
+            .map(|(path, body)| (path.to_string(), body.to_string()))
+            .collect();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // skip the headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split(' ').nth(1).unwrap_or("");
+                let response = match responses.iter().find(|(p, _)| p == path) {
+                    Some((_, body)) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                        Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+                        Connection: close\r\n\r\n"
+                        .to_string(),
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        base_url
+    }
+
+    fn location(url: &str) -> RepoLocation {
+        RepoLocation::from_url(url).unwrap()
+    }
+
+    #[test]
+    fn location_from_url() {
+        assert_eq!(
+            location("https://github.com/adamsky/cns.git"),
+            RepoLocation {
+                host: "github.com".to_string(),
+                owner: "adamsky".to_string(),
+                name: "cns".to_string(),
+            }
+        );
+        assert_eq!(
+            location("https://www.GitHub.com/adamsky/cns/tree/master/src"),
+            location("https://github.com/adamsky/cns")
+        );
+        let gitlab = location("https://gitlab.com/group/sub/project/-/tree/main");
+        assert_eq!(
+            (gitlab.owner.as_str(), gitlab.name.as_str()),
+            ("group/sub", "project")
+        );
+        let codeberg = location("https://codeberg.org/owner/repo/src/branch/main");
+        assert_eq!(
+            (codeberg.owner.as_str(), codeberg.name.as_str()),
+            ("owner", "repo")
+        );
+        // unknown hosts are parsed the same way, but only the first two
+        // segments are used
+        let unknown = location("https://git.example.org/owner/repo/extra");
+        assert_eq!(
+            (
+                unknown.host.as_str(),
+                unknown.owner.as_str(),
+                unknown.name.as_str()
+            ),
+            ("git.example.org", "owner", "repo")
+        );
+        assert_eq!(RepoLocation::from_url("https://github.com/adamsky"), None);
+        assert_eq!(RepoLocation::from_url("not a url"), None);
+    }
+
+    #[test]
+    fn unsupported_host() {
+        let hosts = RepoHosts::default();
+        let error = hosts
+            .fetch("https://git.example.org/owner/repo")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unsupported repository host: git.example.org"
+        );
+        assert!(GitLab::new().handles("gitlab.gnome.org"));
+        assert!(!GitHub::new().handles("gitlab.com"));
+    }
+
+    #[test]
+    fn fetch_github() {
+        let base_url = serve(&[
+            (
+                "/repos/adamsky/cns",
+                r#"{"default_branch": "master", "stargazers_count": 42,
+                "forks_count": 3, "open_issues_count": 5,
+                "pushed_at": "2024-01-02T03:04:05Z", "archived": false,
+                "license": {"spdx_id": "MIT", "name": "MIT License"}}"#,
+            ),
+            (
+                "/repos/adamsky/cns/commits/master",
+                r#"{"commit": {"committer": {"date": "2024-02-03T04:05:06Z"}}}"#,
+            ),
+        ]);
+        let hosts = RepoHosts::new().with(GitHub::new_with_base_url(&base_url));
+        let info = hosts.fetch("https://github.com/adamsky/cns").unwrap();
+        assert_eq!(info.host, "github.com");
+        assert_eq!(
+            (info.owner.as_str(), info.name.as_str()),
+            ("adamsky", "cns")
+        );
+        assert_eq!(info.default_branch.as_deref(), Some("master"));
+        assert_eq!((info.stars, info.forks, info.open_issues), (42, 3, Some(5)));
+        assert_eq!(
+            info.last_commit.unwrap().to_rfc3339(),
+            "2024-02-03T04:05:06+00:00"
+        );
+        assert!(!info.archived);
+        assert_eq!(info.license.as_deref(), Some("MIT"));
+    }
+
+    #[test]
+    fn fetch_github_without_commit() {
+        let base_url = serve(&[(
+            "/repos/owner/repo",
+            r#"{"default_branch": "main", "stargazers_count": 1, "forks_count": 0,
+            "pushed_at": "2024-01-02T03:04:05Z", "archived": true,
+            "license": {"spdx_id": "NOASSERTION", "name": "Other"}}"#,
+        )]);
+        let info = GitHub::new_with_base_url(&base_url)
+            .fetch(&location("https://github.com/owner/repo"))
+            .unwrap();
+        // the last push is used when the commit can't be fetched
+        assert_eq!(
+            info.last_commit.unwrap().to_rfc3339(),
+            "2024-01-02T03:04:05+00:00"
+        );
+        assert_eq!(info.open_issues, None);
+        assert!(info.archived);
+        assert_eq!(info.license.as_deref(), Some("Other"));
+    }
+
+    #[test]
+    fn fetch_gitlab() {
+        let base_url = serve(&[
+            (
+                "/projects/group%2Fsub%2Fproject?license=true",
+                r#"{"id": 7, "default_branch": "main", "star_count": 12,
+                "forks_count": 2, "open_issues_count": 1,
+                "last_activity_at": "2024-01-02T03:04:05Z",
+                "license": {"key": "apache-2.0", "name": "Apache License 2.0",
+                "nickname": "Apache 2"}}"#,
+            ),
+            (
+                "/projects/7/repository/commits/main",
+                r#"{"committed_date": "2024-02-03T04:05:06Z"}"#,
+            ),
+        ]);
+        let info = GitLab::new_with_base_url(&base_url)
+            .fetch(&location("https://gitlab.com/group/sub/project"))
+            .unwrap();
+        assert_eq!(info.host, "gitlab.com");
+        assert_eq!(info.owner, "group/sub");
+        assert_eq!((info.stars, info.forks, info.open_issues), (12, 2, Some(1)));
+        assert_eq!(
+            info.last_commit.unwrap().to_rfc3339(),
+            "2024-02-03T04:05:06+00:00"
+        );
+        assert!(!info.archived);
+        assert_eq!(info.license.as_deref(), Some("Apache-2.0"));
+    }
+
+    #[test]
+    fn gitlab_license_fallback() {
+        let base_url = serve(&[
+            (
+                "/projects/a%2Fother?license=true",
+                r#"{"id": 1, "star_count": 0, "forks_count": 0,
+                "license": {"key": "other", "name": "Custom License", "nickname": null}}"#,
+            ),
+            (
+                "/projects/a%2Fnone?license=true",
+                r#"{"id": 2, "star_count": 0, "forks_count": 0, "license": null}"#,
+            ),
+        ]);
+        let gitlab = GitLab::new_with_base_url(&base_url);
+        let info = gitlab
+            .fetch(&location("https://gitlab.com/a/other"))
+            .unwrap();
+        assert_eq!(info.license.as_deref(), Some("Custom License"));
+        assert_eq!(info.default_branch, None);
+        let info = gitlab
+            .fetch(&location("https://gitlab.com/a/none"))
+            .unwrap();
+        assert_eq!(info.license, None);
+    }
+
+    #[test]
+    fn fetch_codeberg() {
+        let base_url = serve(&[
+            (
+                "/repos/owner/repo",
+                r#"{"default_branch": "main", "stars_count": 9, "forks_count": 1,
+                "open_issues_count": 4, "updated_at": "2024-01-02T03:04:05Z",
+                "archived": false, "licenses": ["MIT", "Apache-2.0"]}"#,
+            ),
+            (
+                "/repos/owner/repo/branches/main",
+                r#"{"commit": {"timestamp": "2024-02-03T04:05:06Z"}}"#,
+            ),
+        ]);
+        let hosts = RepoHosts::new().with(Codeberg::new_with_base_url(&base_url));
+        let info = hosts.fetch("https://codeberg.org/owner/repo").unwrap();
+        assert_eq!(info.host, "codeberg.org");
+        assert_eq!((info.stars, info.forks, info.open_issues), (9, 1, Some(4)));
+        assert_eq!(
+            info.last_commit.unwrap().to_rfc3339(),
+            "2024-02-03T04:05:06+00:00"
+        );
+        assert_eq!(info.license.as_deref(), Some("MIT OR Apache-2.0"));
+    }
+
+    #[test]
+    fn fetch_missing_repo() {
+        let base_url = serve(&[]);
+        let error = GitHub::new_with_base_url(&base_url)
+            .fetch(&location("https://github.com/owner/missing"))
+            .unwrap_err();
+        assert!(error.to_string().contains("404"), "{}", error);
+    }
+}
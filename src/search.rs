@@ -0,0 +1,104 @@
+//! Crate searches running in the background.
+//!
+//! Each search runs on its own thread and reports back through a channel,
+//! keeping the interface responsive while waiting for crates.io. Searches
+//! are numbered as they're started, results of any search other than the
+//! most recent one are discarded, as are results of cancelled searches.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::items::Crate;
+use crate::pool::catch_panic;
+
+/// Frames of the spinner shown while searching.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Single page of search results.
+#[derive(Debug, Clone)]
+pub(crate) struct Page {
+    pub crates: Vec<Crate>,
+    /// Number of the page, numbering starts at 1
+    pub number: usize,
+    /// Total number of results across all pages
+    pub total: u64,
+    /// Number of results on the page left out by local filters
+    pub filtered: u64,
+}
+
+type Response = (u64, String, Result<Page>);
+
+/// Dispatches searches and collects their results.
+pub(crate) struct Searcher {
+    sender: Sender<Response>,
+    receiver: Receiver<Response>,
+    /// Number of the most recently started search
+    latest: u64,
+    /// Start time of the search in progress
+    started: Option<Instant>,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Searcher {
+            sender,
+            receiver,
+            latest: 0,
+            started: None,
+        }
+    }
+}
+
+impl Searcher {
+    /// Starts a new search in the background, superseding any search still
+    /// in progress.
+    pub fn start<F>(&mut self, input: String, search: F)
+    where
+        F: FnOnce(&str) -> Result<Page> + Send + 'static,
+    {
+        self.latest += 1;
+        self.started = Some(Instant::now());
+        let number = self.latest;
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let result = catch_panic(|| search(&input));
+            // the receiver is gone if the application is already closing
+            let _ = sender.send((number, input, result));
+        });
+    }
+
+    /// Cancels the search in progress, its results will be discarded.
+    pub fn cancel(&mut self) {
+        self.latest += 1;
+        self.started = None;
+    }
+
+    /// Checks whether there's a search in progress.
+    pub fn is_searching(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Returns the results of the most recent search if it has finished,
+    /// along with the input it was started with.
+    pub fn poll(&mut self) -> Option<(String, Result<Page>)> {
+        let mut finished = None;
+        while let Ok((number, input, result)) = self.receiver.try_recv() {
+            if number == self.latest && self.started.is_some() {
+                self.started = None;
+                finished = Some((input, result));
+            }
+        }
+        finished
+    }
+
+    /// Returns the current spinner frame if there's a search in progress.
+    pub fn spinner(&self) -> Option<char> {
+        self.started.map(|started| {
+            let frame = started.elapsed().as_millis() / 100;
+            SPINNER[frame as usize % SPINNER.len()]
+        })
+    }
+}
@@ -0,0 +1,154 @@
+//! Download statistics shown in the stats tab.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use consecrates::Client;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Style};
+use tui::symbols;
+use tui::text::Span;
+use tui::widgets::{Axis, BarChart, Block, Borders, Chart, Dataset, GraphType};
+use tui::Frame;
+
+use crate::api;
+
+/// Number of days covered by the daily downloads chart.
+pub const HISTORY_DAYS: i64 = 90;
+
+/// Download statistics of a single crate.
+#[derive(Debug, Clone)]
+pub(crate) struct Stats {
+    /// Daily downloads across all versions, oldest first
+    pub daily: Vec<(NaiveDate, u64)>,
+    /// All-time downloads per version, newest first
+    pub versions: Vec<(String, u64)>,
+}
+
+/// Fetches download statistics for the given crate.
+pub(crate) fn fetch_stats(client: &Client, crate_id: &str) -> Result<Stats> {
+    let downloads = client.get_crate_downloads(crate_id)?;
+    let versions = api::get_crate_versions(crate_id)?;
+
+    // downloads of less popular versions are only available as a sum
+    let mut per_day: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+    for vd in &downloads.version_downloads {
+        *per_day.entry(vd.date).or_default() += vd.downloads;
+    }
+    for ed in &downloads.meta.extra_downloads {
+        *per_day.entry(ed.date).or_default() += ed.downloads;
+    }
+
+    Ok(Stats {
+        daily: daily_downloads(&per_day, Utc::now().date_naive()),
+        versions: versions.into_iter().map(|v| (v.num, v.downloads)).collect(),
+    })
+}
+
+/// Creates the daily downloads history ending at the given day, filling in
+/// the days without any downloads.
+pub(crate) fn daily_downloads(
+    per_day: &BTreeMap<NaiveDate, u64>,
+    until: NaiveDate,
+) -> Vec<(NaiveDate, u64)> {
+    (0..HISTORY_DAYS)
+        .rev()
+        .map(|n| until - Duration::days(n))
+        .map(|day| (day, per_day.get(&day).cloned().unwrap_or(0)))
+        .collect()
+}
+
+/// Renders the stats charts into the given area.
+pub(crate) fn render<B: Backend>(f: &mut Frame<B>, rect: Rect, stats: &Stats) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+        .split(rect);
+
+    // daily downloads line chart
+    let data: Vec<(f64, f64)> = stats
+        .daily
+        .iter()
+        .enumerate()
+        .map(|(n, (_, dl))| (n as f64, *dl as f64))
+        .collect();
+    let total: u64 = stats.daily.iter().map(|(_, dl)| dl).sum();
+    let max = stats.daily.iter().map(|(_, dl)| *dl).max().unwrap_or(0);
+    let date_label = |n: usize| {
+        stats
+            .daily
+            .get(n)
+            .map(|(date, _)| date.format("%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    let len = stats.daily.len();
+    let chart = Chart::new(vec![Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Yellow))
+        .data(&data)])
+    .block(
+        Block::default()
+            .title(format!(
+                "Daily downloads, last {} days ({} total)",
+                HISTORY_DAYS, total
+            ))
+            .borders(Borders::NONE),
+    )
+    .x_axis(
+        Axis::default()
+            .style(Style::default().fg(Color::DarkGray))
+            .bounds([0., len.saturating_sub(1) as f64])
+            .labels(vec![
+                Span::raw(date_label(0)),
+                Span::raw(date_label(len / 2)),
+                Span::raw(date_label(len.saturating_sub(1))),
+            ]),
+    )
+    .y_axis(
+        Axis::default()
+            .style(Style::default().fg(Color::DarkGray))
+            .bounds([0., max as f64 * 1.1])
+            .labels(vec![
+                Span::raw("0"),
+                Span::raw((max / 2).to_string()),
+                Span::raw(max.to_string()),
+            ]),
+    );
+    f.render_widget(chart, chunks[0]);
+
+    // per-version bar chart, showing as many recent versions as will fit
+    let bar_width = stats
+        .versions
+        .iter()
+        .map(|(num, _)| num.len())
+        .max()
+        .unwrap_or(1)
+        .clamp(5, 12) as u16;
+    let bar_count = (chunks[1].width / (bar_width + 1)) as usize;
+    let bars: Vec<(&str, u64)> = stats
+        .versions
+        .iter()
+        .take(bar_count)
+        .rev()
+        .map(|(num, dl)| (num.as_str(), *dl))
+        .collect();
+    let bar_chart = BarChart::default()
+        .block(
+            Block::default()
+                .title(format!(
+                    "All-time downloads per version ({} versions)",
+                    stats.versions.len()
+                ))
+                .borders(Borders::TOP),
+        )
+        .data(&bars)
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Yellow))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Yellow))
+        .label_style(Style::default().fg(Color::DarkGray));
+    f.render_widget(bar_chart, chunks[1]);
+}
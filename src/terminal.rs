@@ -0,0 +1,60 @@
+//! Terminal setup and guaranteed restore.
+//!
+//! The terminal is switched into raw mode and the alternate screen for the
+//! lifetime of the interface. It's restored when the guard is dropped, which
+//! covers both regular exit and early returns on errors, and also before the
+//! message of a panic on the main thread is printed so that it doesn't end
+//! up mangled on the alternate screen.
+//!
+//! Panics on the background threads leave the interface running, their work
+//! is expected to catch them and report them as errors. Their messages are
+//! still printed as usual.
+
+use std::io;
+use std::thread;
+
+use anyhow::Result;
+use crossterm::{cursor, execute, terminal};
+
+/// Keeps the terminal set up for the interface until dropped.
+pub(crate) struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Sets up the terminal, installing a panic hook restoring it. Has to be
+    /// called from the main thread.
+    pub fn new() -> Result<Self> {
+        let main = thread::current().id();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == main {
+                restore();
+            }
+            default_hook(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        // construct the guard right away so that it cleans up after
+        // a partially successful setup
+        let guard = TerminalGuard;
+        execute!(io::stdout(), terminal::EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+        // the hook can't be replaced while panicking, in which case it
+        // already did its job
+        if !thread::panicking() {
+            let _ = std::panic::take_hook();
+        }
+    }
+}
+
+/// Brings the terminal back to its original state, ignoring any errors as
+/// there's nothing more to be done about them.
+fn restore() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(io::stdout(), terminal::LeaveAlternateScreen, cursor::Show);
+}
@@ -0,0 +1,225 @@
+//! Dependency tree of the selected crate shown in the tree tab.
+//!
+//! The tree is resolved from the sparse index the way cargo would build the
+//! crate with default features, so it shows what adding the crate actually
+//! pulls in. Crates can show up in several places of the tree, each of them
+//! is expanded separately.
+
+use std::collections::HashSet;
+
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::Frame;
+
+use crate::index::Resolve;
+
+/// Visible line of the tree.
+struct Row {
+    /// Packages leading to the row, starting with the root
+    path: Vec<usize>,
+    /// Drawing of the tree branches in front of the package
+    prefix: String,
+}
+
+/// State of the tree tab, reset whenever another crate is selected.
+#[derive(Debug, Default)]
+pub(crate) struct TreeView {
+    /// Crate the state belongs to
+    id: String,
+    /// Paths of the expanded nodes, see `Row::path`
+    expanded: HashSet<Vec<usize>>,
+    /// Line under the cursor
+    cursor: usize,
+    list_state: ListState,
+}
+
+impl TreeView {
+    /// Resets the state if it belongs to another crate. Only the direct
+    /// dependencies are shown at first.
+    pub fn show(&mut self, id: &str) {
+        if self.id != id {
+            *self = TreeView {
+                id: id.to_string(),
+                expanded: HashSet::from([vec![0]]),
+                ..TreeView::default()
+            };
+        }
+    }
+
+    /// Moves the cursor down, stopping at the last visible line.
+    pub fn select_next(&mut self, resolve: &Resolve) {
+        let last = self.rows(resolve).len().saturating_sub(1);
+        self.cursor = (self.cursor + 1).min(last);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Expands the node under the cursor, or collapses it if it's expanded.
+    pub fn toggle(&mut self, resolve: &Resolve) {
+        let rows = self.rows(resolve);
+        if let Some(row) = rows.get(self.cursor.min(rows.len().saturating_sub(1))) {
+            if !self.expanded.remove(&row.path) {
+                self.expanded.insert(row.path.clone());
+            }
+        }
+    }
+
+    /// Flattens the expanded part of the tree into lines.
+    fn rows(&self, resolve: &Resolve) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut stack = vec![(vec![0], String::new(), String::new())];
+        while let Some((path, prefix, indent)) = stack.pop() {
+            if self.expanded.contains(&path) {
+                let dependencies = &resolve.packages[*path.last().unwrap()].dependencies;
+                // pushed in reverse to come off the stack in order
+                for (n, dep) in dependencies.iter().enumerate().rev() {
+                    let last = n == dependencies.len() - 1;
+                    let mut child = path.clone();
+                    child.push(*dep);
+                    stack.push((
+                        child,
+                        format!("{}{}", indent, if last { "└── " } else { "├── " }),
+                        format!("{}{}", indent, if last { "    " } else { "│   " }),
+                    ));
+                }
+            }
+            rows.push(Row { path, prefix });
+        }
+        rows
+    }
+}
+
+/// Renders the tree with the unique crate count above it.
+pub(crate) fn render<B: Backend>(
+    f: &mut Frame<B>,
+    rect: Rect,
+    resolve: &Resolve,
+    view: &mut TreeView,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
+        .split(rect);
+
+    let dimmed = Style::default().fg(Color::DarkGray);
+    let direct = resolve.packages[0].dependencies.len();
+    let total = resolve.packages.len() - 1;
+    let summary = Spans::from(vec![
+        Span::raw(format!(
+            "{} unique crates, {} direct dependencies",
+            total, direct
+        )),
+        Span::styled("  <J/K> move | <Space> expand", dimmed),
+    ]);
+    f.render_widget(
+        Paragraph::new(summary).block(Block::default().borders(Borders::NONE)),
+        chunks[0],
+    );
+
+    let rows = view.rows(resolve);
+    view.cursor = view.cursor.min(rows.len().saturating_sub(1));
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let package = &resolve.packages[*row.path.last().unwrap()];
+            let marker = match package.dependencies.is_empty() {
+                true => "  ",
+                false if view.expanded.contains(&row.path) => "- ",
+                false => "+ ",
+            };
+            let mut spans = vec![
+                Span::styled(row.prefix.clone(), dimmed),
+                Span::raw(format!("{}{} {}", marker, package.name, package.version)),
+            ];
+            if !package.features.is_empty() {
+                let features: Vec<&str> = package.features.iter().map(|f| f.as_str()).collect();
+                spans.push(Span::styled(format!(" [{}]", features.join(", ")), dimmed));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    view.list_state.select(Some(view.cursor));
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    f.render_stateful_widget(list, chunks[1], &mut view.list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Package;
+
+    /// Creates a graph where the root depends on `a` and `b`, both of which
+    /// depend on `c`.
+    fn resolve() -> Resolve {
+        let package = |name: &str, dependencies: Vec<usize>| Package {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            dependencies,
+            features: Default::default(),
+        };
+        Resolve {
+            packages: vec![
+                package("root", vec![1, 2]),
+                package("a", vec![3]),
+                package("b", vec![3]),
+                package("c", vec![]),
+            ],
+        }
+    }
+
+    fn lines(view: &TreeView, resolve: &Resolve) -> Vec<String> {
+        view.rows(resolve)
+            .iter()
+            .map(|row| {
+                let package = &resolve.packages[*row.path.last().unwrap()];
+                format!("{}{}", row.prefix, package.name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rows_and_prefixes() {
+        let resolve = resolve();
+        let mut view = TreeView::default();
+        view.show("root");
+        assert_eq!(lines(&view, &resolve), ["root", "├── a", "└── b"]);
+
+        view.expanded.insert(vec![0, 1]);
+        view.expanded.insert(vec![0, 2]);
+        assert_eq!(
+            lines(&view, &resolve),
+            ["root", "├── a", "│   └── c", "└── b", "    └── c"]
+        );
+        let paths: Vec<Vec<usize>> = view.rows(&resolve).into_iter().map(|r| r.path).collect();
+        assert_eq!(paths[2], [0, 1, 3]);
+        assert_eq!(paths[4], [0, 2, 3]);
+    }
+
+    #[test]
+    fn toggle_and_cursor() {
+        let resolve = resolve();
+        let mut view = TreeView::default();
+        view.show("root");
+        view.select_next(&resolve);
+        view.toggle(&resolve);
+        assert_eq!(lines(&view, &resolve).len(), 4);
+
+        for _ in 0..10 {
+            view.select_next(&resolve);
+        }
+        assert_eq!(view.cursor, 3);
+        view.select_previous();
+        view.select_previous();
+        view.toggle(&resolve);
+        assert_eq!(lines(&view, &resolve).len(), 3);
+
+        view.show("other");
+        assert_eq!(view.cursor, 0);
+        assert_eq!(lines(&view, &resolve).len(), 3);
+    }
+}
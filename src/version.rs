@@ -0,0 +1,345 @@
+//! Crate version handling.
+
+use std::fmt;
+
+/// Semantic version, ordered by precedence.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SemVer {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Releases have precedence over pre-releases of the same version
+    pub release: bool,
+    pub pre: Vec<PreIdentifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PreIdentifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl SemVer {
+    /// Parses the version leniently, missing or invalid parts are zero.
+    pub fn parse(s: &str) -> SemVer {
+        let s = s.split('+').next().unwrap_or(s);
+        let (version, pre) = match s.split_once('-') {
+            Some((v, p)) => (v, Some(p)),
+            None => (s, None),
+        };
+        let mut parts = version.split('.').map(|p| p.parse().unwrap_or(0));
+        SemVer {
+            major: parts.next().unwrap_or(0),
+            minor: parts.next().unwrap_or(0),
+            patch: parts.next().unwrap_or(0),
+            release: pre.is_none(),
+            pre: pre
+                .map(|p| {
+                    p.split('.')
+                        .map(|i| match i.parse() {
+                            Ok(n) => PreIdentifier::Numeric(n),
+                            Err(_) => PreIdentifier::Alphanumeric(i.to_string()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (n, identifier) in self.pre.iter().enumerate() {
+            let separator = if n == 0 { '-' } else { '.' };
+            match identifier {
+                PreIdentifier::Numeric(i) => write!(f, "{}{}", separator, i)?,
+                PreIdentifier::Alphanumeric(i) => write!(f, "{}{}", separator, i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Version requirement as written in `Cargo.toml`, e.g. `1.2` or
+/// `>=0.3, <0.5`.
+#[derive(Debug, Clone)]
+pub(crate) struct VersionReq {
+    /// All of the comparators need to match, no comparators match any
+    /// version
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+#[derive(Debug, Clone)]
+struct Comparator {
+    op: Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<PreIdentifier>,
+}
+
+impl VersionReq {
+    /// Parses the requirement, returns `None` if it's invalid.
+    pub fn parse(s: &str) -> Option<VersionReq> {
+        let s = s.trim();
+        if s == "*" || s.is_empty() {
+            return Some(VersionReq {
+                comparators: Vec::new(),
+            });
+        }
+        let comparators = s
+            .split(',')
+            .map(|c| Comparator::parse(c.trim()))
+            .collect::<Option<_>>()?;
+        Some(VersionReq { comparators })
+    }
+
+    /// Checks whether the version satisfies the requirement. Pre-releases
+    /// only match comparators naming a pre-release of the same version.
+    pub fn matches(&self, version: &SemVer) -> bool {
+        if !self.comparators.iter().all(|c| c.matches(version)) {
+            return false;
+        }
+        version.release
+            || self.comparators.iter().any(|c| {
+                !c.pre.is_empty()
+                    && c.major == version.major
+                    && c.minor == Some(version.minor)
+                    && c.patch == Some(version.patch)
+            })
+    }
+}
+
+impl Comparator {
+    fn parse(s: &str) -> Option<Comparator> {
+        let (op, rest) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .iter()
+        .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|rest| (*op, rest.trim())))
+        .unwrap_or((Op::Caret, s));
+
+        let (version, pre) = match rest.split('+').next()?.split_once('-') {
+            Some((v, p)) => (v, SemVer::parse(&format!("0.0.0-{}", p)).pre),
+            None => (rest.split('+').next()?, Vec::new()),
+        };
+        let wildcard = |p: &&str| matches!(*p, "*" | "x" | "X");
+        let mut parts = version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let mut part = || -> Option<Option<u64>> {
+            match parts.next() {
+                None => Some(None),
+                Some(p) if wildcard(&p) => Some(None),
+                Some(p) => p.parse().ok().map(Some),
+            }
+        };
+        let minor = part()?;
+        let patch = match minor {
+            Some(_) => part()?,
+            None => None,
+        };
+        // wildcards behave like exact requirements with the parts missing
+        let op = match (op, version.contains(['*', 'x', 'X'])) {
+            (Op::Caret, true) => Op::Exact,
+            (op, _) => op,
+        };
+        Some(Comparator {
+            op,
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    fn matches(&self, version: &SemVer) -> bool {
+        let release = |major, minor, patch| SemVer {
+            major,
+            minor,
+            patch,
+            release: true,
+            pre: Vec::new(),
+        };
+        let base = SemVer {
+            major: self.major,
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            release: self.pre.is_empty(),
+            pre: self.pre.clone(),
+        };
+        // first version past the range given by the parts present
+        let next = match (self.minor, self.patch) {
+            (Some(minor), Some(patch)) => release(self.major, minor, patch + 1),
+            (Some(minor), None) => release(self.major, minor + 1, 0),
+            (None, _) => release(self.major + 1, 0, 0),
+        };
+        let full = self.patch.is_some();
+        match self.op {
+            Op::Exact if full => *version == base,
+            Op::Exact => *version >= base && *version < next,
+            Op::Greater if full => *version > base,
+            Op::Greater => *version >= next,
+            Op::GreaterEq => *version >= base,
+            Op::Less => *version < base,
+            Op::LessEq if full => *version <= base,
+            Op::LessEq => *version < next,
+            Op::Tilde => {
+                let upper = match self.minor {
+                    Some(minor) => release(self.major, minor + 1, 0),
+                    None => release(self.major + 1, 0, 0),
+                };
+                *version >= base && *version < upper
+            }
+            Op::Caret => {
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => release(0, 0, patch + 1),
+                    (0, Some(minor), _) => release(0, minor + 1, 0),
+                    (major, _, _) => release(major + 1, 0, 0),
+                };
+                *version >= base && *version < upper
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(req: &str, version: &str) -> bool {
+        VersionReq::parse(req)
+            .unwrap()
+            .matches(&SemVer::parse(version))
+    }
+
+    fn check(req: &str, matching: &[&str], not_matching: &[&str]) {
+        for version in matching {
+            assert!(matches(req, version), "{} should match {}", req, version);
+        }
+        for version in not_matching {
+            assert!(
+                !matches(req, version),
+                "{} shouldn't match {}",
+                req,
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn parse_and_order() {
+        let version = SemVer::parse("1.2.3-alpha.1+build");
+        assert_eq!(version.to_string(), "1.2.3-alpha.1");
+        assert!(!version.release);
+        let mut versions: Vec<SemVer> = [
+            "1.0.0",
+            "1.0.0-beta",
+            "1.0.0-alpha.1",
+            "0.9.10",
+            "1.0.0-alpha",
+            "0.9.9",
+            "1.0.0-alpha.beta",
+        ]
+        .iter()
+        .map(|v| SemVer::parse(v))
+        .collect();
+        versions.sort();
+        let sorted: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            sorted,
+            [
+                "0.9.9",
+                "0.9.10",
+                "1.0.0-alpha",
+                "1.0.0-alpha.1",
+                "1.0.0-alpha.beta",
+                "1.0.0-beta",
+                "1.0.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn caret() {
+        check("1.2.3", &["1.2.3", "1.9.0"], &["1.2.2", "2.0.0"]);
+        check("^1.2", &["1.2.0", "1.99.0"], &["1.1.9", "2.0.0"]);
+        check("^1", &["1.0.0", "1.5.0"], &["0.9.0", "2.0.0"]);
+    }
+
+    #[test]
+    fn caret_zero_major() {
+        check("0.2.3", &["0.2.3", "0.2.9"], &["0.2.2", "0.3.0"]);
+        check("^0.2", &["0.2.0", "0.2.9"], &["0.1.9", "0.3.0"]);
+        check("^0.0.3", &["0.0.3"], &["0.0.2", "0.0.4"]);
+        check("^0.0", &["0.0.0", "0.0.9"], &["0.1.0"]);
+        check("^0", &["0.0.1", "0.9.0"], &["1.0.0"]);
+    }
+
+    #[test]
+    fn tilde() {
+        check("~1.2.3", &["1.2.3", "1.2.9"], &["1.2.2", "1.3.0"]);
+        check("~1.2", &["1.2.0", "1.2.9"], &["1.3.0"]);
+        check("~1", &["1.0.0", "1.9.0"], &["2.0.0"]);
+        check("~0.3.1", &["0.3.1", "0.3.5"], &["0.4.0"]);
+    }
+
+    #[test]
+    fn wildcard() {
+        check("*", &["0.0.1", "5.0.0"], &["1.0.0-alpha"]);
+        check("", &["1.0.0"], &[]);
+        check("1.*", &["1.0.0", "1.9.9"], &["0.9.0", "2.0.0"]);
+        check("1.2.x", &["1.2.0", "1.2.9"], &["1.3.0"]);
+    }
+
+    #[test]
+    fn comparison() {
+        check("=1.2.3", &["1.2.3"], &["1.2.4", "1.2.2"]);
+        check("=1.2", &["1.2.0", "1.2.7"], &["1.3.0"]);
+        check(">1.2.3", &["1.2.4", "2.0.0"], &["1.2.3"]);
+        check(">1.2", &["1.3.0"], &["1.2.9"]);
+        check(">=1.2", &["1.2.0", "3.0.0"], &["1.1.9"]);
+        check("<1.2.3", &["1.2.2", "0.1.0"], &["1.2.3"]);
+        check("<=1.2.3", &["1.2.3"], &["1.2.4"]);
+        check("<=1.2", &["1.2.9"], &["1.3.0"]);
+        check("< 2", &["1.9.9"], &["2.0.0"]);
+    }
+
+    #[test]
+    fn comma_separated() {
+        check(">=0.3, <0.5", &["0.3.0", "0.4.9"], &["0.2.9", "0.5.0"]);
+        assert!(VersionReq::parse(">= 1.2, < 1.5, !=").is_none());
+        assert!(VersionReq::parse("foo").is_none());
+    }
+
+    #[test]
+    fn pre_releases() {
+        // pre-releases are only matched when asked for explicitly
+        check("^1.0", &["1.0.0"], &["1.1.0-alpha", "1.0.0-rc.1"]);
+        check(">=1.0.0, <2.0.0", &[], &["1.5.0-beta"]);
+        check("<1.2.3", &["1.2.2"], &["1.2.3-alpha"]);
+        check(
+            "^1.2.3-alpha.2",
+            &["1.2.3-alpha.2", "1.2.3-beta", "1.2.3", "1.5.0"],
+            &["1.2.3-alpha.1", "1.3.0-alpha", "2.0.0"],
+        );
+        check("=1.0.0-rc.1", &["1.0.0-rc.1"], &["1.0.0-rc.2", "1.0.0"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Version history shown in the versions tab.
+
+use chrono::{DateTime, Duration, Utc};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::Spans;
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table};
+use tui::Frame;
+
+use crate::api::Version;
+
+/// Returns the average time between releases, `None` if there's only one.
+fn release_interval(versions: &[Version]) -> Option<Duration> {
+    let first = versions.iter().map(|v| v.created_at).min()?;
+    let last = versions.iter().map(|v| v.created_at).max()?;
+    match versions.len() {
+        0 | 1 => None,
+        n => Some((last - first) / (n as i32 - 1)),
+    }
+}
+
+/// Formats the size in bytes using the largest fitting unit.
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MiB", b as f64 / (1024. * 1024.)),
+        b if b >= 1024 => format!("{:.1} KiB", b as f64 / 1024.),
+        b => format!("{} B", b),
+    }
+}
+
+/// Renders the release summary and the table of versions into the given
+/// area, skipping the given number of versions from the top.
+pub(crate) fn render<B: Backend>(
+    f: &mut Frame<B>,
+    rect: Rect,
+    versions: &[Version],
+    scroll: usize,
+    now: DateTime<Utc>,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+        .split(rect);
+
+    let yanked = versions.iter().filter(|v| v.yanked).count();
+    let last_year = versions
+        .iter()
+        .filter(|v| now - v.created_at < Duration::days(365))
+        .count();
+    let interval = match release_interval(versions) {
+        Some(interval) => format!("a release every {} days on average", interval.num_days()),
+        None => "a single release".to_string(),
+    };
+    let summary = vec![
+        Spans::from(format!(
+            "{} versions, {} yanked, {} in the last year",
+            versions.len(),
+            yanked,
+            last_year
+        )),
+        Spans::from(interval),
+    ];
+    f.render_widget(
+        Paragraph::new(summary).block(Block::default().borders(Borders::NONE)),
+        chunks[0],
+    );
+
+    let na = || "n/a".to_string();
+    let header = Row::new(
+        [
+            "Version",
+            "Published",
+            "By",
+            "MSRV",
+            "Size",
+            "License",
+            "Downloads",
+        ]
+        .iter()
+        .map(|t| Cell::from(*t).style(Style::default().add_modifier(Modifier::BOLD))),
+    );
+    let rows: Vec<Row> = versions
+        .iter()
+        .skip(scroll)
+        .map(|v| {
+            let row = Row::new(vec![
+                match v.yanked {
+                    true => format!("{} yanked", v.num),
+                    false => v.num.clone(),
+                },
+                v.created_at.format("%Y-%m-%d").to_string(),
+                v.published_by
+                    .as_ref()
+                    .map(|p| p.login.clone())
+                    .unwrap_or_else(na),
+                v.rust_version.clone().unwrap_or_else(na),
+                v.crate_size.map(format_size).unwrap_or_else(na),
+                v.license.clone().unwrap_or_else(na),
+                v.downloads.to_string(),
+            ]);
+            match v.yanked {
+                true => row.style(Style::default().fg(Color::Red)),
+                false => row,
+            }
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(18),
+        Constraint::Length(10),
+        Constraint::Length(16),
+        Constraint::Length(6),
+        Constraint::Length(9),
+        Constraint::Length(16),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::NONE))
+        .widths(&widths)
+        .column_spacing(1);
+    f.render_widget(table, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn version(num: &str, day: u32) -> Version {
+        Version {
+            id: 0,
+            num: num.to_string(),
+            created_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            downloads: 0,
+            yanked: false,
+            rust_version: None,
+            published_by: None,
+            crate_size: None,
+            license: None,
+        }
+    }
+
+    #[test]
+    fn release_intervals() {
+        assert_eq!(release_interval(&[]), None);
+        assert_eq!(release_interval(&[version("1.0.0", 1)]), None);
+        // the order of the versions doesn't matter
+        let versions = [
+            version("1.2.0", 31),
+            version("1.0.0", 1),
+            version("1.1.0", 11),
+        ];
+        assert_eq!(release_interval(&versions), Some(Duration::days(15)));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 + 1024 * 1024 / 10), "3.1 MiB");
+    }
+}
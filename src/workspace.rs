@@ -0,0 +1,267 @@
+//! Cargo project the application is run in.
+//!
+//! When started inside a project, the crates it already depends on are
+//! marked in the results along with the versions locked in `Cargo.lock`.
+//! Dependencies of all the workspace members are taken into account.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::items::Crate;
+use crate::manifest::{self, MANIFEST};
+use crate::version::SemVer;
+
+/// Dependencies of the project in the working directory.
+#[derive(Debug, Default)]
+pub(crate) struct Workspace {
+    dependencies: HashSet<String>,
+    /// Locked versions of each package, there can be several of them
+    locked: HashMap<String, Vec<String>>,
+}
+
+impl Workspace {
+    /// Loads the project the given directory is part of, `None` if it
+    /// isn't part of any.
+    pub fn load(dir: &Path) -> Option<Workspace> {
+        let nearest = manifest::find(dir)?;
+        // the workspace root is the closest manifest with a `[workspace]`
+        // table, which is where the lock file is
+        let root_manifest = nearest
+            .parent()?
+            .ancestors()
+            .map(|d| d.join(MANIFEST))
+            .filter(|path| path.is_file())
+            .find(|path| {
+                fs::read_to_string(path)
+                    .ok()
+                    .and_then(|m| manifest::workspace_members(&m))
+                    .is_some()
+            })
+            .unwrap_or(nearest);
+        let root = root_manifest.parent()?.to_path_buf();
+
+        let contents = fs::read_to_string(&root_manifest).ok()?;
+        let excluded: Vec<PathBuf> = manifest::workspace_excludes(&contents)
+            .iter()
+            .map(|path| root.join(path))
+            .collect();
+        let mut manifests = vec![contents.clone()];
+        for pattern in manifest::workspace_members(&contents).unwrap_or_default() {
+            for member in expand(&root, &pattern) {
+                if excluded.iter().any(|path| member.starts_with(path)) {
+                    continue;
+                }
+                if let Ok(contents) = fs::read_to_string(member.join(MANIFEST)) {
+                    manifests.push(contents);
+                }
+            }
+        }
+        let dependencies = manifests
+            .iter()
+            .flat_map(|m| manifest::dependency_names(m))
+            .collect();
+        let locked = fs::read_to_string(root.join("Cargo.lock"))
+            .map(|lock| parse_lock(&lock))
+            .unwrap_or_default();
+
+        Some(Workspace {
+            dependencies,
+            locked,
+        })
+    }
+
+    pub fn depends_on(&self, name: &str) -> bool {
+        self.dependencies.contains(name)
+    }
+
+    /// Returns the highest locked version of the crate.
+    pub fn locked_version(&self, name: &str) -> Option<&str> {
+        self.locked
+            .get(name)?
+            .iter()
+            .max_by_key(|v| SemVer::parse(v))
+            .map(|v| v.as_str())
+    }
+
+    /// Checks whether the locked version of the crate is older than its
+    /// max version.
+    pub fn is_behind(&self, item: &Crate) -> bool {
+        self.locked_version(&item.id)
+            .is_some_and(|locked| SemVer::parse(locked) < SemVer::parse(&item.max_version))
+    }
+
+    /// Describes how the project depends on the crate, `None` if it
+    /// doesn't.
+    pub fn status(&self, item: &Crate) -> Option<String> {
+        if !self.depends_on(&item.id) {
+            return None;
+        }
+        Some(match self.locked_version(&item.id) {
+            Some(locked) if self.is_behind(item) => format!("locked {}, behind", locked),
+            Some(locked) => format!("locked {}", locked),
+            None => "dependency".to_string(),
+        })
+    }
+}
+
+/// Reads the names and versions of the packages in the lock file.
+fn parse_lock(lock: &str) -> HashMap<String, Vec<String>> {
+    let mut locked: HashMap<String, Vec<String>> = HashMap::new();
+    let mut name = None;
+    for line in lock.lines() {
+        let line = line.trim();
+        if line == "[[package]]" {
+            name = None;
+        } else if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"').to_string());
+        } else if let Some(value) = line.strip_prefix("version = ") {
+            if let Some(name) = name.take() {
+                locked
+                    .entry(name)
+                    .or_default()
+                    .push(value.trim_matches('"').to_string());
+            }
+        }
+    }
+    locked
+}
+
+/// Finds the member directories matching the pattern, where `*` in a path
+/// component matches any part of a directory name.
+fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        dirs = match component.contains('*') {
+            true => dirs
+                .iter()
+                .filter_map(|dir| fs::read_dir(dir).ok())
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter(|entry| wildcard_match(component, &entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect(),
+            false => dirs.iter().map(|dir| dir.join(component)).collect(),
+        };
+    }
+    dirs
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (n, part) in parts.iter().enumerate() {
+        if n == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dirs;
+    use crate::items::test_crate;
+
+    const LOCK: &str = "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.100\"\n\n[[package]]\nname = \"rand\"\nversion = \"0.7.3\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"rand\"\nversion = \"0.8.5\"\ndependencies = [\n \"libc\",\n]\n";
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn lock_versions() {
+        let locked = parse_lock(LOCK);
+        assert_eq!(locked["serde"], vec!["1.0.100"]);
+        assert_eq!(locked["rand"], vec!["0.7.3", "0.8.5"]);
+        assert_eq!(locked.len(), 2);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("cns-*", "cns-core"));
+        assert!(wildcard_match("*-core", "cns-core"));
+        assert!(wildcard_match("a*b*c", "a-b-c"));
+        assert!(wildcard_match("exact", "exact"));
+        assert!(!wildcard_match("exact", "exactly"));
+        assert!(!wildcard_match("cns-*", "core"));
+        assert!(!wildcard_match("a*b*c", "a-c-b"));
+    }
+
+    #[test]
+    fn member_patterns() {
+        let root = dirs::test_dir("workspace-expand");
+        for dir in ["crates/a", "crates/b", "crates/other", "app"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        write(&root.join("crates/file"), "");
+
+        let mut found = expand(&root, "crates/*");
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                root.join("crates/a"),
+                root.join("crates/b"),
+                root.join("crates/other")
+            ]
+        );
+        assert_eq!(expand(&root, "./app"), vec![root.join("app")]);
+        assert!(expand(&root, "missing/*").is_empty());
+    }
+
+    #[test]
+    fn load_members_and_status() {
+        let root = dirs::test_dir("workspace-load");
+        write(
+            &root.join(MANIFEST),
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]\n\n[workspace.dependencies]\nregex = \"1\"\n",
+        );
+        write(
+            &root.join("crates/a").join(MANIFEST),
+            "[package]\nname = \"a\"\n\n[dependencies]\nserde = \"1\"\n",
+        );
+        write(
+            &root.join("crates/b").join(MANIFEST),
+            "[package]\nname = \"b\"\n\n[dev-dependencies]\nrand = \"0.8\"\ntokio = \"1\"\n",
+        );
+        write(
+            &root.join("crates/old").join(MANIFEST),
+            "[package]\nname = \"old\"\n\n[dependencies]\nlog = \"0.4\"\n",
+        );
+        write(&root.join("Cargo.lock"), LOCK);
+
+        // loaded from within a member, the whole workspace is found
+        let workspace = Workspace::load(&root.join("crates/a/src")).unwrap();
+        assert!(workspace.depends_on("serde"));
+        assert!(workspace.depends_on("rand"));
+        assert!(!workspace.depends_on("log"));
+        assert!(!workspace.depends_on("regex"));
+        assert_eq!(workspace.locked_version("rand"), Some("0.8.5"));
+
+        let status = |name: &str, version: &str| workspace.status(&test_crate(name, version));
+        assert_eq!(
+            status("serde", "1.0.100").as_deref(),
+            Some("locked 1.0.100")
+        );
+        assert_eq!(
+            status("rand", "0.9.0").as_deref(),
+            Some("locked 0.8.5, behind")
+        );
+        assert_eq!(status("tokio", "1.0.0").as_deref(), Some("dependency"));
+        assert_eq!(status("log", "0.4.0"), None);
+    }
+}